@@ -0,0 +1,43 @@
+#import bevy_sprite::mesh2d_vertex_output::VertexOutput
+
+struct PointLight {
+    color: vec4<f32>,
+    position: vec2<f32>,
+    radius: f32,
+    intensity: f32,
+}
+
+struct Lighting {
+    ambient: f32,
+    count: u32,
+    // Must match `MAX_LIGHTS` in `src/lighting/mod.rs`
+    lights: array<PointLight, 32>,
+}
+
+@group(2) @binding(0) var<uniform> lighting: Lighting;
+
+// How strongly the lights tint the darkness around them
+const GLOW: f32 = 0.25;
+
+@fragment
+fn fragment(in: VertexOutput) -> @location(0) vec4<f32> {
+    var light = vec3<f32>(0.0);
+    for (var i = 0u; i < lighting.count; i++) {
+        let point_light = lighting.lights[i];
+        let light_distance = distance(in.world_position.xy, point_light.position);
+        let falloff = clamp(1.0 - light_distance / point_light.radius, 0.0, 1.0);
+        light += point_light.color.rgb * point_light.intensity * falloff * falloff;
+    }
+
+    let strongest = max(light.r, max(light.g, light.b));
+    let brightness = clamp(strongest, 0.0, 1.0);
+    let darkness = (1.0 - lighting.ambient) * (1.0 - brightness);
+    let glow = (1.0 - lighting.ambient) * brightness * GLOW;
+    let alpha = max(darkness, glow);
+    if alpha <= 0.0 {
+        return vec4<f32>(0.0);
+    }
+    // The darkness is black, the glow has the color of the lights
+    let tint = light / max(strongest, 0.0001);
+    return vec4<f32>(tint * (glow / alpha), alpha);
+}
@@ -0,0 +1,175 @@
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+
+/// The version of the achievement progress written by this build.
+pub const PROGRESS_VERSION: u32 = 1;
+
+/// The gameplay events achievements are evaluated on.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum GameEvent {
+    Collect,
+    Jump,
+    Death,
+    Drown,
+    LevelComplete,
+}
+
+/// When an achievement is unlocked.
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+pub enum Condition {
+    /// The event happened this many times in total
+    Count { event: GameEvent, count: u32 },
+    /// A level was completed, any level when none is given
+    CompleteLevel { level: Option<String> },
+    /// A level was completed without the event happening since entering it
+    CompleteLevelWithout {
+        event: GameEvent,
+        level: Option<String>,
+    },
+    /// A level was completed in at most this many seconds
+    CompleteLevelWithin { seconds: f32, level: Option<String> },
+}
+
+/// A level completed in this frame.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Completion<'a> {
+    /// The identifier of the level
+    pub level: &'a str,
+    pub time: f32,
+}
+
+impl Condition {
+    /// Returns whether the condition is met, given the events in total, since entering the level
+    /// and the level completed in this frame.
+    pub fn is_met(
+        &self,
+        totals: &BTreeMap<GameEvent, u32>,
+        visit: &HashMap<GameEvent, u32>,
+        completion: Option<Completion>,
+    ) -> bool {
+        let completed = |level: &Option<String>| {
+            completion.filter(|completion| {
+                level
+                    .as_ref()
+                    .map_or(true, |level| level == completion.level)
+            })
+        };
+        match self {
+            Condition::Count { event, count } => totals.get(event).copied().unwrap_or(0) >= *count,
+            Condition::CompleteLevel { level } => completed(level).is_some(),
+            Condition::CompleteLevelWithout { event, level } => {
+                completed(level).is_some() && visit.get(event).copied().unwrap_or(0) == 0
+            }
+            Condition::CompleteLevelWithin { seconds, level } => {
+                completed(level).is_some_and(|completion| completion.time <= *seconds)
+            }
+        }
+    }
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct Achievement {
+    pub id: String,
+    pub name: String,
+    pub description: String,
+    pub condition: Condition,
+}
+
+/// The achievements loaded from an `.achievements.ron` file.
+#[derive(Asset, TypePath, Deserialize, Debug, Deref)]
+pub struct Achievements(pub Vec<Achievement>);
+
+/// The handle to the loaded achievements.
+#[derive(Resource, Debug, Deref)]
+pub struct AchievementsHandle(pub Handle<Achievements>);
+
+/// The events counted for achievements and the unlocked achievements, saved between sessions.
+#[derive(Resource, Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+pub struct AchievementProgress {
+    pub version: u32,
+    pub counters: BTreeMap<GameEvent, u32>,
+    /// The ids of the unlocked achievements
+    pub unlocked: BTreeSet<String>,
+}
+
+/// The events since the player entered the current level.
+#[derive(Resource, Debug, Default)]
+pub struct VisitEvents {
+    /// The iid of the level
+    pub level: Option<String>,
+    pub counters: HashMap<GameEvent, u32>,
+}
+
+/// Sent when an achievement is unlocked.
+#[derive(Event, Debug, Clone)]
+pub struct AchievementUnlocked {
+    pub name: String,
+    pub description: String,
+}
+
+#[derive(Component)]
+pub struct AchievementsScreen;
+
+#[derive(Component)]
+pub struct BackButton;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn completion(level: &str, time: f32) -> Option<Completion<'_>> {
+        Some(Completion { level, time })
+    }
+
+    #[test]
+    fn counts_events_in_total() {
+        let condition = Condition::Count {
+            event: GameEvent::Collect,
+            count: 100,
+        };
+        let mut totals = BTreeMap::from([(GameEvent::Collect, 99)]);
+        assert!(!condition.is_met(&totals, &HashMap::new(), None));
+        totals.insert(GameEvent::Collect, 100);
+        assert!(condition.is_met(&totals, &HashMap::new(), None));
+    }
+
+    #[test]
+    fn completes_the_given_level() {
+        let condition = Condition::CompleteLevel {
+            level: Some("Level_0".to_string()),
+        };
+        let (totals, visit) = (BTreeMap::new(), HashMap::new());
+        assert!(!condition.is_met(&totals, &visit, None));
+        assert!(!condition.is_met(&totals, &visit, completion("Level_1", 1.)));
+        assert!(condition.is_met(&totals, &visit, completion("Level_0", 1.)));
+
+        let any_level = Condition::CompleteLevel { level: None };
+        assert!(any_level.is_met(&totals, &visit, completion("Level_1", 1.)));
+    }
+
+    #[test]
+    fn completes_levels_without_events_since_entering() {
+        let condition = Condition::CompleteLevelWithout {
+            event: GameEvent::Jump,
+            level: None,
+        };
+        // Jumps in earlier levels don't count
+        let totals = BTreeMap::from([(GameEvent::Jump, 12)]);
+        let mut visit = HashMap::new();
+        assert!(condition.is_met(&totals, &visit, completion("Level_0", 1.)));
+        visit.insert(GameEvent::Jump, 1);
+        assert!(!condition.is_met(&totals, &visit, completion("Level_0", 1.)));
+    }
+
+    #[test]
+    fn completes_levels_in_time() {
+        let condition = Condition::CompleteLevelWithin {
+            seconds: 30.,
+            level: None,
+        };
+        let (totals, visit) = (BTreeMap::new(), HashMap::new());
+        assert!(condition.is_met(&totals, &visit, completion("Level_0", 30.)));
+        assert!(!condition.is_met(&totals, &visit, completion("Level_0", 30.5)));
+    }
+}
@@ -0,0 +1,40 @@
+pub mod components;
+pub mod systems;
+
+use bevy::prelude::*;
+
+use crate::achievements::components::{AchievementUnlocked, Achievements, VisitEvents};
+use crate::player::systems::collect_fruits;
+use crate::save::systems::record_fruits;
+use crate::AppState;
+
+/// The file the achievements are defined in.
+pub const ACHIEVEMENTS_PATH: &str = "achievements.achievements.ron";
+/// The name of the file the progress of the achievements is stored in.
+pub const ACHIEVEMENTS_FILE: &str = "achievements.json";
+
+pub struct AchievementsPlugin;
+
+impl Plugin for AchievementsPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_asset::<Achievements>()
+            .init_asset_loader::<systems::AchievementsLoader>()
+            .add_event::<AchievementUnlocked>()
+            .init_resource::<VisitEvents>()
+            .add_systems(Startup, systems::load)
+            .add_systems(OnEnter(AppState::Achievements), systems::spawn)
+            .add_systems(OnExit(AppState::Achievements), systems::despawn)
+            .add_systems(OnExit(AppState::InGame), systems::forget_visit)
+            .add_systems(
+                Update,
+                systems::interact_with_back_button.run_if(in_state(AppState::Achievements)),
+            )
+            .add_systems(
+                Update,
+                systems::track_achievements
+                    .after(collect_fruits)
+                    .after(record_fruits)
+                    .run_if(in_state(AppState::InGame)),
+            );
+    }
+}
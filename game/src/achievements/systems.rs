@@ -0,0 +1,275 @@
+use bevy::asset::io::Reader;
+use bevy::asset::{AssetLoader, AsyncReadExt, LoadContext};
+use bevy::ecs::system::SystemParam;
+use bevy::prelude::*;
+
+use crate::achievements::components::{
+    AchievementProgress, AchievementUnlocked, Achievements, AchievementsHandle, AchievementsScreen,
+    BackButton, Completion, Condition, GameEvent, VisitEvents, PROGRESS_VERSION,
+};
+use crate::achievements::{ACHIEVEMENTS_FILE, ACHIEVEMENTS_PATH};
+use crate::fruit::components::FruitCollected;
+use crate::player::components::{Died, Drowned, Jumped};
+use crate::save::components::{LevelCompleted, LevelTimer};
+use crate::save::storage;
+use crate::transition::components::{StartTransition, TransitionStyle, TransitionTarget};
+use crate::{color, AppState};
+
+/// Loads achievements from RON files.
+#[derive(Default)]
+pub struct AchievementsLoader;
+
+impl AssetLoader for AchievementsLoader {
+    type Asset = Achievements;
+    type Settings = ();
+    type Error = std::io::Error;
+
+    async fn load<'a>(
+        &'a self,
+        reader: &'a mut Reader<'_>,
+        _: &'a Self::Settings,
+        _: &'a mut LoadContext<'_>,
+    ) -> Result<Self::Asset, Self::Error> {
+        let mut bytes = Vec::new();
+        reader.read_to_end(&mut bytes).await?;
+        ron::de::from_bytes(&bytes)
+            .map_err(|error| std::io::Error::new(std::io::ErrorKind::InvalidData, error))
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["achievements.ron"]
+    }
+}
+
+/// Loads the achievements and their progress.
+pub fn load(mut commands: Commands, asset_server: Res<AssetServer>) {
+    commands.insert_resource(AchievementsHandle(asset_server.load(ACHIEVEMENTS_PATH)));
+    let progress = match storage::read(ACHIEVEMENTS_FILE) {
+        Ok(Some(text)) => match serde_json::from_str::<AchievementProgress>(&text) {
+            Ok(progress) if progress.version == PROGRESS_VERSION => progress,
+            Ok(progress) => {
+                warn!(
+                    "Ignoring the achievements of the unsupported version {}",
+                    progress.version
+                );
+                AchievementProgress::default()
+            }
+            Err(error) => {
+                warn!("Ignoring the invalid achievements: {error}");
+                AchievementProgress::default()
+            }
+        },
+        Ok(None) => AchievementProgress::default(),
+        Err(error) => {
+            warn!("Could not read the achievements: {error}");
+            AchievementProgress::default()
+        }
+    };
+    commands.insert_resource(progress);
+}
+
+/// The gameplay events achievements are evaluated on.
+#[derive(SystemParam)]
+pub struct GameEvents<'w, 's> {
+    collected: EventReader<'w, 's, FruitCollected>,
+    jumped: EventReader<'w, 's, Jumped>,
+    died: EventReader<'w, 's, Died>,
+    drowned: EventReader<'w, 's, Drowned>,
+    completed: EventReader<'w, 's, LevelCompleted>,
+}
+
+impl GameEvents<'_, '_> {
+    /// Returns the events since the last call and the completed levels.
+    fn read(&mut self) -> (Vec<GameEvent>, Vec<LevelCompleted>) {
+        let mut events = Vec::new();
+        events.extend(self.collected.read().map(|_| GameEvent::Collect));
+        events.extend(self.jumped.read().map(|_| GameEvent::Jump));
+        events.extend(self.died.read().map(|_| GameEvent::Death));
+        events.extend(self.drowned.read().map(|_| GameEvent::Drown));
+        let completed: Vec<_> = self.completed.read().cloned().collect();
+        events.extend(completed.iter().map(|_| GameEvent::LevelComplete));
+        (events, completed)
+    }
+}
+
+/// Counts the gameplay events and unlocks the achievements whose condition is met.
+pub fn track_achievements(
+    mut game_events: GameEvents,
+    timer: Res<LevelTimer>,
+    mut visit: ResMut<VisitEvents>,
+    mut progress: ResMut<AchievementProgress>,
+    achievements: Res<Assets<Achievements>>,
+    achievements_handle: Res<AchievementsHandle>,
+    mut unlocked_events: EventWriter<AchievementUnlocked>,
+) {
+    if visit.level != timer.level {
+        *visit = VisitEvents {
+            level: timer.level.clone(),
+            ..default()
+        };
+    }
+    let (events, completed) = game_events.read();
+    if events.is_empty() {
+        return;
+    }
+    for event in events {
+        *progress.counters.entry(event).or_default() += 1;
+        *visit.counters.entry(event).or_default() += 1;
+    }
+
+    if let Some(achievements) = achievements.get(&achievements_handle.0) {
+        // Conditions without a level are checked once even when no level was completed
+        let completions = completed
+            .iter()
+            .map(|completed| {
+                Some(Completion {
+                    level: &completed.name,
+                    time: completed.time,
+                })
+            })
+            .chain([None]);
+        for completion in completions {
+            for achievement in achievements.iter() {
+                if progress.unlocked.contains(&achievement.id)
+                    || !achievement.condition.is_met(
+                        &progress.counters,
+                        &visit.counters,
+                        completion,
+                    )
+                {
+                    continue;
+                }
+                info!("Achievement unlocked: {}", achievement.name);
+                progress.unlocked.insert(achievement.id.clone());
+                unlocked_events.send(AchievementUnlocked {
+                    name: achievement.name.clone(),
+                    description: achievement.description.clone(),
+                });
+            }
+        }
+    }
+
+    progress.version = PROGRESS_VERSION;
+    let result = serde_json::to_string(&*progress)
+        .map_err(Into::into)
+        .and_then(|text| storage::write(ACHIEVEMENTS_FILE, &text));
+    if let Err(error) = result {
+        error!("Could not save the achievements: {error}");
+    }
+}
+
+pub fn forget_visit(mut visit: ResMut<VisitEvents>) {
+    *visit = VisitEvents::default();
+}
+
+pub fn spawn(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    achievements: Res<Assets<Achievements>>,
+    achievements_handle: Res<AchievementsHandle>,
+    progress: Res<AchievementProgress>,
+) {
+    let text_style = |font_size, color| TextStyle {
+        font_size,
+        color,
+        ..default()
+    };
+    commands
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    flex_direction: FlexDirection::Column,
+                    justify_content: JustifyContent::Center,
+                    align_items: AlignItems::Center,
+                    row_gap: Val::Px(10.0),
+                    width: Val::Percent(100.0),
+                    height: Val::Percent(100.0),
+                    ..default()
+                },
+                ..default()
+            },
+            AchievementsScreen,
+        ))
+        .with_children(|parent| {
+            // Title
+            parent.spawn(TextBundle::from_section(
+                "Achievements",
+                text_style(64.0, color::PRIMARY_CONTENT),
+            ));
+
+            // Achievements
+            for achievement in achievements
+                .get(&achievements_handle.0)
+                .into_iter()
+                .flat_map(|achievements| achievements.iter())
+            {
+                let unlocked = progress.unlocked.contains(&achievement.id);
+                let status = match &achievement.condition {
+                    _ if unlocked => "Unlocked".to_string(),
+                    Condition::Count { event, count } => format!(
+                        "{}/{count}",
+                        progress.counters.get(event).copied().unwrap_or(0)
+                    ),
+                    _ => "Locked".to_string(),
+                };
+                // Locked achievements are dimmed
+                let color = if unlocked {
+                    color::PRIMARY_CONTENT
+                } else {
+                    color::PRIMARY_CONTENT.with_alpha(0.5)
+                };
+                parent.spawn(TextBundle::from_sections([
+                    TextSection::new(format!("{}  ", achievement.name), text_style(32.0, color)),
+                    TextSection::new(
+                        format!("{} ({status})", achievement.description),
+                        text_style(24.0, color),
+                    ),
+                ]));
+            }
+
+            // Back Button
+            parent.spawn((
+                ButtonBundle {
+                    image: UiImage::new(asset_server.load("Menu/Buttons/Back.png")),
+                    style: Style {
+                        width: Val::Px(42.0),
+                        height: Val::Px(44.0),
+                        ..default()
+                    },
+                    ..default()
+                },
+                BackButton,
+            ));
+        });
+}
+
+pub fn despawn(mut commands: Commands, screen_query: Query<Entity, With<AchievementsScreen>>) {
+    if let Ok(screen_entity) = screen_query.get_single() {
+        commands.entity(screen_entity).despawn_recursive();
+    }
+}
+
+type ImageForInteraction<'a> = (&'a Interaction, &'a mut UiImage);
+
+pub fn interact_with_back_button(
+    mut button_query: Query<ImageForInteraction, (Changed<Interaction>, With<BackButton>)>,
+    mut transition_events: EventWriter<StartTransition>,
+) {
+    if let Ok((interaction, mut image)) = button_query.get_single_mut() {
+        match interaction {
+            Interaction::None => {
+                image.color = Color::WHITE;
+            }
+            Interaction::Pressed => {
+                image.color = Color::WHITE;
+                transition_events.send(StartTransition {
+                    target: TransitionTarget::App(AppState::MainMenu),
+                    style: TransitionStyle::Fade,
+                });
+            }
+            Interaction::Hovered => {
+                image.color = color::PRIMARY_HOVER;
+            }
+        }
+    }
+}
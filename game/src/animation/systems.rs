@@ -2,7 +2,7 @@ use bevy::prelude::*;
 use bevy_rapier2d::prelude::{KinematicCharacterControllerOutput, Velocity};
 
 use super::components::{AnimationIndices, AnimationTimer};
-use crate::player::components::{Movement, Player};
+use crate::player::components::{Character, HitAnimation, Movement, Player};
 use crate::Tilesets;
 
 pub fn animate_sprite(
@@ -22,41 +22,64 @@ pub fn animate_sprite(
 }
 
 type AnimationRelated<'a> = (
+    Entity,
+    &'a Character,
     &'a Velocity,
     &'a mut Handle<Image>,
     &'a mut Sprite,
     &'a mut AnimationIndices,
     &'a KinematicCharacterControllerOutput,
+    &'a mut TextureAtlas,
+    Option<&'a mut HitAnimation>,
 );
 
 const DELTA: f32 = 10.0;
 
 pub fn change_player_animation(
+    mut commands: Commands,
     mut player: Query<AnimationRelated, With<Player>>,
-    tileset: Res<Tilesets<Movement>>,
+    tileset: Res<Tilesets<(Character, Movement)>>,
+    time: Res<Time>,
 ) {
-    if let Ok((
+    for (
+        entity,
+        character,
         velocity,
         mut current_texture,
         mut sprite,
         mut animation_indices,
         character_controller,
-    )) = player.get_single_mut()
+        mut atlas,
+        hit_animation,
+    ) in player.iter_mut()
     {
+        let mut play = |movement: Movement| {
+            *current_texture = tileset.get(&(*character, movement)).unwrap().clone();
+            animation_indices.last = movement.last_frame();
+        };
+        // The hit animation plays once before the movement is animated again
+        if let Some(mut hit_animation) = hit_animation {
+            if hit_animation.is_added() {
+                play(Movement::Hit);
+                atlas.index = 0;
+            }
+            if !hit_animation.tick(time.delta()).finished() {
+                continue;
+            }
+            commands.entity(entity).remove::<HitAnimation>();
+            play(Movement::Idle);
+            atlas.index = 0;
+        }
         if character_controller.grounded {
             if (-DELTA..=DELTA).contains(&velocity.linvel.x) {
-                *current_texture = tileset.get(&Movement::Idle).unwrap().clone();
-                animation_indices.last = 10;
+                play(Movement::Idle);
             } else {
-                animation_indices.last = 10;
-                *current_texture = tileset.get(&Movement::Run).unwrap().clone();
+                play(Movement::Run);
             }
-        } else if !character_controller.grounded && velocity.linvel.y > DELTA {
-            *current_texture = tileset.get(&Movement::Jump).unwrap().clone();
-            animation_indices.last = 0;
-        } else if !character_controller.grounded && velocity.linvel.y < -DELTA {
-            animation_indices.last = 0;
-            *current_texture = tileset.get(&Movement::Fall).unwrap().clone();
+        } else if velocity.linvel.y > DELTA {
+            play(Movement::Jump);
+        } else if velocity.linvel.y < -DELTA {
+            play(Movement::Fall);
         }
         if velocity.linvel.x > DELTA {
             sprite.flip_x = false;
@@ -0,0 +1,16 @@
+use bevy::prelude::*;
+
+/// A repeating background, that scrolls slower than the level the further away it is.
+#[derive(Component, Debug, Clone)]
+pub struct BackgroundLayer {
+    /// How much the layer moves with the level, 0 keeps it fixed to the screen
+    pub parallax: Vec2,
+    /// How fast the layer moves by itself in pixels per second
+    pub velocity: Vec2,
+    /// How far the layer moved by itself
+    pub scroll: Vec2,
+}
+
+/// The iid of the level whose background is shown.
+#[derive(Resource, Default, Debug)]
+pub struct BackgroundLevel(pub Option<String>);
@@ -0,0 +1,30 @@
+pub mod components;
+pub mod systems;
+
+use bevy::prelude::*;
+
+use crate::camera::systems::apply_camera_effects;
+use crate::AppState;
+
+/// The size in pixels of the background tiles.
+pub const BACKGROUND_TILE_SIZE: f32 = 64.;
+
+/// Behind the levels, which start at zero.
+pub const BACKGROUND_Z: f32 = -10.;
+
+pub struct BackgroundPlugin;
+
+impl Plugin for BackgroundPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(OnEnter(AppState::InGame), systems::spawn)
+            .add_systems(
+                Update,
+                (systems::update_background_tile, systems::scroll_background)
+                    .chain()
+                    // The background follows the camera, including its shake
+                    .after(apply_camera_effects)
+                    .run_if(in_state(AppState::InGame)),
+            )
+            .add_systems(OnExit(AppState::InGame), systems::despawn);
+    }
+}
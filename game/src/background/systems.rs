@@ -0,0 +1,112 @@
+use bevy::prelude::*;
+use bevy_ecs_ldtk::prelude::*;
+
+use crate::background::components::{BackgroundLayer, BackgroundLevel};
+use crate::background::{BACKGROUND_TILE_SIZE, BACKGROUND_Z};
+use crate::camera::components::CameraController;
+use crate::world::SpawnedLevels;
+
+/// The LDtk level field the background tile is read from.
+const BACKGROUND_FIELD: &str = "Background";
+
+/// The parallax, velocity and opacity of each layer, from the furthest to the nearest.
+const LAYERS: [(Vec2, Vec2, f32); 2] = [
+    (Vec2::new(0.1, 0.1), Vec2::new(0., -8.), 1.),
+    (Vec2::new(0.3, 0.2), Vec2::new(4., -16.), 0.25),
+];
+
+pub fn spawn(mut commands: Commands) {
+    commands.insert_resource(BackgroundLevel::default());
+    for (index, (parallax, velocity, alpha)) in LAYERS.into_iter().enumerate() {
+        commands.spawn((
+            SpriteBundle {
+                sprite: Sprite {
+                    color: Color::WHITE.with_alpha(alpha),
+                    ..default()
+                },
+                transform: Transform::from_xyz(0., 0., BACKGROUND_Z + index as f32),
+                visibility: Visibility::Hidden,
+                ..default()
+            },
+            ImageScaleMode::Tiled {
+                tile_x: true,
+                tile_y: true,
+                stretch_value: 1.,
+            },
+            BackgroundLayer {
+                parallax,
+                velocity,
+                scroll: Vec2::ZERO,
+            },
+        ));
+    }
+}
+
+pub fn despawn(mut commands: Commands, layer_query: Query<Entity, With<BackgroundLayer>>) {
+    for layer in layer_query.iter() {
+        commands.entity(layer).despawn();
+    }
+    commands.remove_resource::<BackgroundLevel>();
+}
+
+/// Shows the background tile of the level the camera is in.
+pub fn update_background_tile(
+    mut layer_query: Query<(&mut Handle<Image>, &mut Visibility), With<BackgroundLayer>>,
+    camera_query: Query<&GlobalTransform, With<CameraController>>,
+    levels: SpawnedLevels,
+    mut background_level: ResMut<BackgroundLevel>,
+    mut clear_color: ResMut<ClearColor>,
+    asset_server: Res<AssetServer>,
+) {
+    let Ok(camera) = camera_query.get_single() else {
+        return;
+    };
+    let Some((_, level)) = levels.containing(camera.translation().truncate()) else {
+        return;
+    };
+    if background_level.0.as_ref() == Some(&level.iid) {
+        return;
+    }
+    background_level.0 = Some(level.iid.clone());
+
+    // Without a tile, the background color of the level shows around it
+    clear_color.0 = level.bg_color;
+    for (mut texture, mut visibility) in layer_query.iter_mut() {
+        match level.get_enum_field(BACKGROUND_FIELD) {
+            Ok(name) => {
+                *texture = asset_server.load(format!("Background/{name}.png"));
+                *visibility = Visibility::Visible;
+            }
+            Err(_) => *visibility = Visibility::Hidden,
+        }
+    }
+}
+
+type CameraRelated<'a> = (&'a Transform, &'a OrthographicProjection);
+
+/// Keeps the layers covering the view, with their tiles moving with their parallax.
+pub fn scroll_background(
+    mut layer_query: Query<(&mut BackgroundLayer, &mut Sprite, &mut Transform)>,
+    camera_query: Query<CameraRelated, (With<CameraController>, Without<BackgroundLayer>)>,
+    time: Res<Time>,
+) {
+    let Ok((camera, projection)) = camera_query.get_single() else {
+        return;
+    };
+    let center = camera.translation.truncate();
+    // An even number of tiles, one more on each side than needed, so the edges of the
+    // sprite are always on the edges of the tiles
+    let tiles = (projection.area.size() / BACKGROUND_TILE_SIZE / 2.).ceil() + 1.;
+    let size = tiles * 2. * BACKGROUND_TILE_SIZE;
+
+    for (mut layer, mut sprite, mut transform) in layer_query.iter_mut() {
+        let velocity = layer.velocity;
+        layer.scroll += velocity * time.delta_seconds();
+        // A tile corner, that moves with the level by the parallax of the layer
+        let anchor = center * (Vec2::ONE - layer.parallax) + layer.scroll;
+        let offset = (anchor - center).rem_euclid(Vec2::splat(BACKGROUND_TILE_SIZE));
+        sprite.custom_size = Some(size);
+        transform.translation.x = center.x + offset.x;
+        transform.translation.y = center.y + offset.y;
+    }
+}
@@ -0,0 +1,23 @@
+use bevy::app::ScheduleRunnerPlugin;
+use bevy::log::LogPlugin;
+use bevy::prelude::*;
+use clap::Parser;
+use std::time::Duration;
+
+/// How often the server answers the games.
+const TICKS_PER_SECOND: f64 = 30.;
+
+fn main() -> AppExit {
+    let server_args = game::cli::ServerArgs::parse();
+    App::new()
+        .add_plugins(
+            MinimalPlugins.set(ScheduleRunnerPlugin::run_loop(Duration::from_secs_f64(
+                1. / TICKS_PER_SECOND,
+            ))),
+        )
+        .add_plugins(LogPlugin::default())
+        .add_plugins(game::lobby::LobbyServerPlugin {
+            listen_address: server_args.listen_address,
+        })
+        .run()
+}
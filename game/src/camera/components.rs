@@ -0,0 +1,180 @@
+use bevy::prelude::*;
+use bevy_ecs_ldtk::prelude::*;
+use std::time::Duration;
+
+/// Makes the camera follow the player within the bounds of the current level.
+#[derive(Component, Debug, Clone)]
+pub struct CameraController {
+    /// The size of the area around the focus in which the player can move
+    /// without moving the camera
+    pub dead_zone: Vec2,
+    /// How far the camera looks ahead in the direction the player is facing or moving
+    pub look_ahead: f32,
+    /// How fast the camera turns towards the direction the player is facing or moving
+    pub look_ahead_speed: f32,
+    /// How fast the camera follows the player
+    pub follow_speed: f32,
+    /// How fast the camera zooms to the zoom of the current level
+    pub zoom_speed: f32,
+    /// The point the camera is centered on, before looking ahead
+    pub focus: Option<Vec2>,
+    /// The current horizontal look ahead offset
+    pub look_ahead_offset: f32,
+}
+
+impl Default for CameraController {
+    fn default() -> Self {
+        Self {
+            dead_zone: Vec2::new(32., 64.),
+            look_ahead: 48.,
+            look_ahead_speed: 2.,
+            follow_speed: 2.,
+            zoom_speed: 2.,
+            focus: None,
+            look_ahead_offset: 0.,
+        }
+    }
+}
+
+/// Marks what the camera follows, the players or the player of a replay.
+#[derive(Component, Debug, Default)]
+pub struct CameraTarget;
+
+/// Detaches the camera from its targets, so it can be moved freely.
+#[derive(Resource, Debug, Default)]
+pub struct FreeCamera;
+
+/// Shakes the camera, the trauma of several shakes adds up.
+#[derive(Event, Debug, Clone, Copy)]
+pub struct CameraShake {
+    /// How much trauma to add, between 0 and 1
+    pub trauma: f32,
+}
+
+/// Eases the zoom of the camera to a scale of the current zoom, 1 restores it.
+#[derive(Event, Debug, Clone, Copy)]
+pub struct CameraZoomTo {
+    /// The projection scale relative to the current zoom, less than 1 zooms in
+    pub scale: f32,
+    /// How long easing to the new scale takes in seconds
+    pub duration: f32,
+}
+
+/// Briefly zooms the camera to a scale of the current zoom and back.
+#[derive(Event, Debug, Clone, Copy)]
+pub struct CameraZoomPulse {
+    /// The projection scale relative to the current zoom at the peak of the pulse
+    pub scale: f32,
+    /// How long the whole pulse takes in seconds
+    pub duration: f32,
+}
+
+/// The shake and zoom applied on top of the camera controller.
+///
+/// The effects are removed before and reapplied after the controller moves the camera,
+/// so the controller never follows its own shake.
+#[derive(Component, Debug, Clone)]
+pub struct CameraEffects {
+    /// The current trauma, the shake grows with its square
+    pub trauma: f32,
+    /// How much trauma is lost per second
+    pub trauma_decay: f32,
+    /// How far the camera is offset at full trauma
+    pub max_offset: Vec2,
+    /// The scale the zoom eases from
+    pub zoom_from: f32,
+    /// The scale the zoom eases to
+    pub zoom_to: f32,
+    pub zoom_timer: Timer,
+    /// The scale at the peak of the current pulse
+    pub pulse_scale: f32,
+    pub pulse_timer: Timer,
+    /// The offset applied to the camera's translation in the previous frame
+    pub applied_offset: Vec2,
+    /// The scale applied to the camera's projection in the previous frame
+    pub applied_scale: f32,
+}
+
+impl Default for CameraEffects {
+    fn default() -> Self {
+        let finished = || {
+            let mut timer = Timer::from_seconds(0., TimerMode::Once);
+            timer.tick(Duration::ZERO);
+            timer
+        };
+        Self {
+            trauma: 0.,
+            trauma_decay: 1.,
+            max_offset: Vec2::new(12., 8.),
+            zoom_from: 1.,
+            zoom_to: 1.,
+            zoom_timer: finished(),
+            pulse_scale: 1.,
+            pulse_timer: finished(),
+            applied_offset: Vec2::ZERO,
+            applied_scale: 1.,
+        }
+    }
+}
+
+/// An area that overrides the framing of the camera while the player is inside it.
+#[derive(Component, Debug, Clone, Default)]
+pub struct CameraZone {
+    pub size: Vec2,
+    /// Keeps the camera horizontally centered on the zone
+    pub lock_x: bool,
+    /// Keeps the camera vertically centered on the zone
+    pub lock_y: bool,
+    /// Overrides the zoom of the level
+    pub zoom: Option<f32>,
+}
+
+impl From<&EntityInstance> for CameraZone {
+    fn from(entity_instance: &EntityInstance) -> Self {
+        Self {
+            size: Vec2::new(entity_instance.width as f32, entity_instance.height as f32),
+            lock_x: entity_instance
+                .get_bool_field("LockX")
+                .copied()
+                .unwrap_or_default(),
+            lock_y: entity_instance
+                .get_bool_field("LockY")
+                .copied()
+                .unwrap_or_default(),
+            zoom: entity_instance
+                .get_maybe_float_field("Zoom")
+                .ok()
+                .copied()
+                .flatten(),
+        }
+    }
+}
+
+#[derive(Default, LdtkEntity, Bundle)]
+pub struct CameraZoneBundle {
+    #[from_entity_instance]
+    camera_zone: CameraZone,
+}
+
+/// How the world is drawn to the window.
+#[derive(Resource, Default, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RenderMode {
+    /// The world is drawn straight to the window and scaled smoothly
+    #[default]
+    Smooth,
+    /// The world is drawn to a low-resolution canvas, that is upscaled by whole multiples
+    /// and letterboxed, so every pixel of the level covers the same number of screen pixels
+    PixelPerfect,
+}
+
+/// The sprite showing the low-resolution image the world is drawn to in pixel-perfect mode.
+#[derive(Component, Debug)]
+pub struct PixelCanvas;
+
+/// The camera drawing the pixel canvas and the user interface to the window.
+#[derive(Component, Debug)]
+pub struct CanvasCamera;
+
+/// How far each sprite was moved to snap it to the pixel grid, so it can be moved back.
+#[derive(Resource, Default, Debug)]
+pub struct PixelSnapOffsets(pub Vec<(Entity, Vec3)>);
@@ -0,0 +1,52 @@
+pub mod components;
+pub mod systems;
+
+use bevy::prelude::*;
+
+use crate::camera::components::{
+    CameraShake, CameraZoomPulse, CameraZoomTo, FreeCamera, PixelSnapOffsets, RenderMode,
+};
+use crate::AppState;
+
+pub struct CameraPlugin;
+
+impl Plugin for CameraPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<CameraShake>()
+            .add_event::<CameraZoomTo>()
+            .add_event::<CameraZoomPulse>()
+            .init_resource::<RenderMode>()
+            .init_resource::<PixelSnapOffsets>()
+            .add_systems(Startup, systems::spawn_camera)
+            .add_systems(
+                Update,
+                (
+                    systems::reset_camera.run_if(systems::player_spawned),
+                    systems::remove_camera_effects,
+                    systems::update_camera.run_if(not(resource_exists::<FreeCamera>)),
+                    systems::move_free_camera.run_if(resource_exists::<FreeCamera>),
+                    systems::apply_camera_effects,
+                )
+                    .chain()
+                    .run_if(
+                        in_state(AppState::InGame)
+                            .or_else(in_state(AppState::Replay))
+                            .or_else(in_state(AppState::Editor)),
+                    ),
+            )
+            .add_systems(
+                Update,
+                systems::fit_canvas.run_if(resource_equals(RenderMode::PixelPerfect)),
+            )
+            // The snapped positions are only seen by the renderer,
+            // which extracts them after the last schedule
+            .add_systems(
+                Last,
+                systems::snap_to_pixel_grid.run_if(resource_equals(RenderMode::PixelPerfect)),
+            )
+            .add_systems(
+                First,
+                systems::unsnap_from_pixel_grid.run_if(resource_equals(RenderMode::PixelPerfect)),
+            );
+    }
+}
@@ -0,0 +1,495 @@
+use bevy::core_pipeline::bloom::BloomSettings;
+use bevy::input::mouse::MouseWheel;
+use bevy::prelude::*;
+use bevy::render::camera::RenderTarget;
+use bevy::render::render_resource::{
+    Extent3d, TextureDescriptor, TextureDimension, TextureFormat, TextureUsages,
+};
+use bevy::render::texture::ImageSampler;
+use bevy::render::view::RenderLayers;
+use bevy::window::PrimaryWindow;
+use bevy_ecs_ldtk::prelude::*;
+use bevy_rapier2d::prelude::*;
+
+use crate::camera::components::{
+    CameraController, CameraEffects, CameraShake, CameraTarget, CameraZone, CameraZoomPulse,
+    CameraZoomTo, CanvasCamera, PixelCanvas, PixelSnapOffsets, RenderMode,
+};
+use crate::player::components::Player;
+use crate::world::SpawnedLevels;
+
+/// The LDtk level field the zoom of the camera is read from.
+const ZOOM_FIELD: &str = "Zoom";
+
+/// The space kept around the players when the camera frames several of them.
+const FRAMING_MARGIN: Vec2 = Vec2::new(96., 64.);
+
+/// The render layer of the pixel canvas, so the world camera does not draw it.
+const CANVAS_LAYER: RenderLayers = RenderLayers::layer(1);
+
+pub fn spawn_camera(
+    mut commands: Commands,
+    window_query: Query<&Window, With<PrimaryWindow>>,
+    render_mode: Res<RenderMode>,
+    mut images: ResMut<Assets<Image>>,
+) {
+    let window = window_query.get_single().unwrap();
+    let width = window.width();
+    let height = window.height();
+    let physical_width = window.physical_width();
+    let physical_height = window.physical_height();
+    info!("logical: {width}x{height}");
+    info!("physical: {physical_width}x{physical_height}");
+
+    let target = match *render_mode {
+        RenderMode::Smooth => RenderTarget::default(),
+        RenderMode::PixelPerfect => {
+            let physical_size = UVec2::new(physical_width, physical_height);
+            let canvas = images.add(canvas_image(physical_size.max(UVec2::ONE)));
+            commands.spawn((
+                SpriteBundle {
+                    texture: canvas.clone(),
+                    ..default()
+                },
+                PixelCanvas,
+                CANVAS_LAYER,
+            ));
+            commands.spawn((
+                Camera2dBundle {
+                    camera: Camera {
+                        // The world is drawn to the canvas before the canvas is drawn
+                        order: 1,
+                        clear_color: ClearColorConfig::Custom(Color::BLACK),
+                        ..default()
+                    },
+                    ..default()
+                },
+                CanvasCamera,
+                CANVAS_LAYER,
+                IsDefaultUiCamera,
+            ));
+            RenderTarget::Image(canvas)
+        }
+    };
+
+    commands.spawn((
+        Camera2dBundle {
+            camera: Camera {
+                hdr: true, // HDR is required for the bloom effect
+                target,
+                ..default()
+            },
+            transform: Transform::from_xyz(window.width() / 2.0, window.height() / 2.0, 0.0),
+            ..default()
+        },
+        BloomSettings::NATURAL,
+        CameraController::default(),
+        CameraEffects::default(),
+    ));
+}
+
+/// Creates the low-resolution image the world is drawn to in pixel-perfect mode.
+fn canvas_image(canvas_size: UVec2) -> Image {
+    let size = Extent3d {
+        width: canvas_size.x,
+        height: canvas_size.y,
+        ..default()
+    };
+    let mut image = Image {
+        texture_descriptor: TextureDescriptor {
+            label: Some("pixel canvas"),
+            size,
+            dimension: TextureDimension::D2,
+            format: TextureFormat::Bgra8UnormSrgb,
+            mip_level_count: 1,
+            sample_count: 1,
+            usage: TextureUsages::TEXTURE_BINDING
+                | TextureUsages::COPY_DST
+                | TextureUsages::RENDER_ATTACHMENT,
+            view_formats: &[],
+        },
+        sampler: ImageSampler::nearest(),
+        ..default()
+    };
+    // Fills the image with zeroes
+    image.resize(size);
+    image
+}
+
+/// Returns the whole multiple the canvas is upscaled by and the size of the canvas.
+///
+/// The scale is the smallest one at which the canvas is no larger than the level,
+/// so the camera never shows what is around it.
+fn fit_to_level(physical_size: UVec2, level_size: UVec2) -> (u32, UVec2) {
+    let scale = (physical_size.as_vec2() / level_size.max(UVec2::ONE).as_vec2())
+        .ceil()
+        .max_element()
+        .max(1.) as u32;
+    (scale, (physical_size / scale).max(UVec2::ONE))
+}
+
+/// Sizes the pixel canvas to the level the camera is in and scales it by a whole multiple
+/// that fills the window.
+///
+/// The space left around the canvas is letterboxed by the clear color of the canvas camera.
+pub fn fit_canvas(
+    window_query: Query<&Window, With<PrimaryWindow>>,
+    mut canvas_query: Query<(&mut Transform, &Handle<Image>), With<PixelCanvas>>,
+    camera_query: Query<&GlobalTransform, With<CameraController>>,
+    levels: SpawnedLevels,
+    mut images: ResMut<Assets<Image>>,
+) {
+    let (Ok(window), Ok((mut canvas, canvas_image))) =
+        (window_query.get_single(), canvas_query.get_single_mut())
+    else {
+        return;
+    };
+    let physical_size = UVec2::new(window.physical_width(), window.physical_height());
+    // Until a level is spawned, the canvas covers the window pixel for pixel
+    let level_size = camera_query
+        .get_single()
+        .ok()
+        .and_then(|camera| levels.containing(camera.translation().truncate()))
+        .map_or(physical_size, |(_, level)| {
+            IVec2::new(level.px_wid, level.px_hei).as_uvec2()
+        });
+    let (scale, canvas_size) = fit_to_level(physical_size, level_size);
+    let resized = images
+        .get(canvas_image)
+        .is_some_and(|image| image.size() != canvas_size);
+    // The world camera follows the size of its target when it changes
+    if let Some(image) = images.get_mut(canvas_image).filter(|_| resized) {
+        image.resize(Extent3d {
+            width: canvas_size.x,
+            height: canvas_size.y,
+            ..default()
+        });
+    }
+
+    // The canvas camera works in logical pixels, so physical pixels are converted
+    let to_logical = 1. / window.scale_factor();
+    canvas.scale = Vec3::new(scale as f32 * to_logical, scale as f32 * to_logical, 1.);
+
+    // A centered canvas starts half a pixel off the pixel grid when the space left is odd
+    let odd = (physical_size - canvas_size * scale) % 2;
+    canvas.translation.x = odd.x as f32 * 0.5 * to_logical;
+    canvas.translation.y = odd.y as f32 * 0.5 * to_logical;
+}
+
+type SnappedFilter = (
+    Or<(With<Sprite>, With<CameraController>)>,
+    Without<PixelCanvas>,
+);
+
+/// Rounds the positions of the camera and all sprites to whole pixels of the canvas
+/// before they are drawn.
+///
+/// A world unit covers as many canvas pixels as the camera zooms in,
+/// so the positions are rounded after scaling them by the zoom.
+/// Only the global transforms are snapped and the offsets are remembered,
+/// so physics and movement keep working with the exact positions.
+pub fn snap_to_pixel_grid(
+    mut snapped_query: Query<(Entity, &mut GlobalTransform), SnappedFilter>,
+    projection_query: Query<&OrthographicProjection, With<CameraController>>,
+    mut offsets: ResMut<PixelSnapOffsets>,
+) {
+    offsets.0.clear();
+    let zoom = projection_query
+        .get_single()
+        .map_or(1., |projection| 1. / projection.scale);
+    for (entity, mut global_transform) in snapped_query.iter_mut() {
+        let translation = global_transform.translation();
+        let snapped = ((translation.truncate() * zoom).round() / zoom).extend(translation.z);
+        let offset = snapped - translation;
+        if offset != Vec3::ZERO {
+            *global_transform = GlobalTransform::from(
+                global_transform
+                    .compute_transform()
+                    .with_translation(snapped),
+            );
+            offsets.0.push((entity, offset));
+        }
+    }
+}
+
+/// Moves the snapped global transforms back to the exact positions.
+pub fn unsnap_from_pixel_grid(
+    mut global_transforms: Query<&mut GlobalTransform>,
+    mut offsets: ResMut<PixelSnapOffsets>,
+) {
+    for (entity, offset) in offsets.0.drain(..) {
+        if let Ok(mut global_transform) = global_transforms.get_mut(entity) {
+            let transform = global_transform.compute_transform();
+            *global_transform =
+                GlobalTransform::from(transform.with_translation(transform.translation - offset));
+        }
+    }
+}
+
+/// Whether the player was spawned, when a game starts or the world respawns.
+pub fn player_spawned(player_query: Query<(), Added<Player>>) -> bool {
+    !player_query.is_empty()
+}
+
+/// Lets the camera jump to the player when they spawn instead of moving there.
+pub fn reset_camera(mut camera_query: Query<(&mut CameraController, &mut CameraEffects)>) {
+    for (mut controller, mut effects) in camera_query.iter_mut() {
+        controller.focus = None;
+        controller.look_ahead_offset = 0.;
+        *effects = CameraEffects::default();
+    }
+}
+
+type CameraRelated<'a> = (
+    &'a mut Transform,
+    &'a mut OrthographicProjection,
+    &'a mut CameraController,
+);
+
+type PlayerRelated<'a> = (
+    &'a GlobalTransform,
+    &'a Velocity,
+    &'a Sprite,
+    &'a KinematicCharacterControllerOutput,
+);
+
+/// Moves the camera towards the player.
+///
+///  1. The focus only follows the player when they leave the dead zone around it
+///  2. Vertically, the focus snaps to the height of the platform the player lands on,
+///     and only follows a jumping or falling player when they leave the dead zone
+///  3. The camera looks ahead in the direction the player is moving or facing
+///  4. The camera zooms to the zoom of the level the player is in,
+///     and is clamped to the bounds of that level
+///  5. A camera zone the player is in can lock the camera onto its center
+///     and override the zoom of the level
+///  6. With several players, the camera follows the middle between them
+///     and zooms out until they all fit
+pub fn update_camera(
+    mut camera_query: Query<CameraRelated, (With<Camera2d>, Without<CameraTarget>)>,
+    player_query: Query<PlayerRelated, (With<CameraTarget>, Without<Camera2d>)>,
+    zone_query: Query<(&CameraZone, &GlobalTransform)>,
+    levels: SpawnedLevels,
+    time: Res<Time>,
+) {
+    let Ok((mut camera, mut projection, mut controller)) = camera_query.get_single_mut() else {
+        return;
+    };
+    let players: Vec<_> = player_query.iter().collect();
+    let Some(framed) = players
+        .iter()
+        .map(|(transform, ..)| {
+            Rect::from_center_size(transform.translation().truncate(), Vec2::ZERO)
+        })
+        .reduce(|framed, player| framed.union(player))
+    else {
+        return;
+    };
+    let player = framed.center();
+    let grounded = players.iter().all(|(.., output)| output.grounded);
+
+    // The level the player is in
+    let level = levels.containing(player);
+
+    // The camera zone the player is in
+    let zone = zone_query
+        .iter()
+        .map(|(zone, zone_transform)| {
+            let bounds = Rect::from_center_size(zone_transform.translation().truncate(), zone.size);
+            (zone, bounds)
+        })
+        .find(|(_, bounds)| bounds.contains(player));
+
+    let is_first_frame = controller.focus.is_none();
+    let mut focus = controller.focus.unwrap_or(player);
+    let half_dead_zone = controller.dead_zone / 2.;
+    focus.x = focus
+        .x
+        .clamp(player.x - half_dead_zone.x, player.x + half_dead_zone.x);
+    focus.y = if grounded {
+        player.y
+    } else {
+        focus
+            .y
+            .clamp(player.y - half_dead_zone.y, player.y + half_dead_zone.y)
+    };
+    controller.focus = Some(focus);
+
+    let direction = match players.as_slice() {
+        [(_, velocity, _, _)] if velocity.linvel.x.abs() > 1. => velocity.linvel.x.signum(),
+        [(_, _, sprite, _)] if sprite.flip_x => -1.,
+        [_] => 1.,
+        // Looking ahead of one player could push the others out of view
+        _ => 0.,
+    };
+    let look_ahead_rate = (time.delta_seconds() * controller.look_ahead_speed).min(1.);
+    controller.look_ahead_offset = controller
+        .look_ahead_offset
+        .lerp(direction * controller.look_ahead, look_ahead_rate);
+
+    let zoom = zone
+        .and_then(|(zone, _)| zone.zoom)
+        .or_else(|| level.and_then(|(_, level)| level.get_float_field(ZOOM_FIELD).ok().copied()))
+        .filter(|zoom| *zoom > 0.)
+        .unwrap_or(1.);
+    let mut scale = 1. / zoom;
+    let unscaled_view = projection.area.size() / projection.scale;
+    if players.len() > 1 && unscaled_view.min_element() > 0. {
+        let fitting_scale = ((framed.size() + 2. * FRAMING_MARGIN) / unscaled_view).max_element();
+        scale = scale.max(fitting_scale);
+    }
+    let zoom_rate = (time.delta_seconds() * controller.zoom_speed).min(1.);
+    projection.scale = if is_first_frame {
+        scale
+    } else {
+        projection.scale.lerp(scale, zoom_rate)
+    };
+
+    let mut target = focus + Vec2::new(controller.look_ahead_offset, 0.);
+    if let Some((zone, bounds)) = zone {
+        if zone.lock_x {
+            target.x = bounds.center().x;
+        }
+        if zone.lock_y {
+            target.y = bounds.center().y;
+        }
+    }
+    if let Some((bounds, _)) = level {
+        // The visible area scales with the projection
+        let half_view = projection.area.half_size();
+        target = clamp_to_bounds(target, half_view, bounds);
+    }
+
+    let target = target.extend(camera.translation.z);
+    camera.translation = if is_first_frame {
+        target
+    } else {
+        // Applies a smooth effect to camera movement using interpolation between
+        // the camera position and the target position on the x and y axes.
+        // Here we use the in-game time, to get the elapsed time (in seconds)
+        // since the previous update. This avoids jittery movement when tracking
+        // the player.
+        camera.translation.lerp(
+            target,
+            (time.delta_seconds() * controller.follow_speed).min(1.),
+        )
+    };
+}
+
+/// Clamps the center of a view, so the view stays within the bounds.
+///
+/// A view that is larger than the bounds is centered on them.
+fn clamp_to_bounds(center: Vec2, half_view: Vec2, bounds: Rect) -> Vec2 {
+    let clamp_axis = |center: f32, half_view: f32, min: f32, max: f32| {
+        if max - min <= 2. * half_view {
+            (min + max) / 2.
+        } else {
+            center.clamp(min + half_view, max - half_view)
+        }
+    };
+    Vec2::new(
+        clamp_axis(center.x, half_view.x, bounds.min.x, bounds.max.x),
+        clamp_axis(center.y, half_view.y, bounds.min.y, bounds.max.y),
+    )
+}
+
+/// How fast the free camera moves, in screens per second.
+const FREE_CAMERA_SPEED: f32 = 0.75;
+/// How much one step of the mouse wheel zooms the free camera.
+const FREE_CAMERA_ZOOM_STEP: f32 = 1.1;
+
+/// Pans the free camera with WASD and zooms it with the mouse wheel.
+pub fn move_free_camera(
+    mut camera_query: Query<(&mut Transform, &mut OrthographicProjection), With<CameraController>>,
+    keyboard_input: Res<ButtonInput<KeyCode>>,
+    mut wheel_events: EventReader<MouseWheel>,
+    time: Res<Time>,
+) {
+    let Ok((mut transform, mut projection)) = camera_query.get_single_mut() else {
+        return;
+    };
+    let direction = [
+        (KeyCode::KeyA, Vec2::NEG_X),
+        (KeyCode::KeyD, Vec2::X),
+        (KeyCode::KeyW, Vec2::Y),
+        (KeyCode::KeyS, Vec2::NEG_Y),
+    ]
+    .into_iter()
+    .filter(|(key, _)| keyboard_input.pressed(*key))
+    .map(|(_, direction)| direction)
+    .sum::<Vec2>();
+    let speed = projection.area.size().max_element() * FREE_CAMERA_SPEED;
+    transform.translation += (direction * speed * time.delta_seconds()).extend(0.);
+
+    for wheel in wheel_events.read() {
+        projection.scale *= FREE_CAMERA_ZOOM_STEP.powf(-wheel.y.signum());
+    }
+}
+
+/// Removes the effects applied in the previous frame, before the camera controller runs.
+pub fn remove_camera_effects(
+    mut camera_query: Query<(&mut Transform, &mut OrthographicProjection, &CameraEffects)>,
+) {
+    for (mut transform, mut projection, effects) in camera_query.iter_mut() {
+        transform.translation -= effects.applied_offset.extend(0.);
+        projection.scale /= effects.applied_scale;
+    }
+}
+
+/// Applies the shake and zoom, after the camera controller ran.
+pub fn apply_camera_effects(
+    mut camera_query: Query<(
+        &mut Transform,
+        &mut OrthographicProjection,
+        &mut CameraEffects,
+    )>,
+    mut shake_events: EventReader<CameraShake>,
+    mut zoom_events: EventReader<CameraZoomTo>,
+    mut pulse_events: EventReader<CameraZoomPulse>,
+    time: Res<Time>,
+) {
+    let Ok((mut transform, mut projection, mut effects)) = camera_query.get_single_mut() else {
+        return;
+    };
+
+    let trauma = effects.trauma - effects.trauma_decay * time.delta_seconds();
+    effects.trauma = shake_events
+        .read()
+        .fold(trauma, |trauma, shake| trauma + shake.trauma)
+        .clamp(0., 1.);
+
+    effects.zoom_timer.tick(time.delta());
+    for zoom in zoom_events.read() {
+        effects.zoom_from = zoom_scale(&effects);
+        effects.zoom_to = zoom.scale;
+        effects.zoom_timer = Timer::from_seconds(zoom.duration, TimerMode::Once);
+    }
+
+    effects.pulse_timer.tick(time.delta());
+    if let Some(pulse) = pulse_events.read().last() {
+        effects.pulse_scale = pulse.scale;
+        effects.pulse_timer = Timer::from_seconds(pulse.duration, TimerMode::Once);
+    }
+
+    // Sine waves of unrelated frequencies make a smooth shake that does not repeat noticeably
+    let seconds = time.elapsed_seconds();
+    let noise = Vec2::new(
+        (seconds * 37.).sin() * (seconds * 11.).cos(),
+        (seconds * 43.).sin() * (seconds * 13.).cos(),
+    );
+    effects.applied_offset = effects.max_offset * effects.trauma.powi(2) * noise;
+
+    // The pulse follows half a sine wave from no zoom to its scale and back
+    let pulse = (effects.pulse_timer.fraction() * std::f32::consts::PI).sin();
+    effects.applied_scale = zoom_scale(&effects) * 1f32.lerp(effects.pulse_scale, pulse);
+
+    transform.translation += effects.applied_offset.extend(0.);
+    projection.scale *= effects.applied_scale;
+}
+
+/// The scale the zoom has eased to so far.
+fn zoom_scale(effects: &CameraEffects) -> f32 {
+    let progress = effects.zoom_timer.fraction();
+    let eased = progress * progress * (3. - 2. * progress);
+    effects.zoom_from.lerp(effects.zoom_to, eased)
+}
@@ -1,8 +1,48 @@
 use clap::Parser;
+use std::net::SocketAddr;
 
 #[derive(Parser, Debug)]
 #[command(author, version, about)]
 pub struct CliArgs {
+    /// The address of the lobby server to find other players through, see `game-server`
     #[arg(short, long)]
     pub listen_address: Option<String>,
+    /// Draws the world at its native resolution, upscaled by whole multiples
+    #[arg(long)]
+    pub pixel_perfect: bool,
+    /// Times runs through every level and races the ghost of the personal best
+    #[arg(long)]
+    pub speedrun: bool,
+    /// The URL of the REST endpoint completed levels are submitted to
+    #[arg(long)]
+    pub leaderboard_url: Option<String>,
+    /// How far the sticks must be pushed before the player moves, from 0 to 1
+    #[arg(long, default_value_t = crate::input::DEFAULT_DEAD_ZONE)]
+    pub dead_zone: f32,
+    /// Puts the touch controls' d-pad on the right and the jump button on the left
+    #[arg(long)]
+    pub left_handed: bool,
+    /// Adds a second player sharing the screen, who plays with the arrow keys or a second gamepad
+    #[arg(long)]
+    pub co_op: bool,
+    /// The address of another player to play co-op with online, implies `--co-op`
+    #[arg(long)]
+    pub peer: Option<SocketAddr>,
+    /// The UDP port inputs are exchanged with the peer on, also when matched by the lobby
+    #[arg(long, default_value_t = crate::netcode::DEFAULT_ROLLBACK_PORT)]
+    pub rollback_port: u16,
+    /// Which of the two players is played on this machine when playing online
+    #[arg(long, default_value_t = 1, value_parser = clap::value_parser!(u8).range(1..=2))]
+    pub local_player: u8,
+    /// Adds a level editor to the main menu, which writes to the LDtk project in the assets
+    #[arg(long)]
+    pub editor: bool,
+}
+
+/// Runs the lobby server the games find each other through, without rendering.
+#[derive(Parser, Debug)]
+#[command(author, version, about)]
+pub struct ServerArgs {
+    #[arg(short, long, default_value = crate::lobby::DEFAULT_LOBBY_ADDRESS)]
+    pub listen_address: String,
 }
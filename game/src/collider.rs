@@ -2,6 +2,15 @@ use bevy::prelude::*;
 use bevy_ecs_ldtk::prelude::*;
 use bevy_rapier2d::prelude::*;
 
+/// The collision group of one-way platforms.
+///
+/// Excluding this group from the filters of a character controller lets the character
+/// fall through all one-way platforms.
+pub const ONE_WAY_PLATFORM_GROUP: Group = Group::GROUP_2;
+
+/// The collision group of the players, who walk through each other.
+pub const PLAYER_GROUP: Group = Group::GROUP_3;
+
 #[derive(Clone, Debug, Default, Bundle, LdtkIntCell)]
 pub struct ColliderBundle {
     pub collider: Collider,
@@ -0,0 +1,15 @@
+use bevy::prelude::*;
+use std::collections::HashMap;
+
+use crate::player::components::PlayerId;
+
+/// Whether the second player joins the game, added when playing co-op.
+#[derive(Resource, Debug, Default)]
+pub struct CoOp;
+
+/// The fruits each player collected since entering the game.
+#[derive(Resource, Default, Debug, Deref, DerefMut)]
+pub struct Scores(pub HashMap<PlayerId, u32>);
+
+#[derive(Component)]
+pub struct ScoreDisplay;
@@ -0,0 +1,44 @@
+pub mod components;
+pub mod systems;
+
+use bevy::prelude::*;
+
+use crate::coop::components::{CoOp, Scores};
+use crate::AppState;
+
+/// Tells the second player apart from the first one, who looks the same otherwise.
+pub const PLAYER_TWO_TINT: Color = Color::srgb(0.6, 1., 0.6);
+
+/// Adds a second local player sharing the screen with the first one, while [`CoOp`] exists.
+pub struct CoopPlugin;
+
+impl Plugin for CoopPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Scores>()
+            .add_systems(
+                OnEnter(AppState::InGame),
+                systems::spawn_score_display.run_if(resource_exists::<CoOp>),
+            )
+            .add_systems(
+                OnExit(AppState::InGame),
+                (systems::despawn_score_display, systems::reset_scores),
+            )
+            .add_systems(
+                Update,
+                (
+                    systems::assign_gamepads,
+                    systems::count_scores,
+                    systems::update_score_display,
+                )
+                    .run_if(in_state(AppState::InGame))
+                    .run_if(resource_exists::<CoOp>),
+            )
+            // The first player is moved to their start or quicksave before they are joined
+            .add_systems(
+                PostUpdate,
+                systems::spawn_player_two
+                    .run_if(in_state(AppState::InGame))
+                    .run_if(resource_exists::<CoOp>),
+            );
+    }
+}
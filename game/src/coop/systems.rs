@@ -0,0 +1,174 @@
+use bevy::prelude::*;
+use bevy_ecs_ldtk::prelude::*;
+use leafwing_input_manager::prelude::*;
+
+use crate::coop::components::{ScoreDisplay, Scores};
+use crate::coop::PLAYER_TWO_TINT;
+use crate::fruit::components::FruitCollected;
+use crate::player::components::{Health, Player, PlayerBundle, PlayerId};
+use crate::{color, Action};
+
+type FirstPlayer<'a> = (
+    Entity,
+    &'a EntityInstance,
+    &'a Transform,
+    &'a Handle<Image>,
+    &'a TextureAtlas,
+    Option<&'a Parent>,
+    &'a PlayerId,
+);
+
+/// Spawns the second player where the first one is, whenever the first one is spawned.
+pub fn spawn_player_two(mut commands: Commands, player_query: Query<FirstPlayer, Added<Player>>) {
+    for (player_one, entity_instance, transform, texture, atlas, world, id) in player_query.iter() {
+        if *id != PlayerId::One {
+            continue;
+        }
+        // The first player leaves the arrow keys to the second one
+        commands
+            .entity(player_one)
+            .insert(Action::co_op_player_one());
+        let sprite_sheet_bundle = LdtkSpriteSheetBundle {
+            sprite_bundle: SpriteBundle {
+                texture: texture.clone(),
+                sprite: Sprite {
+                    color: PLAYER_TWO_TINT,
+                    ..default()
+                },
+                transform: *transform,
+                ..default()
+            },
+            texture_atlas: atlas.clone(),
+        };
+        let player_two = commands
+            .spawn(PlayerBundle::player_two(
+                entity_instance,
+                sprite_sheet_bundle,
+            ))
+            .id();
+        // Like the first player, the second one is a child of the world and respawns with it
+        if let Some(world) = world {
+            commands.entity(world.get()).add_child(player_two);
+        }
+    }
+}
+
+/// Gives each player a gamepad of their own, in the order the gamepads were connected.
+pub fn assign_gamepads(
+    gamepads: Res<Gamepads>,
+    mut player_query: Query<(&PlayerId, &mut InputMap<Action>)>,
+) {
+    let mut connected: Vec<_> = gamepads.iter().collect();
+    connected.sort_by_key(|gamepad| gamepad.id);
+    for (id, mut input_map) in player_query.iter_mut() {
+        if let Some(gamepad) = connected.get(id.index()).copied() {
+            if input_map.gamepad() != Some(gamepad) {
+                input_map.set_gamepad(gamepad);
+            }
+        }
+    }
+}
+
+pub fn count_scores(mut collected_events: EventReader<FruitCollected>, mut scores: ResMut<Scores>) {
+    for event in collected_events.read() {
+        *scores.entry(event.player).or_default() += 1;
+    }
+}
+
+pub fn reset_scores(mut scores: ResMut<Scores>) {
+    scores.clear();
+}
+
+pub fn spawn_score_display(mut commands: Commands) {
+    commands.spawn((
+        TextBundle::from_section(
+            String::new(),
+            TextStyle {
+                font_size: 24.0,
+                color: color::PRIMARY_CONTENT,
+                ..default()
+            },
+        )
+        .with_style(Style {
+            position_type: PositionType::Absolute,
+            top: Val::Px(10.0),
+            left: Val::Percent(40.0),
+            ..default()
+        }),
+        ScoreDisplay,
+    ));
+}
+
+pub fn despawn_score_display(
+    mut commands: Commands,
+    display_query: Query<Entity, With<ScoreDisplay>>,
+) {
+    for display in display_query.iter() {
+        commands.entity(display).despawn_recursive();
+    }
+}
+
+/// Shows the health and the collected fruits of each player.
+pub fn update_score_display(
+    mut display_query: Query<&mut Text, With<ScoreDisplay>>,
+    player_query: Query<(&PlayerId, &Health), With<Player>>,
+    scores: Res<Scores>,
+) {
+    let Ok(mut text) = display_query.get_single_mut() else {
+        return;
+    };
+    let players: Vec<String> = PlayerId::ALL
+        .iter()
+        .map(|id| {
+            let health = player_query
+                .iter()
+                .find(|(player, _)| *player == id)
+                .map_or(0, |(_, health)| health.current);
+            let score = scores.get(id).copied().unwrap_or(0);
+            format!("P{}  {health} HP  {score} fruits", id.index() + 1)
+        })
+        .collect();
+    text.sections[0].value = players.join("     ");
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn joins_the_first_player_in_their_world() {
+        let mut world = World::new();
+        let ldtk_world = world.spawn_empty().id();
+        let player_one = world
+            .spawn((
+                Player,
+                PlayerId::One,
+                EntityInstance::default(),
+                Transform::from_xyz(16., 32., 1.),
+                Handle::<Image>::default(),
+                TextureAtlas::default(),
+            ))
+            .set_parent(ldtk_world)
+            .id();
+        let mut schedule = Schedule::default();
+        schedule.add_systems(spawn_player_two);
+        schedule.run(&mut world);
+
+        let mut players = world.query::<(Entity, &PlayerId, &Transform, &Parent)>();
+        let (player_two, _, transform, parent) = players
+            .iter(&world)
+            .find(|(_, id, ..)| **id == PlayerId::Two)
+            .unwrap();
+        assert_ne!(player_two, player_one);
+        assert_eq!(transform.translation, Vec3::new(16., 32., 1.));
+        assert_eq!(parent.get(), ldtk_world);
+        assert_eq!(
+            world.get::<Sprite>(player_two).unwrap().color,
+            PLAYER_TWO_TINT
+        );
+
+        // The second player does not join themselves
+        schedule.run(&mut world);
+        assert_eq!(players.iter(&world).count(), 2);
+    }
+}
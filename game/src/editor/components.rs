@@ -0,0 +1,102 @@
+use bevy::prelude::*;
+use std::path::PathBuf;
+
+use crate::fruit::FRUIT_IDENTIFIER;
+use crate::save::systems::START_IDENTIFIER;
+
+/// Where the LDtk project the editor writes to is.
+#[derive(Resource, Debug, Clone)]
+pub struct EditorSettings {
+    pub project: PathBuf,
+}
+
+/// What the mouse places and removes.
+#[derive(Resource, Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum EditorTool {
+    #[default]
+    Ground,
+    Fruit,
+    Trap,
+    /// The start of the level, which is also where the game continues from
+    Checkpoint,
+}
+
+impl EditorTool {
+    pub const ALL: [EditorTool; 4] = [
+        EditorTool::Ground,
+        EditorTool::Fruit,
+        EditorTool::Trap,
+        EditorTool::Checkpoint,
+    ];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            EditorTool::Ground => "Ground",
+            EditorTool::Fruit => "Fruit",
+            EditorTool::Trap => "Trap",
+            EditorTool::Checkpoint => "Checkpoint",
+        }
+    }
+
+    /// The identifier of the LDtk entity the tool places, if it places one.
+    pub fn identifier(&self) -> Option<&'static str> {
+        match self {
+            EditorTool::Ground => None,
+            EditorTool::Fruit => Some(FRUIT_IDENTIFIER),
+            EditorTool::Trap => Some("Fire"),
+            EditorTool::Checkpoint => Some(START_IDENTIFIER),
+        }
+    }
+}
+
+/// The point of the level under the mouse.
+#[derive(Resource, Debug, Default, Clone)]
+pub struct EditorCursor {
+    /// The iid of the level and the pixel in it, whose y axis points down
+    pub level: Option<(String, IVec2)>,
+    pub world: Vec2,
+    /// Whether the mouse is over a button, which keeps the last point
+    pub over_button: bool,
+}
+
+/// Drops the player at a point when playing from the editor.
+#[derive(Resource, Debug, Clone)]
+pub struct Playtest {
+    /// The iid of the level
+    pub level: String,
+    pub position: Vec2,
+}
+
+#[derive(Component, Debug)]
+pub struct EditorUi;
+
+/// Shows the tool, the history and the controls.
+#[derive(Component, Debug)]
+pub struct EditorStatus;
+
+/// Highlights what the mouse would place or remove.
+#[derive(Component, Debug)]
+pub struct CursorHighlight;
+
+/// Which action an editor button or key triggers.
+#[derive(Component, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EditorButton {
+    Tool(EditorTool),
+    Undo,
+    Redo,
+    /// Plays the level with the player dropped at the cursor
+    Play,
+    Back,
+}
+
+impl EditorButton {
+    pub fn label(&self) -> &'static str {
+        match self {
+            EditorButton::Tool(tool) => tool.label(),
+            EditorButton::Undo => "Undo",
+            EditorButton::Redo => "Redo",
+            EditorButton::Play => "Play",
+            EditorButton::Back => "Back",
+        }
+    }
+}
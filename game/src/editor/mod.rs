@@ -0,0 +1,92 @@
+pub mod components;
+pub mod project;
+pub mod systems;
+
+use bevy::prelude::*;
+use std::path::PathBuf;
+
+use crate::camera::systems::reset_camera;
+use crate::editor::components::{EditorCursor, EditorSettings, EditorTool, Playtest};
+use crate::editor::project::LdtkFile;
+use crate::quicksave::systems::apply_player_snapshot;
+use crate::save::systems::track_level;
+use crate::{replay, world, AppState};
+
+/// Edits the levels in the running game and writes them back to the LDtk project.
+pub struct EditorPlugin;
+
+impl Plugin for EditorPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(EditorSettings {
+            project: project_path(),
+        })
+        .init_resource::<EditorTool>()
+        .init_resource::<EditorCursor>()
+        .add_systems(
+            OnEnter(AppState::Editor),
+            (
+                world::systems::spawn,
+                // The history is kept while playing from the editor
+                systems::open_project.run_if(not(resource_exists::<LdtkFile>)),
+                systems::end_playtest,
+                systems::spawn_ui,
+            ),
+        )
+        .add_systems(
+            OnExit(AppState::Editor),
+            (systems::despawn_ui, world::systems::despawn),
+        )
+        .add_systems(
+            OnTransition {
+                exited: AppState::Editor,
+                entered: AppState::MainMenu,
+            },
+            systems::close_project,
+        )
+        .add_systems(
+            Update,
+            (
+                replay::systems::remove_players,
+                (
+                    systems::select_level_under_camera,
+                    systems::update_cursor,
+                    systems::update_highlight,
+                    systems::edit_with_mouse,
+                    systems::control_with_keyboard,
+                    systems::interact_with_editor_buttons,
+                    systems::update_status,
+                    systems::save_project,
+                )
+                    .chain()
+                    .run_if(resource_exists::<LdtkFile>),
+            )
+                .run_if(in_state(AppState::Editor)),
+        )
+        .add_systems(
+            Update,
+            (
+                systems::drop_players
+                    .before(reset_camera)
+                    .before(track_level)
+                    .before(apply_player_snapshot),
+                systems::return_to_editor,
+            )
+                .run_if(resource_exists::<Playtest>)
+                .run_if(in_state(AppState::InGame)),
+        );
+    }
+}
+
+/// Returns where the LDtk project is on disk, in the assets directory the game loads it from.
+#[cfg(not(target_arch = "wasm32"))]
+fn project_path() -> PathBuf {
+    bevy::asset::io::file::FileAssetReader::get_base_path()
+        .join("assets")
+        .join(world::LDTK_FILE)
+}
+
+/// The web has no file system, so opening the project fails.
+#[cfg(target_arch = "wasm32")]
+fn project_path() -> PathBuf {
+    PathBuf::from("assets").join(world::LDTK_FILE)
+}
@@ -0,0 +1,714 @@
+//! Edits the LDtk project file as raw JSON, so everything the game doesn't know about
+//! survives and the file stays compatible with the LDtk editor.
+
+use bevy::prelude::*;
+use serde_json::{json, Value};
+use std::cmp::Reverse;
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+use std::{fs, io};
+
+use crate::world::TERRAIN_LAYER;
+
+/// One change to the project, with what it replaced so it can be reverted.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Change {
+    /// An IntGrid cell of the terrain layer was set
+    Cell {
+        level: String,
+        index: usize,
+        from: i64,
+        to: i64,
+        /// The auto-layer tiles that were drawn for the cell before
+        tiles: Vec<Value>,
+    },
+    /// An entity instance was inserted at an index of its layer
+    Placed {
+        level: String,
+        index: usize,
+        instance: Value,
+    },
+    /// An entity instance was removed from an index of its layer
+    Removed {
+        level: String,
+        index: usize,
+        instance: Value,
+    },
+}
+
+/// The LDtk project that is being edited.
+#[derive(Resource, Debug)]
+pub struct LdtkFile {
+    pub path: PathBuf,
+    pub json: Value,
+    /// Whether there are changes that have not been written to the file
+    pub dirty: bool,
+}
+
+impl LdtkFile {
+    pub fn new(path: PathBuf, json: Value) -> Self {
+        Self {
+            path,
+            json,
+            dirty: false,
+        }
+    }
+
+    pub fn open(path: PathBuf) -> io::Result<Self> {
+        let json = serde_json::from_str(&fs::read_to_string(&path)?)?;
+        Ok(Self::new(path, json))
+    }
+
+    /// Writes the project back with the tab indentation of LDtk.
+    pub fn save(&mut self) -> io::Result<()> {
+        let mut contents = Vec::new();
+        let formatter = serde_json::ser::PrettyFormatter::with_indent(b"\t");
+        let mut serializer = serde_json::Serializer::with_formatter(&mut contents, formatter);
+        serde::Serialize::serialize(&self.json, &mut serializer)?;
+        // Writing to a temporary file first keeps the old file when writing fails halfway
+        let temporary = self.path.with_extension("tmp");
+        fs::write(&temporary, contents)?;
+        fs::rename(temporary, &self.path)?;
+        self.dirty = false;
+        Ok(())
+    }
+
+    fn level(&self, level: &str) -> Option<&Value> {
+        self.json["levels"]
+            .as_array()?
+            .iter()
+            .find(|candidate| candidate["iid"] == level)
+    }
+
+    /// Returns the iid of the level containing a point of the world, whose y axis points up.
+    pub fn level_at(&self, point: Vec2) -> Option<&str> {
+        self.json["levels"].as_array()?.iter().find_map(|level| {
+            let min = Vec2::new(
+                level["worldX"].as_f64()? as f32,
+                -(level["worldY"].as_f64()? + level["pxHei"].as_f64()?) as f32,
+            );
+            let size = Vec2::new(
+                level["pxWid"].as_f64()? as f32,
+                level["pxHei"].as_f64()? as f32,
+            );
+            Rect::from_corners(min, min + size)
+                .contains(point)
+                .then(|| level["iid"].as_str())
+                .flatten()
+        })
+    }
+
+    fn layer(&self, level: &str, layer_type: &str) -> Option<&Value> {
+        self.level(level)?["layerInstances"]
+            .as_array()?
+            .iter()
+            .find(|layer| is_layer(layer, layer_type))
+    }
+
+    fn layer_mut(&mut self, level: &str, layer_type: &str) -> Option<&mut Value> {
+        self.json["levels"]
+            .as_array_mut()?
+            .iter_mut()
+            .find(|candidate| candidate["iid"] == level)?["layerInstances"]
+            .as_array_mut()?
+            .iter_mut()
+            .find(|layer| is_layer(layer, layer_type))
+    }
+
+    fn entity_definition(&self, identifier: &str) -> Option<&Value> {
+        self.json["defs"]["entities"]
+            .as_array()?
+            .iter()
+            .find(|definition| definition["identifier"] == identifier)
+    }
+
+    /// Returns the size of the cells of the terrain layer in pixels.
+    pub fn grid_size(&self, level: &str) -> Option<i32> {
+        Some(self.layer(level, TERRAIN_LAYER)?["__gridSize"].as_i64()? as i32)
+    }
+
+    /// Returns the size of an entity in pixels.
+    pub fn entity_size(&self, identifier: &str) -> Option<IVec2> {
+        let definition = self.entity_definition(identifier)?;
+        Some(IVec2::new(
+            definition["width"].as_i64()? as i32,
+            definition["height"].as_i64()? as i32,
+        ))
+    }
+
+    /// Returns the value of the terrain cell at a pixel of a level, whose y axis points down.
+    pub fn cell(&self, level: &str, px: IVec2) -> Option<i64> {
+        let layer = self.layer(level, TERRAIN_LAYER)?;
+        let index = cell_index(layer, px)?;
+        layer["intGridCsv"][index].as_i64()
+    }
+
+    /// Sets the terrain cell at a pixel of a level.
+    ///
+    /// The cell is drawn with the tile most often drawn for its value, until LDtk applies
+    /// the rules of the auto-layer to it and its neighbours again.
+    pub fn set_cell(&mut self, level: &str, px: IVec2, value: i64) -> Option<Change> {
+        let layer = self.layer(level, TERRAIN_LAYER)?;
+        let index = cell_index(layer, px)?;
+        let from = layer["intGridCsv"][index].as_i64()?;
+        if from == value {
+            return None;
+        }
+        let tiles = self.write_cell(level, index, value)?;
+        Some(Change::Cell {
+            level: level.to_string(),
+            index,
+            from,
+            to: value,
+            tiles,
+        })
+    }
+
+    /// Writes a cell, returning the tiles that were drawn for it.
+    fn write_cell(&mut self, level: &str, index: usize, value: i64) -> Option<Vec<Value>> {
+        let layer = self.layer_mut(level, TERRAIN_LAYER)?;
+        let grid_size = layer["__gridSize"].as_i64()?;
+        let width = layer["__cWid"].as_u64()? as usize;
+        let cells = layer["intGridCsv"].as_array_mut()?;
+        *cells.get_mut(index)? = value.into();
+        let placeholder = (value != 0)
+            .then(|| most_common_tile(layer, value))
+            .flatten();
+        let tiles = layer["autoLayerTiles"].as_array_mut()?;
+        let (removed, kept): (Vec<_>, Vec<_>) = tiles
+            .drain(..)
+            .partition(|tile| tile["d"][1].as_u64() == Some(index as u64));
+        *tiles = kept;
+        if let Some(mut tile) = placeholder {
+            let (x, y) = ((index % width) as i64, (index / width) as i64);
+            tile["px"] = json!([x * grid_size, y * grid_size]);
+            tile["d"][1] = index.into();
+            tiles.push(tile);
+        }
+        self.dirty = true;
+        Some(removed)
+    }
+
+    /// Returns the index and instance of the topmost entity covering a pixel of a level.
+    pub fn entity_at(&self, level: &str, px: IVec2) -> Option<(usize, &Value)> {
+        let instances = self.layer(level, "Entities")?["entityInstances"].as_array()?;
+        instances
+            .iter()
+            .enumerate()
+            .rev()
+            .find(|(_, instance)| entity_rect(instance).is_some_and(|rect| rect.contains(px)))
+    }
+
+    /// Removes the topmost entity covering a pixel of a level.
+    pub fn remove_entity_at(&mut self, level: &str, px: IVec2) -> Option<Change> {
+        let (index, _) = self.entity_at(level, px)?;
+        self.remove_entity(level, index)
+    }
+
+    fn remove_entity(&mut self, level: &str, index: usize) -> Option<Change> {
+        let instances = self.layer_mut(level, "Entities")?["entityInstances"].as_array_mut()?;
+        let instance = (index < instances.len()).then(|| instances.remove(index))?;
+        self.dirty = true;
+        Some(Change::Removed {
+            level: level.to_string(),
+            index,
+            instance,
+        })
+    }
+
+    fn insert_entity(&mut self, level: &str, index: usize, instance: Value) -> Option<()> {
+        let instances = self.layer_mut(level, "Entities")?["entityInstances"].as_array_mut()?;
+        instances.insert(index.min(instances.len()), instance);
+        self.dirty = true;
+        Some(())
+    }
+
+    /// Places an entity with its top left corner in the cell at a pixel of a level.
+    ///
+    /// Like in LDtk, an entity limited to a number of instances per level replaces
+    /// the oldest one once the limit is reached, unless it prevents adding more.
+    pub fn place_entity(&mut self, level: &str, identifier: &str, px: IVec2) -> Vec<Change> {
+        let Some(instance) = self.new_entity(level, identifier, px) else {
+            return Vec::new();
+        };
+        let mut changes = Vec::new();
+        let definition = &self
+            .entity_definition(identifier)
+            .cloned()
+            .unwrap_or_default();
+        let max_count = definition["maxCount"].as_u64().unwrap_or_default() as usize;
+        if max_count > 0 && definition["limitScope"] == "PerLevel" {
+            let existing = self
+                .layer(level, "Entities")
+                .and_then(|layer| layer["entityInstances"].as_array())
+                .into_iter()
+                .flatten()
+                .enumerate()
+                .filter(|(_, instance)| instance["__identifier"] == identifier)
+                .map(|(index, _)| index)
+                .collect::<Vec<_>>();
+            if existing.len() >= max_count {
+                if definition["limitBehavior"] == "PreventAdding" {
+                    return changes;
+                }
+                changes.extend(self.remove_entity(level, existing[0]));
+            }
+        }
+        let Some(instances) = self
+            .layer(level, "Entities")
+            .and_then(|layer| layer["entityInstances"].as_array())
+        else {
+            return changes;
+        };
+        let index = instances.len();
+        if self.insert_entity(level, index, instance.clone()).is_some() {
+            changes.push(Change::Placed {
+                level: level.to_string(),
+                index,
+                instance,
+            });
+        }
+        changes
+    }
+
+    /// Creates an instance of an entity, copying the fields of an existing instance
+    /// or falling back to the defaults of its definition.
+    fn new_entity(&self, level: &str, identifier: &str, px: IVec2) -> Option<Value> {
+        let definition = self.entity_definition(identifier)?;
+        let layer = self.layer(level, "Entities")?;
+        let grid_size = layer["__gridSize"].as_i64()? as i32;
+        let level_data = self.level(level)?;
+        let size = IVec2::new(
+            definition["width"].as_i64()? as i32,
+            definition["height"].as_i64()? as i32,
+        );
+        let pivot = Vec2::new(
+            definition["pivotX"].as_f64().unwrap_or_default() as f32,
+            definition["pivotY"].as_f64().unwrap_or_default() as f32,
+        );
+        let grid = px.div_euclid(IVec2::splat(grid_size));
+        let position = grid * grid_size + (pivot * size.as_vec2()).round().as_ivec2();
+
+        let template = self.json["levels"]
+            .as_array()?
+            .iter()
+            .flat_map(|level| level["layerInstances"].as_array().into_iter().flatten())
+            .flat_map(|layer| layer["entityInstances"].as_array().into_iter().flatten())
+            .find(|instance| instance["__identifier"] == identifier);
+        let mut instance = template.cloned().unwrap_or_else(|| {
+            json!({
+                "__identifier": identifier,
+                "__pivot": [pivot.x, pivot.y],
+                "__tags": definition["tags"],
+                "__tile": definition["tileRect"],
+                "__smartColor": definition["color"],
+                "width": size.x,
+                "height": size.y,
+                "defUid": definition["uid"],
+                "fieldInstances": [],
+            })
+        });
+        instance["__grid"] = json!([grid.x, grid.y]);
+        instance["iid"] = new_iid().into();
+        instance["px"] = json!([position.x, position.y]);
+        instance["__worldX"] = (level_data["worldX"].as_i64()? + position.x as i64).into();
+        instance["__worldY"] = (level_data["worldY"].as_i64()? + position.y as i64).into();
+        Some(instance)
+    }
+
+    /// Undoes a change.
+    pub fn revert(&mut self, change: &Change) {
+        match change {
+            Change::Cell {
+                level,
+                index,
+                from,
+                tiles,
+                ..
+            } => {
+                self.write_cell(level, *index, *from);
+                if let Some(auto_tiles) = self
+                    .layer_mut(level, TERRAIN_LAYER)
+                    .and_then(|layer| layer["autoLayerTiles"].as_array_mut())
+                {
+                    auto_tiles.retain(|tile| tile["d"][1].as_u64() != Some(*index as u64));
+                    auto_tiles.extend(tiles.iter().cloned());
+                }
+            }
+            Change::Placed { level, index, .. } => {
+                self.remove_entity(level, *index);
+            }
+            Change::Removed {
+                level,
+                index,
+                instance,
+            } => {
+                self.insert_entity(level, *index, instance.clone());
+            }
+        }
+    }
+
+    /// Makes a reverted change again.
+    pub fn reapply(&mut self, change: &Change) {
+        match change {
+            Change::Cell {
+                level, index, to, ..
+            } => {
+                self.write_cell(level, *index, *to);
+            }
+            Change::Placed {
+                level,
+                index,
+                instance,
+            } => {
+                self.insert_entity(level, *index, instance.clone());
+            }
+            Change::Removed { level, index, .. } => {
+                self.remove_entity(level, *index);
+            }
+        }
+    }
+}
+
+fn is_layer(layer: &Value, layer_type: &str) -> bool {
+    // The entity layer is found by its type, the IntGrid layers by their identifier
+    layer["__identifier"] == layer_type || layer["__type"] == layer_type
+}
+
+fn cell_index(layer: &Value, px: IVec2) -> Option<usize> {
+    let grid_size = layer["__gridSize"].as_i64()? as i32;
+    let size = IVec2::new(
+        layer["__cWid"].as_i64()? as i32,
+        layer["__cHei"].as_i64()? as i32,
+    );
+    let cell = px.div_euclid(IVec2::splat(grid_size));
+    (cell.cmpge(IVec2::ZERO).all() && cell.cmplt(size).all())
+        .then(|| (cell.y * size.x + cell.x) as usize)
+}
+
+/// Returns the area an entity instance covers in the pixels of its level.
+fn entity_rect(instance: &Value) -> Option<IRect> {
+    let size = IVec2::new(
+        instance["width"].as_i64()? as i32,
+        instance["height"].as_i64()? as i32,
+    );
+    let pivot = Vec2::new(
+        instance["__pivot"][0].as_f64().unwrap_or_default() as f32,
+        instance["__pivot"][1].as_f64().unwrap_or_default() as f32,
+    );
+    let px = IVec2::new(
+        instance["px"][0].as_i64()? as i32,
+        instance["px"][1].as_i64()? as i32,
+    );
+    let min = px - (pivot * size.as_vec2()).round().as_ivec2();
+    // The far edges are exclusive, so neighbouring entities don't overlap
+    Some(IRect::from_corners(min, min + size - IVec2::ONE))
+}
+
+/// Returns the tile the auto-layer draws most often for cells with a value.
+fn most_common_tile(layer: &Value, value: i64) -> Option<Value> {
+    let cells = layer["intGridCsv"].as_array()?;
+    // Keyed by the source of the tile and the rule drawing it
+    let mut counts: BTreeMap<(i64, i64, i64), (usize, &Value)> = BTreeMap::new();
+    for tile in layer["autoLayerTiles"].as_array()? {
+        let Some(index) = tile["d"][1].as_u64() else {
+            continue;
+        };
+        if cells.get(index as usize).and_then(Value::as_i64) == Some(value) {
+            let key = (
+                tile["src"][0].as_i64().unwrap_or_default(),
+                tile["src"][1].as_i64().unwrap_or_default(),
+                tile["d"][0].as_i64().unwrap_or_default(),
+            );
+            counts.entry(key).or_insert((0, tile)).0 += 1;
+        }
+    }
+    // Ties go to the smallest key, so the same edit always writes the same tile
+    counts
+        .into_iter()
+        .max_by_key(|(key, (count, _))| (*count, Reverse(*key)))
+        .map(|(_, (_, tile))| tile.clone())
+}
+
+/// Generates a random version 4 UUID, which LDtk uses to identify instances.
+fn new_iid() -> String {
+    let bytes: [u8; 16] = rand::random();
+    let hex = |range: std::ops::Range<usize>| {
+        bytes[range]
+            .iter()
+            .map(|byte| format!("{byte:02x}"))
+            .collect::<String>()
+    };
+    format!(
+        "{}-{}-4{}-{:x}{}-{}",
+        hex(0..4),
+        hex(4..6),
+        &hex(6..8)[1..],
+        8 | (bytes[8] & 0x3),
+        &hex(8..10)[1..],
+        hex(10..16)
+    )
+}
+
+/// The changes made to the project, grouped into the steps they are undone in.
+#[derive(Resource, Debug, Default)]
+pub struct EditHistory {
+    pub undo: Vec<Vec<Change>>,
+    pub redo: Vec<Vec<Change>>,
+}
+
+impl EditHistory {
+    /// Starts a new step, such as a stroke of the mouse.
+    pub fn begin(&mut self) {
+        if !matches!(self.undo.last(), Some(step) if step.is_empty()) {
+            self.undo.push(Vec::new());
+        }
+    }
+
+    /// Adds changes to the current step.
+    pub fn record(&mut self, changes: impl IntoIterator<Item = Change>) {
+        let mut changes = changes.into_iter().peekable();
+        if changes.peek().is_none() {
+            return;
+        }
+        self.redo.clear();
+        match self.undo.last_mut() {
+            Some(step) => step.extend(changes),
+            None => self.undo.push(changes.collect()),
+        }
+    }
+
+    /// Reverts the last step, returning whether there was one.
+    pub fn undo(&mut self, file: &mut LdtkFile) -> bool {
+        while let Some(step) = self.undo.pop() {
+            if step.is_empty() {
+                continue;
+            }
+            for change in step.iter().rev() {
+                file.revert(change);
+            }
+            self.redo.push(step);
+            return true;
+        }
+        false
+    }
+
+    /// Makes the last undone step again, returning whether there was one.
+    pub fn redo(&mut self, file: &mut LdtkFile) -> bool {
+        let Some(step) = self.redo.pop() else {
+            return false;
+        };
+        for change in &step {
+            file.reapply(change);
+        }
+        self.undo.push(step);
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const LEVEL: &str = "level";
+
+    /// A level of 4x2 cells of 16 pixels, with ground in the bottom row and one start.
+    fn project() -> LdtkFile {
+        let tile = |index: usize| json!({ "px": [index % 4 * 16, index / 4 * 16], "src": [112,16], "f": 0, "t": 29, "d": [21, index], "a": 1 });
+        LdtkFile::new(
+            PathBuf::from("test.ldtk"),
+            json!({
+                "defs": { "entities": [
+                    { "identifier": "Start", "uid": 45, "width": 32, "height": 32, "pivotX": 0, "pivotY": 0,
+                      "tags": [], "color": "#63C74D", "tileRect": null,
+                      "maxCount": 1, "limitScope": "PerLevel", "limitBehavior": "MoveLastOne" },
+                    { "identifier": "Cherry", "uid": 2, "width": 32, "height": 32, "pivotX": 0, "pivotY": 0,
+                      "tags": [], "color": "#BE4A2F", "tileRect": null,
+                      "maxCount": 0, "limitScope": "PerLevel", "limitBehavior": "MoveLastOne" },
+                ] },
+                "levels": [{
+                    "iid": LEVEL, "worldX": 100, "worldY": 200, "pxWid": 64, "pxHei": 32,
+                    "layerInstances": [
+                        { "__identifier": "Entities", "__type": "Entities", "__gridSize": 16,
+                          "entityInstances": [
+                              { "__identifier": "Start", "__grid": [0, 0], "__pivot": [0, 0], "iid": "start",
+                                "width": 32, "height": 32, "defUid": 45, "px": [0, 0], "fieldInstances": [] },
+                          ] },
+                        { "__identifier": TERRAIN_LAYER, "__type": "IntGrid", "__gridSize": 16,
+                          "__cWid": 4, "__cHei": 2, "intGridCsv": [0, 0, 0, 0, 1, 1, 1, 1],
+                          "autoLayerTiles": (4..8).map(tile).collect::<Vec<_>>() },
+                    ],
+                }],
+            }),
+        )
+    }
+
+    #[test]
+    fn levels_are_found_at_their_world_location() {
+        let file = project();
+        assert_eq!(file.level_at(Vec2::new(110., -210.)), Some(LEVEL));
+        assert_eq!(file.level_at(Vec2::new(110., -190.)), None);
+        assert_eq!(file.level_at(Vec2::new(170., -210.)), None);
+    }
+
+    #[test]
+    fn setting_a_cell_swaps_its_tiles_and_reverts() {
+        let mut file = project();
+        let original = file.json.clone();
+
+        let change = file.set_cell(LEVEL, IVec2::new(20, 5), 1).unwrap();
+        assert_eq!(file.cell(LEVEL, IVec2::new(20, 5)), Some(1));
+        let tiles = file.json["levels"][0]["layerInstances"][1]["autoLayerTiles"].clone();
+        assert_eq!(tiles.as_array().unwrap().len(), 5);
+        assert_eq!(tiles[4]["px"], json!([16, 0]));
+        assert_eq!(tiles[4]["d"], json!([21, 1]));
+        assert!(file.set_cell(LEVEL, IVec2::new(20, 5), 1).is_none());
+        assert!(file.set_cell(LEVEL, IVec2::new(64, 0), 1).is_none());
+
+        file.revert(&change);
+        assert_eq!(file.json, original);
+        file.reapply(&change);
+        assert_eq!(
+            file.json["levels"][0]["layerInstances"][1]["autoLayerTiles"],
+            tiles
+        );
+    }
+
+    #[test]
+    fn removing_ground_removes_its_tiles() {
+        let mut file = project();
+        let change = file.set_cell(LEVEL, IVec2::new(0, 16), 0).unwrap();
+        let Change::Cell { tiles, .. } = &change else {
+            panic!("expected a cell change");
+        };
+        assert_eq!(tiles.len(), 1);
+        assert_eq!(
+            file.json["levels"][0]["layerInstances"][1]["autoLayerTiles"]
+                .as_array()
+                .unwrap()
+                .len(),
+            3
+        );
+    }
+
+    #[test]
+    fn placing_a_limited_entity_replaces_the_oldest() {
+        let mut file = project();
+        let original = file.json.clone();
+
+        let changes = file.place_entity(LEVEL, "Start", IVec2::new(40, 20));
+        assert_eq!(changes.len(), 2);
+        assert!(matches!(changes[0], Change::Removed { index: 0, .. }));
+        let instances = &file.json["levels"][0]["layerInstances"][0]["entityInstances"];
+        assert_eq!(instances.as_array().unwrap().len(), 1);
+        assert_eq!(instances[0]["px"], json!([32, 16]));
+        assert_eq!(instances[0]["__grid"], json!([2, 1]));
+        assert_eq!(instances[0]["__worldX"], 132);
+        assert_eq!(instances[0]["__worldY"], 216);
+        assert_ne!(instances[0]["iid"], "start");
+
+        for change in changes.iter().rev() {
+            file.revert(change);
+        }
+        assert_eq!(file.json, original);
+    }
+
+    #[test]
+    fn undoing_a_replacement_restores_the_order_and_iids() {
+        let mut file = project();
+        let mut history = EditHistory::default();
+        let iids = |file: &LdtkFile| {
+            file.json["levels"][0]["layerInstances"][0]["entityInstances"]
+                .as_array()
+                .unwrap()
+                .iter()
+                .map(|instance| instance["iid"].as_str().unwrap().to_string())
+                .collect::<Vec<_>>()
+        };
+        history.begin();
+        history.record(file.place_entity(LEVEL, "Cherry", IVec2::new(32, 0)));
+        let cherry = iids(&file)[1].clone();
+        let original = file.json.clone();
+
+        history.begin();
+        history.record(file.place_entity(LEVEL, "Start", IVec2::new(0, 16)));
+        let first_start = iids(&file)[1].clone();
+        assert_eq!(iids(&file), [cherry.clone(), first_start.clone()]);
+        history.begin();
+        history.record(file.place_entity(LEVEL, "Start", IVec2::new(16, 16)));
+        let second_start = iids(&file)[1].clone();
+        assert_eq!(iids(&file), [cherry.clone(), second_start.clone()]);
+        let edited = file.json.clone();
+
+        assert!(history.undo(&mut file));
+        assert_eq!(iids(&file), [cherry.clone(), first_start]);
+        assert!(history.undo(&mut file));
+        assert_eq!(iids(&file), ["start".to_string(), cherry]);
+        assert_eq!(file.json, original);
+
+        assert!(history.redo(&mut file));
+        assert!(history.redo(&mut file));
+        assert_eq!(file.json, edited);
+    }
+
+    #[test]
+    fn placeholder_ties_go_to_the_smallest_tile() {
+        let mut file = project();
+        let tiles = &mut file.json["levels"][0]["layerInstances"][1]["autoLayerTiles"];
+        for tile in tiles.as_array_mut().unwrap().iter_mut().take(2) {
+            tile["src"] = json!([96, 0]);
+            tile["d"][0] = 5.into();
+        }
+
+        file.set_cell(LEVEL, IVec2::new(0, 0), 1);
+        let tiles = &file.json["levels"][0]["layerInstances"][1]["autoLayerTiles"];
+        assert_eq!(tiles[4]["src"], json!([96, 0]));
+        assert_eq!(tiles[4]["d"], json!([5, 0]));
+    }
+
+    #[test]
+    fn entities_are_removed_where_they_are_hit() {
+        let mut file = project();
+        file.place_entity(LEVEL, "Cherry", IVec2::new(20, 0));
+
+        assert!(file.entity_at(LEVEL, IVec2::new(47, 31)).is_some());
+        assert!(file.entity_at(LEVEL, IVec2::new(48, 31)).is_none());
+        assert!(file.entity_at(LEVEL, IVec2::new(40, 32)).is_none());
+        let Some(Change::Removed { instance, .. }) =
+            file.remove_entity_at(LEVEL, IVec2::new(20, 5))
+        else {
+            panic!("expected a removal");
+        };
+        // The cherry is on top of the start it overlaps
+        assert_eq!(instance["__identifier"], "Cherry");
+    }
+
+    #[test]
+    fn history_undoes_and_redoes_whole_steps() {
+        let mut file = project();
+        let original = file.json.clone();
+        let mut history = EditHistory::default();
+
+        history.begin();
+        history.record(file.set_cell(LEVEL, IVec2::new(0, 0), 1));
+        history.record(file.set_cell(LEVEL, IVec2::new(16, 0), 1));
+        history.begin();
+        history.record(file.place_entity(LEVEL, "Cherry", IVec2::new(32, 0)));
+        let edited = file.json.clone();
+        history.begin();
+
+        assert!(history.undo(&mut file));
+        assert!(history.undo(&mut file));
+        assert_eq!(file.json, original);
+        assert!(!history.undo(&mut file));
+
+        assert!(history.redo(&mut file));
+        assert!(history.redo(&mut file));
+        assert_eq!(file.json, edited);
+        assert!(!history.redo(&mut file));
+
+        history.undo(&mut file);
+        history.record(file.set_cell(LEVEL, IVec2::new(48, 0), 1));
+        assert!(history.redo.is_empty());
+    }
+}
@@ -0,0 +1,452 @@
+use bevy::prelude::*;
+use bevy::sprite::Anchor;
+use bevy::window::PrimaryWindow;
+use bevy_ecs_ldtk::prelude::*;
+
+use crate::camera::components::{CameraController, FreeCamera};
+use crate::editor::components::{
+    CursorHighlight, EditorButton, EditorCursor, EditorSettings, EditorStatus, EditorTool,
+    EditorUi, Playtest,
+};
+use crate::editor::project::{EditHistory, LdtkFile};
+use crate::player::components::Player;
+use crate::transition::components::{StartTransition, TransitionStyle, TransitionTarget};
+use crate::world::{SpawnedLevels, LDTK_FILE};
+use crate::{color, AppState};
+
+/// The IntGrid value of the ground in the terrain layer.
+const GROUND: i64 = 1;
+
+pub fn open_project(mut commands: Commands, settings: Res<EditorSettings>) {
+    match LdtkFile::open(settings.project.clone()) {
+        Ok(file) => {
+            commands.insert_resource(file);
+            commands.insert_resource(EditHistory::default());
+        }
+        Err(error) => error!(
+            "Could not open the project {}: {error}",
+            settings.project.display()
+        ),
+    }
+}
+
+/// Forgets the project and its history when leaving the editor for the main menu.
+pub fn close_project(mut commands: Commands) {
+    commands.remove_resource::<LdtkFile>();
+    commands.remove_resource::<EditHistory>();
+}
+
+/// Writes the changes to the project once the mouse is released and reloads the levels,
+/// so a stroke is written at once.
+pub fn save_project(
+    mut file: ResMut<LdtkFile>,
+    mouse_input: Res<ButtonInput<MouseButton>>,
+    asset_server: Res<AssetServer>,
+) {
+    if !file.dirty || mouse_input.any_pressed([MouseButton::Left, MouseButton::Right]) {
+        return;
+    }
+    match file.save() {
+        Ok(()) => asset_server.reload(LDTK_FILE),
+        Err(error) => {
+            error!("Could not save the project: {error}");
+            // Trying again every frame would flood the log
+            file.dirty = false;
+        }
+    }
+}
+
+pub fn spawn_ui(mut commands: Commands) {
+    let text_style = |font_size| TextStyle {
+        font_size,
+        color: color::PRIMARY_CONTENT,
+        ..default()
+    };
+    commands.insert_resource(FreeCamera);
+    commands.spawn((
+        SpriteBundle {
+            sprite: Sprite {
+                color: color::PRIMARY.with_alpha(0.5),
+                anchor: Anchor::TopLeft,
+                ..default()
+            },
+            visibility: Visibility::Hidden,
+            ..default()
+        },
+        CursorHighlight,
+    ));
+
+    commands
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    flex_direction: FlexDirection::Column,
+                    justify_content: JustifyContent::SpaceBetween,
+                    width: Val::Percent(100.0),
+                    height: Val::Percent(100.0),
+                    padding: UiRect::all(Val::Px(10.0)),
+                    ..default()
+                },
+                ..default()
+            },
+            EditorUi,
+        ))
+        .with_children(|parent| {
+            parent.spawn((TextBundle::from_section("", text_style(20.0)), EditorStatus));
+
+            // Buttons
+            parent
+                .spawn(NodeBundle {
+                    style: Style {
+                        flex_direction: FlexDirection::Row,
+                        justify_content: JustifyContent::Center,
+                        column_gap: Val::Px(10.0),
+                        ..default()
+                    },
+                    ..default()
+                })
+                .with_children(|parent| {
+                    let buttons = EditorTool::ALL.map(EditorButton::Tool).into_iter().chain([
+                        EditorButton::Undo,
+                        EditorButton::Redo,
+                        EditorButton::Play,
+                        EditorButton::Back,
+                    ]);
+                    for button in buttons {
+                        parent
+                            .spawn((
+                                ButtonBundle {
+                                    background_color: color::PRIMARY.into(),
+                                    style: Style {
+                                        justify_content: JustifyContent::Center,
+                                        align_items: AlignItems::Center,
+                                        min_width: Val::Px(56.0),
+                                        height: Val::Px(48.0),
+                                        padding: UiRect::horizontal(Val::Px(10.0)),
+                                        ..default()
+                                    },
+                                    ..default()
+                                },
+                                button,
+                            ))
+                            .with_children(|parent| {
+                                parent.spawn(TextBundle::from_section(
+                                    button.label(),
+                                    text_style(24.0),
+                                ));
+                            });
+                    }
+                });
+        });
+}
+
+type EditorEntities = Or<(With<EditorUi>, With<CursorHighlight>)>;
+
+pub fn despawn_ui(mut commands: Commands, query: Query<Entity, EditorEntities>) {
+    for entity in query.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+    commands.remove_resource::<FreeCamera>();
+}
+
+/// Loads the levels around the camera as it is moved.
+pub fn select_level_under_camera(
+    camera_query: Query<&GlobalTransform, With<CameraController>>,
+    file: Res<LdtkFile>,
+    mut level_selection: ResMut<LevelSelection>,
+) {
+    let Ok(camera_transform) = camera_query.get_single() else {
+        return;
+    };
+    // The levels that are not spawned are found in the project
+    let Some(level) = file.level_at(camera_transform.translation().truncate()) else {
+        return;
+    };
+    let selection = LevelSelection::iid(level);
+    if *level_selection != selection {
+        *level_selection = selection;
+    }
+}
+
+/// Finds the point of the level under the mouse.
+pub fn update_cursor(
+    window_query: Query<&Window, With<PrimaryWindow>>,
+    camera_query: Query<(&Camera, &GlobalTransform), With<CameraController>>,
+    button_query: Query<&Interaction, With<EditorButton>>,
+    spawned_levels: SpawnedLevels,
+    mut cursor: ResMut<EditorCursor>,
+) {
+    cursor.over_button = button_query
+        .iter()
+        .any(|interaction| *interaction != Interaction::None);
+    if cursor.over_button {
+        return;
+    }
+    cursor.level = None;
+    let Some(world) = window_query
+        .get_single()
+        .ok()
+        .and_then(Window::cursor_position)
+        .zip(camera_query.get_single().ok())
+        .and_then(|(position, (camera, camera_transform))| {
+            camera.viewport_to_world_2d(camera_transform, position)
+        })
+    else {
+        return;
+    };
+    let Some((bounds, level)) = spawned_levels.containing(world) else {
+        return;
+    };
+    // LDtk's y axis points down from the top of the level
+    let px = IVec2::new(
+        (world.x - bounds.min.x).floor() as i32,
+        (bounds.max.y - world.y).floor() as i32,
+    );
+    cursor.world = world;
+    cursor.level = Some((level.iid.clone(), px));
+}
+
+/// Highlights the cells the tool would edit at the cursor.
+pub fn update_highlight(
+    mut highlight_query: Query<
+        (&mut Transform, &mut Sprite, &mut Visibility),
+        With<CursorHighlight>,
+    >,
+    cursor: Res<EditorCursor>,
+    file: Res<LdtkFile>,
+    tool: Res<EditorTool>,
+) {
+    let Ok((mut transform, mut sprite, mut visibility)) = highlight_query.get_single_mut() else {
+        return;
+    };
+    let Some((px, grid_size)) = cursor
+        .level
+        .as_ref()
+        .filter(|_| !cursor.over_button)
+        .and_then(|(level, px)| Some((*px, file.grid_size(level)?)))
+    else {
+        *visibility = Visibility::Hidden;
+        return;
+    };
+    let size = tool
+        .identifier()
+        .and_then(|identifier| file.entity_size(identifier))
+        .unwrap_or(IVec2::splat(grid_size));
+    // The corner of the cell under the cursor, moving up in the world is moving down in LDtk
+    let offset = (px - px.div_euclid(IVec2::splat(grid_size)) * grid_size).as_vec2();
+    transform.translation = Vec3::new(
+        (cursor.world.x - offset.x).floor(),
+        (cursor.world.y + offset.y).ceil(),
+        50.,
+    );
+    sprite.custom_size = Some(size.as_vec2());
+    *visibility = Visibility::Visible;
+}
+
+/// Places with the left mouse button and removes with the right one.
+///
+/// The ground is painted for as long as a button is held, which is undone as one step.
+pub fn edit_with_mouse(
+    mouse_input: Res<ButtonInput<MouseButton>>,
+    cursor: Res<EditorCursor>,
+    tool: Res<EditorTool>,
+    mut file: ResMut<LdtkFile>,
+    mut history: ResMut<EditHistory>,
+) {
+    if mouse_input.any_just_pressed([MouseButton::Left, MouseButton::Right]) {
+        history.begin();
+    }
+    let Some((level, px)) = cursor.level.as_ref().filter(|_| !cursor.over_button) else {
+        return;
+    };
+    let place = mouse_input.pressed(MouseButton::Left);
+    let remove = mouse_input.pressed(MouseButton::Right);
+    match tool.identifier() {
+        None if place || remove => {
+            let value = if place { GROUND } else { 0 };
+            let change = file.set_cell(level, *px, value);
+            history.record(change);
+        }
+        Some(identifier) if mouse_input.just_pressed(MouseButton::Left) => {
+            let changes = file.place_entity(level, identifier, *px);
+            history.record(changes);
+        }
+        Some(_) if mouse_input.just_pressed(MouseButton::Right) => {
+            let change = file.remove_entity_at(level, *px);
+            history.record(change);
+        }
+        _ => {}
+    }
+}
+
+/// Which action an editor button or key triggers.
+fn apply_control(
+    button: EditorButton,
+    commands: &mut Commands,
+    file: &mut LdtkFile,
+    history: &mut EditHistory,
+    cursor: &EditorCursor,
+    transition_events: &mut EventWriter<StartTransition>,
+) {
+    match button {
+        EditorButton::Tool(tool) => commands.insert_resource(tool),
+        EditorButton::Undo => {
+            history.undo(file);
+        }
+        EditorButton::Redo => {
+            history.redo(file);
+        }
+        EditorButton::Play => {
+            let Some((level, _)) = &cursor.level else {
+                info!("Point at the level to play from");
+                return;
+            };
+            commands.insert_resource(Playtest {
+                level: level.clone(),
+                position: cursor.world,
+            });
+            transition_events.send(StartTransition {
+                target: TransitionTarget::App(AppState::InGame),
+                style: TransitionStyle::Fade,
+            });
+        }
+        EditorButton::Back => {
+            transition_events.send(StartTransition {
+                target: TransitionTarget::App(AppState::MainMenu),
+                style: TransitionStyle::Fade,
+            });
+        }
+    }
+}
+
+/// Picks the tools with 1 to 4, undoes with Ctrl+Z, redoes with Ctrl+Y or Ctrl+Shift+Z,
+/// plays from the cursor with P and leaves with escape.
+pub fn control_with_keyboard(
+    mut commands: Commands,
+    keyboard_input: Res<ButtonInput<KeyCode>>,
+    mut file: ResMut<LdtkFile>,
+    mut history: ResMut<EditHistory>,
+    cursor: Res<EditorCursor>,
+    mut transition_events: EventWriter<StartTransition>,
+) {
+    let control = keyboard_input.any_pressed([KeyCode::ControlLeft, KeyCode::ControlRight]);
+    let shift = keyboard_input.any_pressed([KeyCode::ShiftLeft, KeyCode::ShiftRight]);
+    let keys = [
+        (KeyCode::Digit1, EditorButton::Tool(EditorTool::Ground)),
+        (KeyCode::Digit2, EditorButton::Tool(EditorTool::Fruit)),
+        (KeyCode::Digit3, EditorButton::Tool(EditorTool::Trap)),
+        (KeyCode::Digit4, EditorButton::Tool(EditorTool::Checkpoint)),
+        (KeyCode::KeyP, EditorButton::Play),
+        (KeyCode::Escape, EditorButton::Back),
+    ];
+    let mut buttons = keys
+        .into_iter()
+        .filter(|(key, _)| keyboard_input.just_pressed(*key))
+        .map(|(_, button)| button)
+        .collect::<Vec<_>>();
+    if control && keyboard_input.just_pressed(KeyCode::KeyZ) {
+        buttons.push(if shift {
+            EditorButton::Redo
+        } else {
+            EditorButton::Undo
+        });
+    }
+    if control && keyboard_input.just_pressed(KeyCode::KeyY) {
+        buttons.push(EditorButton::Redo);
+    }
+    for button in buttons {
+        apply_control(
+            button,
+            &mut commands,
+            &mut file,
+            &mut history,
+            &cursor,
+            &mut transition_events,
+        );
+    }
+}
+
+type ButtonForInteraction<'a> = (&'a Interaction, &'a mut BackgroundColor, &'a EditorButton);
+
+pub fn interact_with_editor_buttons(
+    mut commands: Commands,
+    mut button_query: Query<ButtonForInteraction, Changed<Interaction>>,
+    mut file: ResMut<LdtkFile>,
+    mut history: ResMut<EditHistory>,
+    cursor: Res<EditorCursor>,
+    mut transition_events: EventWriter<StartTransition>,
+) {
+    for (interaction, mut background_color, button) in button_query.iter_mut() {
+        match interaction {
+            Interaction::None => {
+                *background_color = color::PRIMARY.into();
+            }
+            Interaction::Pressed => {
+                *background_color = color::PRIMARY.into();
+                apply_control(
+                    *button,
+                    &mut commands,
+                    &mut file,
+                    &mut history,
+                    &cursor,
+                    &mut transition_events,
+                );
+            }
+            Interaction::Hovered => {
+                *background_color = color::PRIMARY_HOVER.into();
+            }
+        }
+    }
+}
+
+pub fn update_status(
+    mut status_query: Query<&mut Text, With<EditorStatus>>,
+    tool: Res<EditorTool>,
+    history: Res<EditHistory>,
+) {
+    let Ok(mut text) = status_query.get_single_mut() else {
+        return;
+    };
+    let steps = history.undo.iter().filter(|step| !step.is_empty()).count();
+    text.sections[0].value = format!(
+        "Tool: {} | {steps} edits, {} undone\n\
+         Left click places, right click removes, 1-4 pick tools, Ctrl+Z undo, Ctrl+Y redo\n\
+         WASD and the wheel move the camera, P plays from the cursor and returns to the editor",
+        tool.label(),
+        history.redo.len(),
+    );
+}
+
+/// Drops the players at the point they play from, whenever they spawn.
+pub fn drop_players(
+    playtest: Res<Playtest>,
+    mut player_query: Query<(&mut Transform, &mut GlobalTransform), Added<Player>>,
+    mut level_selection: ResMut<LevelSelection>,
+) {
+    if player_query.is_empty() {
+        return;
+    }
+    for (mut transform, mut global_transform) in player_query.iter_mut() {
+        transform.translation = playtest.position.extend(transform.translation.z);
+        // The camera jumps to the player in this frame, before the transforms are propagated
+        *global_transform = GlobalTransform::from(*transform);
+    }
+    *level_selection = LevelSelection::iid(playtest.level.clone());
+}
+
+/// Goes back to the editor with P when playing from it.
+pub fn return_to_editor(
+    keyboard_input: Res<ButtonInput<KeyCode>>,
+    mut transition_events: EventWriter<StartTransition>,
+) {
+    if keyboard_input.just_pressed(KeyCode::KeyP) {
+        transition_events.send(StartTransition {
+            target: TransitionTarget::App(AppState::Editor),
+            style: TransitionStyle::Fade,
+        });
+    }
+}
+
+pub fn end_playtest(mut commands: Commands) {
+    commands.remove_resource::<Playtest>();
+}
@@ -1,12 +1,32 @@
 use bevy::prelude::*;
 use bevy_ecs_ldtk::prelude::*;
+use std::collections::HashSet;
 
 use crate::animation::components::{AnimationIndices, AnimationTimer};
 use crate::collider::ColliderBundle;
+use crate::lighting::components::PointLight2d;
+use crate::player::components::PlayerId;
 
 #[derive(Default, Component)]
 pub struct Fruit;
 
+/// Sent when the player collects a fruit.
+#[derive(Event, Debug, Clone)]
+pub struct FruitCollected {
+    pub fruit: Entity,
+    /// The iid of the fruit's LDtk entity
+    pub iid: String,
+    pub position: Vec2,
+    /// The player who collected the fruit
+    pub player: PlayerId,
+}
+
+/// The iids of the fruits collected since entering the game.
+///
+/// Collected fruits are despawned again when their level is spawned anew.
+#[derive(Resource, Default, Debug, Deref, DerefMut)]
+pub struct CollectedFruits(pub HashSet<String>);
+
 #[derive(Default, LdtkEntity, Bundle)]
 pub struct FruitBundle {
     fruit: Fruit,
@@ -18,11 +38,21 @@ pub struct FruitBundle {
     animation_indices: AnimationIndices,
     #[with(animation_timer)]
     animation_timer: AnimationTimer,
+    #[with(glow)]
+    glow: PointLight2d,
 }
 
 fn animation_indices(_: &EntityInstance) -> AnimationIndices {
     AnimationIndices { first: 0, last: 16 }
 }
+fn glow(_: &EntityInstance) -> PointLight2d {
+    PointLight2d {
+        color: Color::srgb(1., 0.3, 0.3),
+        intensity: 0.6,
+        radius: 32.,
+    }
+}
+
 fn animation_timer(_: &EntityInstance) -> AnimationTimer {
     AnimationTimer(Timer::from_seconds(0.1, TimerMode::Repeating))
 }
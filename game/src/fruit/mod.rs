@@ -3,15 +3,31 @@ pub mod systems;
 
 use bevy::prelude::*;
 
+use crate::fruit::components::{CollectedFruits, FruitCollected};
 use crate::AppState;
 
 pub struct FruitPlugin;
 
 pub const FRUIT_HEIGHT: f32 = 32.0;
 pub const FRUIT_WIDTH: f32 = 32.0;
+/// The identifier of the fruit entities in LDtk.
+pub const FRUIT_IDENTIFIER: &str = "Cherry";
 
 impl Plugin for FruitPlugin {
     fn build(&self, app: &mut App) {
-        app.add_systems(OnExit(AppState::InGame), systems::despawn);
+        app.add_event::<FruitCollected>()
+            .init_resource::<CollectedFruits>()
+            .add_systems(
+                Update,
+                (
+                    systems::remember_collected_fruits,
+                    systems::remove_collected_fruits,
+                )
+                    .run_if(in_state(AppState::InGame)),
+            )
+            .add_systems(
+                OnExit(AppState::InGame),
+                (systems::despawn, systems::forget_collected_fruits),
+            );
     }
 }
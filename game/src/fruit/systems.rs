@@ -1,9 +1,36 @@
 use bevy::prelude::*;
+use bevy_ecs_ldtk::prelude::*;
 
-use crate::fruit::components::Fruit;
+use crate::fruit::components::{CollectedFruits, Fruit, FruitCollected};
 
 pub fn despawn(mut commands: Commands, fruits: Query<Entity, With<Fruit>>) {
     for fruit in fruits.iter() {
         commands.entity(fruit).despawn();
     }
 }
+
+pub fn remember_collected_fruits(
+    mut collected_events: EventReader<FruitCollected>,
+    mut collected_fruits: ResMut<CollectedFruits>,
+) {
+    for event in collected_events.read() {
+        collected_fruits.insert(event.iid.clone());
+    }
+}
+
+/// Despawns the fruits that were collected before their level was spawned.
+pub fn remove_collected_fruits(
+    mut commands: Commands,
+    fruits: Query<(Entity, &EntityIid), Added<Fruit>>,
+    collected_fruits: Res<CollectedFruits>,
+) {
+    for (fruit, iid) in fruits.iter() {
+        if collected_fruits.contains(iid.as_str()) {
+            commands.entity(fruit).despawn();
+        }
+    }
+}
+
+pub fn forget_collected_fruits(mut collected_fruits: ResMut<CollectedFruits>) {
+    collected_fruits.clear();
+}
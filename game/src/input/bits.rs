@@ -0,0 +1,95 @@
+//! The input of a player as one byte per frame, which is what the netcode exchanges,
+//! what the players are moved by and what replays and leaderboard times record.
+
+use leafwing_input_manager::prelude::*;
+
+use crate::input::components::InputSettings;
+use crate::Action;
+
+/// The actions of a player in a frame, as a bitmask of [`InputBits::ACTIONS`]
+/// in the lower four bits and the stick in the upper four bits.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct InputBits(pub u8);
+
+impl InputBits {
+    /// The actions that are recorded, in the order of their bits.
+    pub const ACTIONS: [Action; 4] = [Action::Left, Action::Right, Action::Jump, Action::Fall];
+    const ACTIONS_MASK: u8 = 0b1111;
+    /// How many steps the stick is recorded with in each direction
+    const STICK_STEPS: f32 = 7.;
+
+    /// Reads the pressed actions and the stick, outside of the dead zone.
+    pub fn from_action_state(
+        action_state: &ActionState<Action>,
+        input_settings: &InputSettings,
+    ) -> Self {
+        let stick = input_settings.apply_dead_zone(action_state.value(&Action::Move));
+        Self::from_actions(
+            Self::ACTIONS
+                .into_iter()
+                .filter(|action| action_state.pressed(action)),
+        )
+        .with_stick(stick)
+    }
+
+    pub fn from_actions(actions: impl IntoIterator<Item = Action>) -> Self {
+        Self(actions.into_iter().fold(0, |bits, action| {
+            let bit = Self::ACTIONS.iter().position(|logged| *logged == action);
+            bit.map_or(bits, |bit| bits | 1 << bit)
+        }))
+    }
+
+    /// Replaces the stick, from -1 (left) to 1 (right).
+    pub fn with_stick(self, stick: f32) -> Self {
+        let steps = (stick.clamp(-1., 1.) * Self::STICK_STEPS).round() as i8;
+        Self(self.0 & Self::ACTIONS_MASK | (steps as u8) << 4)
+    }
+
+    /// Adds the pressed actions of other input, keeping the stick.
+    pub fn with_actions_of(self, other: InputBits) -> Self {
+        Self(self.0 | other.0 & Self::ACTIONS_MASK)
+    }
+
+    pub fn pressed(self, action: Action) -> bool {
+        Self::ACTIONS
+            .iter()
+            .position(|logged| *logged == action)
+            .is_some_and(|bit| self.0 & 1 << bit != 0)
+    }
+
+    /// How far the stick is pushed, from -1 (left) to 1 (right).
+    pub fn stick(self) -> f32 {
+        // Shifting the signed byte keeps the sign of the upper four bits
+        f32::from((self.0 as i8) >> 4) / Self::STICK_STEPS
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trips_through_the_bitmask() {
+        let input = InputBits::from_actions([Action::Right, Action::Jump]);
+        assert_eq!(input, InputBits(0b0110));
+        assert!(input.pressed(Action::Right));
+        assert!(input.pressed(Action::Jump));
+        assert!(!input.pressed(Action::Left));
+        assert!(!input.pressed(Action::Move));
+        assert_eq!(input.stick(), 0.);
+    }
+
+    #[test]
+    fn keeps_the_stick_in_the_upper_bits() {
+        let input = InputBits::from_actions([Action::Fall]).with_stick(-1.);
+        assert_eq!(input.stick(), -1.);
+        assert!(input.pressed(Action::Fall));
+        assert!(!input.pressed(Action::Left));
+        assert_eq!(input.with_stick(0.5).stick(), 4. / 7.);
+        assert_eq!(input.with_stick(2.).stick(), 1.);
+
+        let merged = input.with_actions_of(InputBits::from_actions([Action::Jump]).with_stick(1.));
+        assert!(merged.pressed(Action::Jump) && merged.pressed(Action::Fall));
+        assert_eq!(merged.stick(), -1.);
+    }
+}
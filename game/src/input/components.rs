@@ -0,0 +1,85 @@
+use bevy::prelude::*;
+
+use crate::input::DEFAULT_DEAD_ZONE;
+
+/// How the analog inputs are read.
+#[derive(Resource, Debug, Clone, Copy)]
+pub struct InputSettings {
+    /// Stick deflections below this are ignored, from 0 to 1
+    pub dead_zone: f32,
+    /// Whether gamepads vibrate on damage and landing
+    pub rumble: bool,
+}
+
+impl Default for InputSettings {
+    fn default() -> Self {
+        Self {
+            dead_zone: DEFAULT_DEAD_ZONE,
+            rumble: true,
+        }
+    }
+}
+
+impl InputSettings {
+    /// Rescales a stick deflection so it starts at 0 at the edge of the dead zone.
+    pub fn apply_dead_zone(&self, value: f32) -> f32 {
+        let dead_zone = self.dead_zone.clamp(0., 0.99);
+        if value.abs() <= dead_zone {
+            return 0.;
+        }
+        (value.signum() * (value.abs() - dead_zone) / (1. - dead_zone)).clamp(-1., 1.)
+    }
+}
+
+/// The device the player last used.
+#[derive(Resource, Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum InputDevice {
+    #[default]
+    Keyboard,
+    Gamepad(Gamepad),
+    Touch,
+}
+
+impl InputDevice {
+    /// The controls shown to the player.
+    pub fn prompt(&self) -> &'static str {
+        match self {
+            InputDevice::Keyboard => "Move: A/D   Jump: Space   Fall: S   Pause: Esc",
+            InputDevice::Gamepad(_) => {
+                "Move: Left Stick   Jump: A   Fall: D-Pad Down   Pause: Start"
+            }
+            InputDevice::Touch => "Move: Arrows   Jump: Jump   Fall: Down Arrow",
+        }
+    }
+}
+
+/// A text showing the controls of the device the player last used.
+#[derive(Component)]
+pub struct ButtonPrompt;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ignores_deflections_inside_the_dead_zone() {
+        let settings = InputSettings {
+            dead_zone: 0.2,
+            ..default()
+        };
+        assert_eq!(settings.apply_dead_zone(0.1), 0.);
+        assert_eq!(settings.apply_dead_zone(-0.2), 0.);
+    }
+
+    #[test]
+    fn rescales_deflections_outside_the_dead_zone() {
+        let settings = InputSettings {
+            dead_zone: 0.2,
+            ..default()
+        };
+        assert!((settings.apply_dead_zone(0.6) - 0.5).abs() < 1e-6);
+        assert!((settings.apply_dead_zone(-0.6) + 0.5).abs() < 1e-6);
+        assert_eq!(settings.apply_dead_zone(1.), 1.);
+        assert_eq!(settings.apply_dead_zone(-1.), -1.);
+    }
+}
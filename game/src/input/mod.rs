@@ -0,0 +1,33 @@
+pub mod bits;
+pub mod components;
+pub mod systems;
+
+use bevy::prelude::*;
+
+use crate::input::components::{InputDevice, InputSettings};
+use crate::GameState;
+
+/// Stick deflections below this are ignored unless configured otherwise
+pub const DEFAULT_DEAD_ZONE: f32 = 0.15;
+
+pub struct InputPlugin;
+
+impl Plugin for InputPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<InputSettings>()
+            .init_resource::<InputDevice>()
+            .add_systems(
+                PreUpdate,
+                (systems::switch_device, systems::forget_disconnected_gamepad)
+                    .chain()
+                    .after(bevy::input::InputSystem),
+            )
+            .add_systems(
+                Update,
+                (
+                    systems::update_button_prompts,
+                    systems::rumble.run_if(in_state(GameState::Running)),
+                ),
+            );
+    }
+}
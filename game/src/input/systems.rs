@@ -0,0 +1,93 @@
+use bevy::input::gamepad::{GamepadRumbleIntensity, GamepadRumbleRequest};
+use bevy::prelude::*;
+use std::time::Duration;
+
+use crate::input::components::{ButtonPrompt, InputDevice, InputSettings};
+use crate::player::components::{DamageEvent, Landed};
+
+/// Switches to the device the player pressed a button, moved a stick or touched the screen on.
+pub fn switch_device(
+    keyboard_input: Res<ButtonInput<KeyCode>>,
+    touches: Res<Touches>,
+    gamepad_input: Res<ButtonInput<GamepadButton>>,
+    gamepad_axes: Res<Axis<GamepadAxis>>,
+    gamepads: Res<Gamepads>,
+    input_settings: Res<InputSettings>,
+    mut device: ResMut<InputDevice>,
+) {
+    if keyboard_input.get_just_pressed().next().is_some() {
+        device.set_if_neq(InputDevice::Keyboard);
+        return;
+    }
+    if touches.any_just_pressed() {
+        device.set_if_neq(InputDevice::Touch);
+        return;
+    }
+    if let Some(button) = gamepad_input.get_just_pressed().next() {
+        device.set_if_neq(InputDevice::Gamepad(button.gamepad));
+        return;
+    }
+    let moved = gamepads.iter().find(|gamepad| {
+        [GamepadAxisType::LeftStickX, GamepadAxisType::LeftStickY]
+            .into_iter()
+            .filter_map(|axis| gamepad_axes.get(GamepadAxis::new(*gamepad, axis)))
+            .any(|value| value.abs() > input_settings.dead_zone)
+    });
+    if let Some(gamepad) = moved {
+        device.set_if_neq(InputDevice::Gamepad(gamepad));
+    }
+}
+
+/// Falls back to the keyboard when the gamepad in use is disconnected.
+pub fn forget_disconnected_gamepad(gamepads: Res<Gamepads>, mut device: ResMut<InputDevice>) {
+    if let InputDevice::Gamepad(gamepad) = *device {
+        if !gamepads.contains(gamepad) {
+            *device = InputDevice::Keyboard;
+        }
+    }
+}
+
+pub fn update_button_prompts(
+    mut prompt_query: Query<(&mut Text, Ref<ButtonPrompt>)>,
+    device: Res<InputDevice>,
+) {
+    for (mut text, prompt) in prompt_query.iter_mut() {
+        if device.is_changed() || prompt.is_added() {
+            text.sections[0].value = device.prompt().to_string();
+        }
+    }
+}
+
+/// Vibrates the gamepad in use when the player is damaged or lands.
+///
+/// Gamepads and platforms without force feedback ignore the requests.
+pub fn rumble(
+    mut damage_events: EventReader<DamageEvent>,
+    mut landed_events: EventReader<Landed>,
+    mut rumble_requests: EventWriter<GamepadRumbleRequest>,
+    input_settings: Res<InputSettings>,
+    device: Res<InputDevice>,
+) {
+    let damaged = damage_events.read().count() > 0;
+    let landed = landed_events
+        .read()
+        .map(|landed| landed.hard)
+        .reduce(|hard, other| hard || other);
+    let InputDevice::Gamepad(gamepad) = *device else {
+        return;
+    };
+    if !input_settings.rumble {
+        return;
+    }
+    let (intensity, seconds) = match (damaged, landed) {
+        (true, _) => (GamepadRumbleIntensity::MAX, 0.3),
+        (false, Some(true)) => (GamepadRumbleIntensity::strong_motor(0.6), 0.2),
+        (false, Some(false)) => (GamepadRumbleIntensity::weak_motor(0.3), 0.1),
+        (false, None) => return,
+    };
+    rumble_requests.send(GamepadRumbleRequest::Add {
+        gamepad,
+        intensity,
+        duration: Duration::from_secs_f32(seconds),
+    });
+}
@@ -0,0 +1,250 @@
+//! Submits times to a remote leaderboard.
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::sync::Arc;
+
+use crate::hash::fnv1a;
+
+/// A time submitted to a leaderboard.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Submission {
+    /// The iid of the level
+    pub level: String,
+    pub time: f32,
+    pub score: u32,
+    /// The hash of the inputs the level was completed with, so the time can be checked
+    pub replay_hash: String,
+}
+
+impl Submission {
+    /// Returns a checksum of the submission and its replay hash.
+    ///
+    /// The checksum is not keyed, so it detects submissions corrupted on the way to the
+    /// server but not forged ones, which the server has to check against the replay.
+    pub fn checksum(&self) -> String {
+        let summed = format!(
+            "{}|{}|{}|{}",
+            self.level,
+            self.time.to_bits(),
+            self.score,
+            self.replay_hash
+        );
+        format!("{:016x}", fnv1a(summed.as_bytes()))
+    }
+}
+
+#[derive(Debug)]
+pub enum LeaderboardError {
+    Io(std::io::Error),
+    Json(serde_json::Error),
+    InvalidUrl(String),
+    InvalidResponse,
+    /// The server answered with an unsuccessful status code
+    Status(u16),
+}
+
+impl fmt::Display for LeaderboardError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LeaderboardError::Io(error) => write!(f, "{error}"),
+            LeaderboardError::Json(error) => write!(f, "{error}"),
+            LeaderboardError::InvalidUrl(url) => write!(f, "invalid leaderboard url {url}"),
+            LeaderboardError::InvalidResponse => write!(f, "invalid response"),
+            LeaderboardError::Status(status) => write!(f, "the server answered {status}"),
+        }
+    }
+}
+
+impl std::error::Error for LeaderboardError {}
+
+impl From<std::io::Error> for LeaderboardError {
+    fn from(error: std::io::Error) -> Self {
+        LeaderboardError::Io(error)
+    }
+}
+
+impl From<serde_json::Error> for LeaderboardError {
+    fn from(error: serde_json::Error) -> Self {
+        LeaderboardError::Json(error)
+    }
+}
+
+/// A remote leaderboard times can be submitted to.
+pub trait LeaderboardBackend: Send + Sync {
+    /// Submits a time, blocking until the leaderboard accepted or rejected it.
+    fn submit(&self, submission: &Submission) -> Result<(), LeaderboardError>;
+}
+
+/// The leaderboard the completed levels are submitted to.
+#[derive(Resource, Clone)]
+pub struct LeaderboardServer(pub Arc<dyn LeaderboardBackend>);
+
+impl LeaderboardServer {
+    pub fn new(backend: impl LeaderboardBackend + 'static) -> Self {
+        Self(Arc::new(backend))
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+pub use rest::RestBackend;
+
+#[cfg(not(target_arch = "wasm32"))]
+mod rest {
+    use std::io::{BufRead, BufReader, Write};
+    use std::net::TcpStream;
+    use std::time::Duration;
+
+    use super::{LeaderboardBackend, LeaderboardError, Submission};
+
+    const TIMEOUT: Duration = Duration::from_secs(10);
+
+    /// Posts the submissions as JSON to a REST endpoint over plain HTTP.
+    ///
+    /// The checksum is sent in the `X-Checksum` header.
+    pub struct RestBackend {
+        pub url: String,
+    }
+
+    impl RestBackend {
+        pub fn new(url: impl Into<String>) -> Self {
+            Self { url: url.into() }
+        }
+
+        /// Splits the url into the address to connect to, the host and the path.
+        fn endpoint(&self) -> Result<(String, &str, &str), LeaderboardError> {
+            let invalid_url = || LeaderboardError::InvalidUrl(self.url.clone());
+            let rest = self.url.strip_prefix("http://").ok_or_else(invalid_url)?;
+            let (host, path) = rest.find('/').map_or((rest, "/"), |i| rest.split_at(i));
+            if host.is_empty() {
+                return Err(invalid_url());
+            }
+            let address = if host.contains(':') {
+                host.to_string()
+            } else {
+                format!("{host}:80")
+            };
+            Ok((address, host, path))
+        }
+    }
+
+    impl LeaderboardBackend for RestBackend {
+        fn submit(&self, submission: &Submission) -> Result<(), LeaderboardError> {
+            let (address, host, path) = self.endpoint()?;
+            let body = serde_json::to_string(submission)?;
+            let mut stream = TcpStream::connect(address)?;
+            stream.set_read_timeout(Some(TIMEOUT))?;
+            stream.set_write_timeout(Some(TIMEOUT))?;
+            write!(
+                stream,
+                "POST {path} HTTP/1.1\r\n\
+                 Host: {host}\r\n\
+                 Content-Type: application/json\r\n\
+                 Content-Length: {}\r\n\
+                 X-Checksum: {}\r\n\
+                 Connection: close\r\n\
+                 \r\n\
+                 {body}",
+                body.len(),
+                submission.checksum()
+            )?;
+            stream.flush()?;
+
+            let mut status_line = String::new();
+            BufReader::new(stream).read_line(&mut status_line)?;
+            let status: u16 = status_line
+                .split_whitespace()
+                .nth(1)
+                .and_then(|status| status.parse().ok())
+                .ok_or(LeaderboardError::InvalidResponse)?;
+            if (200..300).contains(&status) {
+                Ok(())
+            } else {
+                Err(LeaderboardError::Status(status))
+            }
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+        use std::io::Read;
+        use std::net::TcpListener;
+        use std::thread;
+
+        fn submission() -> Submission {
+            Submission {
+                level: "level".to_string(),
+                time: 12.5,
+                score: 19,
+                replay_hash: "00ff".to_string(),
+            }
+        }
+
+        /// Serves a single request with the response and returns the request.
+        fn mock_server(response: &'static str) -> (String, thread::JoinHandle<String>) {
+            let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+            let url = format!("http://{}/scores", listener.local_addr().unwrap());
+            let server = thread::spawn(move || {
+                let (mut stream, _) = listener.accept().unwrap();
+                let mut request = Vec::new();
+                let mut buffer = [0; 1024];
+                // The request ends with the body, whose length is in the headers
+                loop {
+                    let read = stream.read(&mut buffer).unwrap();
+                    request.extend_from_slice(&buffer[..read]);
+                    let text = String::from_utf8_lossy(&request);
+                    if let Some((headers, body)) = text.split_once("\r\n\r\n") {
+                        let length = headers
+                            .lines()
+                            .find_map(|line| line.strip_prefix("Content-Length: "))
+                            .and_then(|length| length.parse::<usize>().ok())
+                            .unwrap();
+                        if body.len() >= length {
+                            break;
+                        }
+                    }
+                }
+                stream.write_all(response.as_bytes()).unwrap();
+                String::from_utf8(request).unwrap()
+            });
+            (url, server)
+        }
+
+        #[test]
+        fn posts_submissions_with_their_checksum() {
+            let (url, server) = mock_server("HTTP/1.1 201 Created\r\nContent-Length: 0\r\n\r\n");
+            RestBackend::new(url).submit(&submission()).unwrap();
+
+            let request = server.join().unwrap();
+            assert!(request.starts_with("POST /scores HTTP/1.1\r\n"));
+            assert!(request.contains(&format!("X-Checksum: {}\r\n", submission().checksum())));
+            let body = request.split_once("\r\n\r\n").unwrap().1;
+            let received: Submission = serde_json::from_str(body).unwrap();
+            assert_eq!(received, submission());
+        }
+
+        #[test]
+        fn reports_rejected_submissions() {
+            let (url, server) = mock_server("HTTP/1.1 403 Forbidden\r\nContent-Length: 0\r\n\r\n");
+            let result = RestBackend::new(url).submit(&submission());
+            server.join().unwrap();
+            assert!(matches!(result, Err(LeaderboardError::Status(403))));
+        }
+
+        #[test]
+        fn rejects_unsupported_urls() {
+            let result = RestBackend::new("https://example.com").submit(&submission());
+            assert!(matches!(result, Err(LeaderboardError::InvalidUrl(_))));
+        }
+
+        #[test]
+        fn checksums_depend_on_the_replay() {
+            let mut other = submission();
+            other.replay_hash = "00fe".to_string();
+            assert_ne!(submission().checksum(), other.checksum());
+            assert_eq!(submission().checksum(), submission().checksum());
+        }
+    }
+}
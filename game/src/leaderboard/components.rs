@@ -0,0 +1,38 @@
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// The version of the local leaderboards written by this build.
+pub const LEADERBOARD_VERSION: u32 = 1;
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct LeaderboardEntry {
+    /// How long completing the level took in seconds
+    pub time: f32,
+    /// The number of fruits collected
+    pub score: u32,
+    /// The hash of the inputs the level was completed with
+    pub replay_hash: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+pub struct LevelLeaderboard {
+    /// The identifier of the level
+    pub name: String,
+    /// The entries, fastest first
+    pub entries: Vec<LeaderboardEntry>,
+}
+
+/// The best times of each level on this device.
+#[derive(Resource, Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+pub struct LocalLeaderboard {
+    pub version: u32,
+    /// The leaderboards by the iid of their level
+    pub levels: BTreeMap<String, LevelLeaderboard>,
+}
+
+#[derive(Component)]
+pub struct LeaderboardScreen;
+
+#[derive(Component)]
+pub struct BackButton;
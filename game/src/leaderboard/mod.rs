@@ -0,0 +1,44 @@
+pub mod backend;
+pub mod components;
+pub mod systems;
+
+use bevy::prelude::*;
+
+use crate::save::systems::record_fruits;
+use crate::AppState;
+
+/// The name of the file the local leaderboard is stored in.
+pub const LEADERBOARD_FILE: &str = "leaderboard.json";
+/// The number of entries kept per level.
+pub const MAX_ENTRIES: usize = 10;
+
+/// Keeps the best times of each level and submits them to a leaderboard server.
+pub struct LeaderboardPlugin {
+    /// The URL of the REST endpoint the times are submitted to
+    pub submission_url: Option<String>,
+}
+
+impl Plugin for LeaderboardPlugin {
+    fn build(&self, app: &mut App) {
+        #[cfg(not(target_arch = "wasm32"))]
+        if let Some(url) = &self.submission_url {
+            app.insert_resource(backend::LeaderboardServer::new(backend::RestBackend::new(
+                url.clone(),
+            )));
+        }
+        app.add_systems(Startup, systems::load)
+            .add_systems(OnEnter(AppState::Leaderboard), systems::spawn)
+            .add_systems(OnExit(AppState::Leaderboard), systems::despawn)
+            .add_systems(
+                Update,
+                systems::interact_with_back_button.run_if(in_state(AppState::Leaderboard)),
+            )
+            .add_systems(
+                Update,
+                // The recording only starts over in the fixed step after the next level is entered
+                systems::record_times
+                    .after(record_fruits)
+                    .run_if(in_state(AppState::InGame)),
+            );
+    }
+}
@@ -0,0 +1,209 @@
+use bevy::prelude::*;
+use bevy::tasks::IoTaskPool;
+
+use crate::leaderboard::backend::{LeaderboardServer, Submission};
+use crate::leaderboard::components::{
+    BackButton, LeaderboardEntry, LeaderboardScreen, LocalLeaderboard, LEADERBOARD_VERSION,
+};
+use crate::leaderboard::{LEADERBOARD_FILE, MAX_ENTRIES};
+use crate::replay::components::ReplayRecorder;
+use crate::save::components::{LevelCompleted, SaveGame};
+use crate::save::storage;
+use crate::transition::components::{StartTransition, TransitionStyle, TransitionTarget};
+use crate::{color, AppState};
+
+pub fn load(mut commands: Commands) {
+    let leaderboard = match storage::read(LEADERBOARD_FILE) {
+        Ok(Some(text)) => match serde_json::from_str::<LocalLeaderboard>(&text) {
+            Ok(leaderboard) if leaderboard.version == LEADERBOARD_VERSION => leaderboard,
+            Ok(leaderboard) => {
+                warn!(
+                    "Ignoring the leaderboard of the unsupported version {}",
+                    leaderboard.version
+                );
+                LocalLeaderboard::default()
+            }
+            Err(error) => {
+                warn!("Ignoring the invalid leaderboard: {error}");
+                LocalLeaderboard::default()
+            }
+        },
+        Ok(None) => LocalLeaderboard::default(),
+        Err(error) => {
+            warn!("Could not read the leaderboard: {error}");
+            LocalLeaderboard::default()
+        }
+    };
+    commands.insert_resource(leaderboard);
+}
+
+/// Adds the times of completed levels to the local leaderboard and submits them to the server.
+pub fn record_times(
+    mut completed_events: EventReader<LevelCompleted>,
+    mut leaderboard: ResMut<LocalLeaderboard>,
+    save: Res<SaveGame>,
+    recorder: Res<ReplayRecorder>,
+    server: Option<Res<LeaderboardServer>>,
+) {
+    let mut changed = false;
+    for event in completed_events.read() {
+        let entry = LeaderboardEntry {
+            time: event.time,
+            score: save
+                .levels
+                .get(&event.level)
+                .map_or(0, |progress| progress.fruits_collected),
+            replay_hash: recorder.input_hash(),
+        };
+
+        if let Some(server) = &server {
+            let backend = server.0.clone();
+            let submission = Submission {
+                level: event.level.clone(),
+                time: entry.time,
+                score: entry.score,
+                replay_hash: entry.replay_hash.clone(),
+            };
+            // Submitting blocks until the server answered
+            IoTaskPool::get()
+                .spawn(async move {
+                    if let Err(error) = backend.submit(&submission) {
+                        warn!("Could not submit the time to the leaderboard: {error}");
+                    }
+                })
+                .detach();
+        }
+
+        let level = leaderboard.levels.entry(event.level.clone()).or_default();
+        level.name.clone_from(&event.name);
+        let rank = level
+            .entries
+            .partition_point(|other| other.time <= entry.time);
+        if rank < MAX_ENTRIES {
+            level.entries.insert(rank, entry);
+            level.entries.truncate(MAX_ENTRIES);
+            changed = true;
+        }
+    }
+    if !changed {
+        return;
+    }
+    leaderboard.version = LEADERBOARD_VERSION;
+    let result = serde_json::to_string(&*leaderboard)
+        .map_err(Into::into)
+        .and_then(|text| storage::write(LEADERBOARD_FILE, &text));
+    if let Err(error) = result {
+        error!("Could not save the leaderboard: {error}");
+    }
+}
+
+pub fn spawn(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    leaderboard: Res<LocalLeaderboard>,
+) {
+    let text_style = |font_size| TextStyle {
+        font_size,
+        color: color::PRIMARY_CONTENT,
+        ..default()
+    };
+    commands
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    flex_direction: FlexDirection::Column,
+                    justify_content: JustifyContent::Center,
+                    align_items: AlignItems::Center,
+                    row_gap: Val::Px(10.0),
+                    width: Val::Percent(100.0),
+                    height: Val::Percent(100.0),
+                    ..default()
+                },
+                ..default()
+            },
+            LeaderboardScreen,
+        ))
+        .with_children(|parent| {
+            // Title
+            parent.spawn(TextBundle::from_section("Leaderboard", text_style(64.0)));
+
+            // Levels
+            parent
+                .spawn(NodeBundle {
+                    style: Style {
+                        flex_direction: FlexDirection::Row,
+                        column_gap: Val::Px(40.0),
+                        ..default()
+                    },
+                    ..default()
+                })
+                .with_children(|parent| {
+                    if leaderboard.levels.is_empty() {
+                        parent.spawn(TextBundle::from_section("No times yet", text_style(24.0)));
+                    }
+                    for level in leaderboard.levels.values() {
+                        let mut sections = vec![TextSection::new(
+                            format!("{}\n", level.name),
+                            text_style(32.0),
+                        )];
+                        sections.extend(level.entries.iter().enumerate().map(|(rank, entry)| {
+                            TextSection::new(
+                                format!(
+                                    "{}. {:.2}s  {} fruits\n",
+                                    rank + 1,
+                                    entry.time,
+                                    entry.score
+                                ),
+                                text_style(24.0),
+                            )
+                        }));
+                        parent.spawn(TextBundle::from_sections(sections));
+                    }
+                });
+
+            // Back Button
+            parent.spawn((
+                ButtonBundle {
+                    image: UiImage::new(asset_server.load("Menu/Buttons/Back.png")),
+                    style: Style {
+                        width: Val::Px(42.0),
+                        height: Val::Px(44.0),
+                        ..default()
+                    },
+                    ..default()
+                },
+                BackButton,
+            ));
+        });
+}
+
+pub fn despawn(mut commands: Commands, screen_query: Query<Entity, With<LeaderboardScreen>>) {
+    if let Ok(screen_entity) = screen_query.get_single() {
+        commands.entity(screen_entity).despawn_recursive();
+    }
+}
+
+type ImageForInteraction<'a> = (&'a Interaction, &'a mut UiImage);
+
+pub fn interact_with_back_button(
+    mut button_query: Query<ImageForInteraction, (Changed<Interaction>, With<BackButton>)>,
+    mut transition_events: EventWriter<StartTransition>,
+) {
+    if let Ok((interaction, mut image)) = button_query.get_single_mut() {
+        match interaction {
+            Interaction::None => {
+                image.color = Color::WHITE;
+            }
+            Interaction::Pressed => {
+                image.color = Color::WHITE;
+                transition_events.send(StartTransition {
+                    target: TransitionTarget::App(AppState::MainMenu),
+                    style: TransitionStyle::Fade,
+                });
+            }
+            Interaction::Hovered => {
+                image.color = color::PRIMARY_HOVER;
+            }
+        }
+    }
+}
@@ -1,57 +1,184 @@
+pub mod achievements;
 pub mod animation;
+pub mod background;
+pub mod camera;
 pub mod cli;
 pub mod collider;
 pub mod color;
+pub mod coop;
+pub mod editor;
 pub mod fruit;
+pub mod hash;
+pub mod input;
+pub mod leaderboard;
+pub mod lighting;
+pub mod lobby;
 pub mod main_menu;
+pub mod netcode;
+pub mod particles;
 pub mod player;
+pub mod quicksave;
+pub mod replay;
+pub mod save;
+pub mod speedrun;
+pub mod transition;
+pub mod trap;
 pub mod ui;
 pub mod world;
 
+use crate::camera::components::{CameraZoneBundle, RenderMode};
 use crate::cli::CliArgs;
 use crate::collider::ColliderBundle;
+use crate::coop::components::CoOp;
 use crate::fruit::components::FruitBundle;
-use crate::player::components::{Player, PlayerBundle};
-use crate::world::components::GroundBundle;
+use crate::input::components::InputSettings;
+use crate::main_menu::lobby::components::LobbySettings;
+use crate::netcode::components::{LocalPlayer, PeerConnection};
+use crate::player::components::{PlayerBundle, PlayerId};
+use crate::trap::components::FireTrapBundle;
+use crate::ui::touch_controls::components::TouchLayout;
+use crate::world::components::{
+    BreakableBlockBundle, GroundBundle, LadderBundle, OneWayPlatformBundle, WaterBundle,
+};
+use crate::world::{TERRAIN_LAYER, VOLUMES_LAYER};
 
-use bevy::core_pipeline::bloom::BloomSettings;
 use bevy::prelude::*;
-use bevy::window::PrimaryWindow;
 use bevy_ecs_ldtk::prelude::*;
 use bevy_rapier2d::prelude::*;
 use leafwing_input_manager::prelude::*;
 use std::collections::HashMap;
+use std::net::Ipv4Addr;
 
 /// Runs the game given the cli arguments parameters.
-pub fn run(CliArgs { listen_address }: CliArgs) {
+pub fn run(
+    CliArgs {
+        listen_address,
+        pixel_perfect,
+        speedrun,
+        leaderboard_url,
+        dead_zone,
+        left_handed,
+        co_op,
+        peer,
+        rollback_port,
+        local_player,
+        editor,
+    }: CliArgs,
+) {
     tracing::info!("Game started {listen_address:?}");
-    App::new()
-        .add_plugins(DefaultPlugins)
+    let (render_mode, image_plugin) = if pixel_perfect {
+        // Smoothing would blur the pixels of the sprites
+        (RenderMode::PixelPerfect, ImagePlugin::default_nearest())
+    } else {
+        (RenderMode::Smooth, ImagePlugin::default())
+    };
+    let mut app = App::new();
+    app.add_plugins(DefaultPlugins.set(image_plugin))
+        .insert_resource(render_mode)
+        .insert_resource(InputSettings {
+            dead_zone,
+            ..default()
+        })
+        .insert_resource(if left_handed {
+            TouchLayout::LeftHanded
+        } else {
+            TouchLayout::RightHanded
+        })
         .insert_state(AppState::MainMenu)
+        .add_plugins(save::SavePlugin)
         .add_plugins(main_menu::MainMenuPlugin)
-        .add_plugins(GamePlugin)
-        .add_systems(Startup, spawn_camera)
-        .run();
+        .add_plugins(achievements::AchievementsPlugin)
+        .add_plugins(leaderboard::LeaderboardPlugin {
+            submission_url: leaderboard_url,
+        })
+        .add_plugins(camera::CameraPlugin)
+        .add_plugins(transition::TransitionPlugin)
+        .add_plugins(GamePlugin);
+    if speedrun {
+        app.add_plugins(speedrun::SpeedrunPlugin);
+    }
+    // The lobby only plays co-op once it starts a session
+    if co_op || peer.is_some() || listen_address.is_some() {
+        app.add_plugins(coop::CoopPlugin);
+    }
+    if co_op || peer.is_some() {
+        app.insert_resource(CoOp);
+    }
+    if peer.is_some() || listen_address.is_some() {
+        app.add_plugins(netcode::RollbackPlugin);
+    }
+    if let Some(peer) = peer {
+        let local_address = (Ipv4Addr::UNSPECIFIED, rollback_port).into();
+        match PeerConnection::udp(local_address, peer) {
+            Ok(connection) => {
+                let local_player = if local_player == 2 {
+                    PlayerId::Two
+                } else {
+                    PlayerId::One
+                };
+                app.insert_resource(connection)
+                    .insert_resource(LocalPlayer(local_player));
+            }
+            Err(error) => tracing::error!("Could not connect to {peer}: {error}"),
+        }
+    }
+    if editor {
+        app.add_plugins(editor::EditorPlugin);
+    }
+    if let Some(address) = listen_address {
+        app.insert_resource(LobbySettings {
+            address,
+            rollback_port,
+        });
+    }
+    app.run();
 }
 
 pub struct GamePlugin;
 impl Plugin for GamePlugin {
     fn build(&self, app: &mut App) {
         app.insert_state(GameState::Paused)
-            .add_plugins(RapierPhysicsPlugin::<NoUserData>::pixels_per_meter(16.0))
+            .add_plugins(
+                // The physics only move the players, so they step with the players' movement
+                RapierPhysicsPlugin::<NoUserData>::pixels_per_meter(16.0)
+                    .in_schedule(player::MovementStep),
+            )
             .add_plugins(InputManagerPlugin::<Action>::default())
             .add_plugins(LdtkPlugin)
             .insert_resource(LevelSelection::index(0))
-            .register_ldtk_entity::<FruitBundle>("Cherry")
+            .insert_resource(LdtkSettings {
+                // Spawning the levels at their location in the world and loading their neighbours
+                // makes the levels of GridVania and linear worlds form one continuous map
+                level_spawn_behavior: LevelSpawnBehavior::UseWorldTranslation {
+                    load_level_neighbors: true,
+                },
+                ..default()
+            })
+            .register_ldtk_entity::<FruitBundle>(fruit::FRUIT_IDENTIFIER)
             .register_ldtk_entity::<PlayerBundle>("Player")
-            .register_ldtk_int_cell::<GroundBundle>(1)
+            .register_ldtk_entity::<CameraZoneBundle>("CameraZone")
+            .register_ldtk_entity::<FireTrapBundle>("Fire")
+            .register_ldtk_int_cell_for_layer::<GroundBundle>(TERRAIN_LAYER, 1)
+            .register_ldtk_int_cell_for_layer::<OneWayPlatformBundle>(TERRAIN_LAYER, 2)
+            .register_ldtk_int_cell_for_layer::<BreakableBlockBundle>(TERRAIN_LAYER, 3)
+            .register_ldtk_int_cell_for_layer::<LadderBundle>(VOLUMES_LAYER, 1)
+            .register_ldtk_int_cell_for_layer::<LadderBundle>(VOLUMES_LAYER, 2)
+            .register_ldtk_int_cell_for_layer::<WaterBundle>(VOLUMES_LAYER, 3)
             .add_plugins(world::WorldPlugin)
             .add_plugins(animation::AnimationPlugin)
+            .add_plugins(background::BackgroundPlugin)
             .add_plugins(player::PlayerPlugin)
             .add_plugins(fruit::FruitPlugin)
+            .add_plugins(particles::ParticlesPlugin)
+            .add_plugins(lighting::LightingPlugin)
+            .add_plugins(quicksave::QuicksavePlugin)
+            .add_plugins(replay::ReplayPlugin)
+            .add_plugins(input::InputPlugin)
             .add_plugins(ui::UiPlugin)
-            .insert_resource(Tilesets::<player::components::Movement>::default())
-            .add_systems(Update, (touch_system, update_camera));
+            .insert_resource(Tilesets::<(
+                player::components::Character,
+                player::components::Movement,
+            )>::default());
         #[cfg(debug_assertions)]
         app.add_plugins(RapierDebugRenderPlugin::default());
     }
@@ -63,11 +190,14 @@ pub enum Action {
     Right,
     Jump,
     Fall,
+    /// Horizontal movement from -1 (left) to 1 (right), for analog sticks
+    #[actionlike(Axis)]
+    Move,
 }
 
 impl Action {
     pub fn player_one() -> InputMap<Action> {
-        InputMap::new([
+        Self::with_gamepad_bindings(InputMap::new([
             (Action::Left, KeyCode::KeyA),
             (Action::Left, KeyCode::ArrowLeft),
             (Action::Right, KeyCode::KeyD),
@@ -77,7 +207,41 @@ impl Action {
             (Action::Jump, KeyCode::ArrowUp),
             (Action::Fall, KeyCode::KeyS),
             (Action::Fall, KeyCode::ArrowDown),
-        ])
+        ]))
+    }
+
+    /// The first player of a co-op game, who leaves the arrow keys to the second player.
+    pub fn co_op_player_one() -> InputMap<Action> {
+        Self::with_gamepad_bindings(InputMap::new([
+            (Action::Left, KeyCode::KeyA),
+            (Action::Right, KeyCode::KeyD),
+            (Action::Jump, KeyCode::KeyW),
+            (Action::Jump, KeyCode::Space),
+            (Action::Fall, KeyCode::KeyS),
+        ]))
+        .with_gamepad(Gamepad::new(0))
+    }
+
+    pub fn player_two() -> InputMap<Action> {
+        Self::with_gamepad_bindings(InputMap::new([
+            (Action::Left, KeyCode::ArrowLeft),
+            (Action::Right, KeyCode::ArrowRight),
+            (Action::Jump, KeyCode::ArrowUp),
+            (Action::Jump, KeyCode::Enter),
+            (Action::Fall, KeyCode::ArrowDown),
+        ]))
+        // Without a gamepad of their own, the players would share the first one
+        .with_gamepad(Gamepad::new(1))
+    }
+
+    fn with_gamepad_bindings(input_map: InputMap<Action>) -> InputMap<Action> {
+        input_map
+            .with(Action::Left, GamepadButtonType::DPadLeft)
+            .with(Action::Right, GamepadButtonType::DPadRight)
+            .with(Action::Jump, GamepadButtonType::South)
+            .with(Action::Jump, GamepadButtonType::DPadUp)
+            .with(Action::Fall, GamepadButtonType::DPadDown)
+            .with_axis(Action::Move, GamepadControlAxis::LEFT_X)
     }
 }
 
@@ -86,7 +250,13 @@ pub enum AppState {
     #[default]
     MainMenu,
     InGame,
-    GameOver,
+    Lobby,
+    /// Plays back the last recorded level
+    Replay,
+    /// Edits the levels with the mouse and plays them from any point
+    Editor,
+    Leaderboard,
+    Achievements,
 }
 
 #[derive(States, Clone, Debug, Copy, PartialEq, Eq, Hash, Default)]
@@ -96,82 +266,5 @@ pub enum GameState {
     Paused,
 }
 
-pub fn spawn_camera(mut commands: Commands, window_query: Query<&Window, With<PrimaryWindow>>) {
-    let window = window_query.get_single().unwrap();
-    let width = window.width();
-    let height = window.height();
-    let physical_width = window.physical_width();
-    let physical_height = window.physical_height();
-    info!("logical: {width}x{height}");
-    info!("physical: {physical_width}x{physical_height}");
-    commands.spawn((
-        Camera2dBundle {
-            camera: Camera {
-                hdr: true, // HDR is required for the bloom effect
-                ..default()
-            },
-            transform: Transform::from_xyz(window.width() / 2.0, window.height() / 2.0, 0.0),
-            ..default()
-        },
-        BloomSettings::NATURAL,
-    ));
-}
-
-fn update_camera(
-    mut camera: Query<&mut Transform, (With<Camera2d>, Without<Player>)>,
-    player: Query<&Transform, (With<Player>, Without<Camera2d>)>,
-    time: Res<Time>,
-) {
-    if let (Ok(mut camera), Ok(player)) = (camera.get_single_mut(), player.get_single()) {
-        let Vec3 { x, y, .. } = player.translation;
-        let direction = Vec3::new(x, y, camera.translation.z);
-        // Applies a smooth effect to camera movement using interpolation between
-        // the camera position and the player position on the x and y axes.
-        // Here we use the in-game time, to get the elapsed time (in seconds)
-        // since the previous update. This avoids jittery movement when tracking
-        // the player.
-        camera.translation = camera
-            .translation
-            .lerp(direction, time.delta_seconds() * 2.);
-    }
-}
-
-fn touch_system(
-    touches: Res<Touches>,
-    window_query: Query<&Window, With<PrimaryWindow>>,
-    mut action_state_query: Query<&mut ActionState<Action>>,
-    player_query: Query<&Transform, With<Player>>,
-) {
-    let window = window_query.get_single().unwrap();
-    if let Ok(player) = player_query.get_single() {
-        if let Ok(mut action_state) = action_state_query.get_single_mut() {
-            if touches
-                .iter()
-                .any(|touch| touch.position().x < player.translation.x)
-            {
-                action_state.press(&Action::Left);
-            } else if touches
-                .iter()
-                .any(|touch| touch.position().x >= player.translation.x)
-            {
-                action_state.press(&Action::Right);
-            }
-            if touches.iter().count() + touches.iter_just_pressed().count() >= 2 {
-                if touches
-                    .iter_just_pressed()
-                    .any(|touch| touch.position().y < window.height() / 2.)
-                {
-                    action_state.press(&Action::Jump);
-                } else if touches
-                    .iter_just_pressed()
-                    .any(|touch| touch.position().y >= window.height() / 2.)
-                {
-                    action_state.press(&Action::Fall);
-                }
-            }
-        }
-    };
-}
-
 #[derive(Default, Deref, Resource)]
 pub struct Tilesets<T>(pub HashMap<T, Handle<Image>>);
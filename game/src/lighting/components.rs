@@ -0,0 +1,89 @@
+use bevy::prelude::*;
+use bevy::render::render_resource::{AsBindGroup, ShaderRef};
+use bevy::sprite::Material2d;
+
+/// A light shining equally in all directions from the entity it is attached to.
+#[derive(Component, Debug, Clone, Copy)]
+pub struct PointLight2d {
+    pub color: Color,
+    /// How bright the light is at its center, 1 fully lights up darkness
+    pub intensity: f32,
+    /// How far the light reaches in pixels
+    pub radius: f32,
+}
+
+impl Default for PointLight2d {
+    fn default() -> Self {
+        Self {
+            color: Color::WHITE,
+            intensity: 1.,
+            radius: 64.,
+        }
+    }
+}
+
+/// Fades out a light and despawns it afterwards.
+#[derive(Component, Debug, Clone)]
+pub struct FadingLight {
+    pub timer: Timer,
+    /// The intensity of the light before it started fading
+    pub intensity: f32,
+}
+
+/// The screen-sized quad darkening the level around the lights.
+#[derive(Component, Debug)]
+pub struct LightingOverlay;
+
+pub use self::shader_types::{GpuPointLight, LightingUniform};
+
+// The size checks generated by deriving `ShaderType` are reported as unused,
+// so only the derived types are kept in a module that allows them
+#[allow(dead_code)]
+mod shader_types {
+    use bevy::prelude::*;
+    use bevy::render::render_resource::ShaderType;
+
+    use crate::lighting::MAX_LIGHTS;
+
+    /// A light as the lighting shader sees it.
+    #[derive(ShaderType, Debug, Clone, Copy, Default)]
+    pub struct GpuPointLight {
+        pub color: Vec4,
+        pub position: Vec2,
+        pub radius: f32,
+        pub intensity: f32,
+    }
+
+    #[derive(ShaderType, Debug, Clone)]
+    pub struct LightingUniform {
+        /// How bright the level is without lights, from 0 to 1
+        pub ambient: f32,
+        /// How many of the lights are used
+        pub count: u32,
+        pub lights: [GpuPointLight; MAX_LIGHTS],
+    }
+
+    impl Default for LightingUniform {
+        fn default() -> Self {
+            Self {
+                ambient: 1.,
+                count: 0,
+                lights: [GpuPointLight::default(); MAX_LIGHTS],
+            }
+        }
+    }
+}
+
+/// Draws the darkness and the lights in a single pass, using only a uniform buffer,
+/// so it also runs on WebGL2 and software renderers.
+#[derive(Asset, TypePath, AsBindGroup, Debug, Clone, Default)]
+pub struct LightingMaterial {
+    #[uniform(0)]
+    pub lighting: LightingUniform,
+}
+
+impl Material2d for LightingMaterial {
+    fn fragment_shader() -> ShaderRef {
+        "shaders/lighting.wgsl".into()
+    }
+}
@@ -0,0 +1,38 @@
+pub mod components;
+pub mod systems;
+
+use bevy::prelude::*;
+use bevy::sprite::Material2dPlugin;
+
+use crate::camera::systems::apply_camera_effects;
+use crate::lighting::components::LightingMaterial;
+use crate::{AppState, GameState};
+
+/// How many lights are drawn at once, the ones closest to the camera are picked.
+///
+/// Must match the size of the light array in `shaders/lighting.wgsl`.
+pub const MAX_LIGHTS: usize = 32;
+
+/// In front of the levels and particles.
+pub const LIGHTING_Z: f32 = 500.;
+
+pub struct LightingPlugin;
+
+impl Plugin for LightingPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_plugins(Material2dPlugin::<LightingMaterial>::default())
+            .add_systems(OnEnter(AppState::InGame), systems::spawn)
+            .add_systems(
+                Update,
+                systems::update_lighting
+                    // The overlay follows the camera, including its shake
+                    .after(apply_camera_effects)
+                    .run_if(in_state(AppState::InGame)),
+            )
+            .add_systems(
+                Update,
+                systems::fade_lights.run_if(in_state(GameState::Running)),
+            )
+            .add_systems(OnExit(AppState::InGame), systems::despawn);
+    }
+}
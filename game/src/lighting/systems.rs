@@ -0,0 +1,133 @@
+use bevy::prelude::*;
+use bevy::sprite::{MaterialMesh2dBundle, Mesh2dHandle};
+use bevy_ecs_ldtk::prelude::*;
+
+use crate::camera::components::CameraController;
+use crate::lighting::components::{
+    FadingLight, GpuPointLight, LightingMaterial, LightingOverlay, PointLight2d,
+};
+use crate::lighting::{LIGHTING_Z, MAX_LIGHTS};
+use crate::world::SpawnedLevels;
+
+/// The LDtk level field the ambient light is read from.
+const AMBIENT_LIGHT_FIELD: &str = "AmbientLight";
+
+/// How fast the ambient light changes to the one of the next level.
+const AMBIENT_LIGHT_SPEED: f32 = 2.;
+
+pub fn spawn(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<LightingMaterial>>,
+) {
+    commands.spawn((
+        MaterialMesh2dBundle {
+            mesh: Mesh2dHandle(meshes.add(Rectangle::new(1., 1.))),
+            material: materials.add(LightingMaterial::default()),
+            transform: Transform::from_xyz(0., 0., LIGHTING_Z),
+            visibility: Visibility::Hidden,
+            ..default()
+        },
+        LightingOverlay,
+    ));
+}
+
+pub fn despawn(
+    mut commands: Commands,
+    overlay_query: Query<Entity, With<LightingOverlay>>,
+    fading_light_query: Query<Entity, With<FadingLight>>,
+) {
+    for entity in overlay_query.iter().chain(fading_light_query.iter()) {
+        commands.entity(entity).despawn();
+    }
+}
+
+type CameraRelated<'a> = (&'a Transform, &'a OrthographicProjection);
+type OverlayRelated<'a> = (
+    &'a mut Transform,
+    &'a mut Visibility,
+    &'a Handle<LightingMaterial>,
+);
+
+/// Covers the view with the overlay and passes it the ambient light and the closest lights.
+pub fn update_lighting(
+    mut overlay_query: Query<OverlayRelated, With<LightingOverlay>>,
+    camera_query: Query<CameraRelated, (With<CameraController>, Without<LightingOverlay>)>,
+    light_query: Query<(&PointLight2d, &GlobalTransform)>,
+    levels: SpawnedLevels,
+    mut materials: ResMut<Assets<LightingMaterial>>,
+    time: Res<Time>,
+) {
+    let (Ok((mut transform, mut visibility, material)), Ok((camera, projection))) =
+        (overlay_query.get_single_mut(), camera_query.get_single())
+    else {
+        return;
+    };
+    let Some(material) = materials.get_mut(material) else {
+        return;
+    };
+    let center = camera.translation.truncate();
+
+    let ambient = levels
+        .containing(center)
+        .and_then(|(_, level)| level.get_float_field(AMBIENT_LIGHT_FIELD).ok().copied())
+        .unwrap_or(1.)
+        .clamp(0., 1.);
+    let lighting = &mut material.lighting;
+    lighting.ambient = lighting.ambient.lerp(
+        ambient,
+        (time.delta_seconds() * AMBIENT_LIGHT_SPEED).min(1.),
+    );
+
+    // Without darkness, there is nothing to draw
+    if lighting.ambient > 0.999 {
+        *visibility = Visibility::Hidden;
+        return;
+    }
+    *visibility = Visibility::Visible;
+
+    // A little larger than the view, so the shake does not uncover the edges
+    let size = projection.area.size() * 1.1;
+    transform.translation = center.extend(LIGHTING_Z);
+    transform.scale = size.extend(1.);
+
+    let mut lights: Vec<_> = light_query
+        .iter()
+        .map(|(light, light_transform)| (light, light_transform.translation().truncate()))
+        .filter(|(light, position)| {
+            // Lights outside the view do not reach into it
+            let reach = size / 2. + light.radius;
+            (position.x - center.x).abs() < reach.x && (position.y - center.y).abs() < reach.y
+        })
+        .collect();
+    lights.sort_by(|(_, a), (_, b)| {
+        a.distance_squared(center)
+            .total_cmp(&b.distance_squared(center))
+    });
+    lights.truncate(MAX_LIGHTS);
+
+    lighting.count = lights.len() as u32;
+    for (gpu_light, (light, position)) in lighting.lights.iter_mut().zip(lights) {
+        *gpu_light = GpuPointLight {
+            color: light.color.to_linear().to_vec4(),
+            position,
+            radius: light.radius,
+            intensity: light.intensity,
+        };
+    }
+}
+
+/// Dims the fading lights and despawns them when they are dark.
+pub fn fade_lights(
+    mut commands: Commands,
+    mut light_query: Query<(Entity, &mut PointLight2d, &mut FadingLight)>,
+    time: Res<Time>,
+) {
+    for (entity, mut light, mut fading) in light_query.iter_mut() {
+        if fading.timer.tick(time.delta()).finished() {
+            commands.entity(entity).despawn();
+        } else {
+            light.intensity = fading.intensity * (1. - fading.timer.fraction());
+        }
+    }
+}
@@ -0,0 +1,25 @@
+//! Connects the game to a lobby server.
+
+use bevy::prelude::*;
+use std::net::TcpStream;
+
+use crate::lobby::protocol::{ClientMessage, Connection, ServerMessage};
+
+/// The connection to the lobby server.
+#[derive(Resource)]
+pub struct LobbyClient(Connection);
+
+impl LobbyClient {
+    pub fn connect(address: &str) -> std::io::Result<Self> {
+        Ok(Self(Connection::new(TcpStream::connect(address)?)?))
+    }
+
+    pub fn send(&mut self, message: &ClientMessage) -> std::io::Result<()> {
+        self.0.send(message)
+    }
+
+    /// Returns the messages received since the last call, an error once the server is gone.
+    pub fn receive(&mut self) -> std::io::Result<Vec<ServerMessage>> {
+        self.0.receive()
+    }
+}
@@ -0,0 +1,36 @@
+pub mod client;
+pub mod protocol;
+pub mod server;
+pub mod systems;
+
+use bevy::prelude::*;
+
+use crate::lobby::server::LobbyServer;
+
+/// The address the lobby server listens on, unless another one is given.
+pub const DEFAULT_LOBBY_ADDRESS: &str = "127.0.0.1:7100";
+/// The players of a session, like in co-op.
+pub const MAX_PLAYERS: usize = 2;
+
+/// Runs a lobby server the games find each other through.
+pub struct LobbyServerPlugin {
+    pub listen_address: String,
+}
+
+impl Plugin for LobbyServerPlugin {
+    fn build(&self, app: &mut App) {
+        match LobbyServer::bind(&self.listen_address) {
+            Ok(server) => {
+                info!("Lobby listening on {}", self.listen_address);
+                app.insert_resource(server)
+                    .add_systems(Update, systems::serve);
+            }
+            Err(error) => {
+                error!("Could not listen on {}: {error}", self.listen_address);
+                app.add_systems(Startup, |mut exit: EventWriter<AppExit>| {
+                    exit.send(AppExit::error());
+                });
+            }
+        }
+    }
+}
@@ -0,0 +1,148 @@
+//! The messages between the lobby server and the games, one JSON object per line over TCP.
+
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::io::{ErrorKind, Read, Write};
+use std::net::{SocketAddr, TcpStream};
+
+use crate::player::components::{Character, PlayerId};
+
+/// How many bytes a connection buffers in each direction, the other side is dropped beyond it.
+pub const MAX_BUFFERED_BYTES: usize = 64 * 1024;
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub enum ClientMessage {
+    ListSessions,
+    /// Opens a new session and joins it
+    Create {
+        name: String,
+        character: Character,
+        /// The UDP port the game exchanges inputs with the other players on
+        rollback_port: u16,
+    },
+    Join {
+        session: u32,
+        character: Character,
+        rollback_port: u16,
+    },
+    Leave,
+    Ready(bool),
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub enum ServerMessage {
+    /// The sessions that can be joined
+    Sessions(Vec<SessionInfo>),
+    /// The session the game is in changed
+    Session {
+        session: SessionInfo,
+        /// The game's player in the session
+        player: PlayerId,
+    },
+    Left,
+    /// The session is full and everyone in it is ready
+    Start {
+        player: PlayerId,
+        /// Where the other player's inputs come from
+        peer: SocketAddr,
+        members: Vec<MemberInfo>,
+    },
+    Error(String),
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct SessionInfo {
+    pub id: u32,
+    pub name: String,
+    pub members: Vec<MemberInfo>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+pub struct MemberInfo {
+    pub player: PlayerId,
+    pub character: Character,
+    pub ready: bool,
+}
+
+/// A non-blocking TCP connection exchanging messages line by line.
+pub struct Connection {
+    stream: TcpStream,
+    /// The start of a line that was not received completely yet
+    buffer: Vec<u8>,
+    /// The lines that were not sent completely yet
+    outgoing: Vec<u8>,
+}
+
+impl Connection {
+    pub fn new(stream: TcpStream) -> std::io::Result<Self> {
+        stream.set_nonblocking(true)?;
+        stream.set_nodelay(true)?;
+        Ok(Self {
+            stream,
+            buffer: Vec::new(),
+            outgoing: Vec::new(),
+        })
+    }
+
+    pub fn peer_addr(&self) -> std::io::Result<SocketAddr> {
+        self.stream.peer_addr()
+    }
+
+    /// Queues the message and sends as much as the other side takes without waiting.
+    ///
+    /// The rest is sent by the next calls to `send` and `receive`.
+    pub fn send(&mut self, message: &impl Serialize) -> std::io::Result<()> {
+        let mut line = serde_json::to_vec(message)?;
+        line.push(b'\n');
+        self.outgoing.extend_from_slice(&line);
+        self.flush()
+    }
+
+    /// Sends the queued lines, an error once the other side stopped reading them.
+    fn flush(&mut self) -> std::io::Result<()> {
+        while !self.outgoing.is_empty() {
+            match self.stream.write(&self.outgoing) {
+                Ok(0) => return Err(ErrorKind::WriteZero.into()),
+                Ok(written) => {
+                    self.outgoing.drain(..written);
+                }
+                Err(error) if error.kind() == ErrorKind::WouldBlock => break,
+                Err(error) if error.kind() == ErrorKind::Interrupted => {}
+                Err(error) => return Err(error),
+            }
+        }
+        if self.outgoing.len() > MAX_BUFFERED_BYTES {
+            return Err(std::io::Error::other("the other side stopped reading"));
+        }
+        Ok(())
+    }
+
+    /// Returns the messages received since the last call, an error once the connection closed.
+    ///
+    /// Invalid messages are skipped. A line longer than [`MAX_BUFFERED_BYTES`] is an error.
+    pub fn receive<T: DeserializeOwned>(&mut self) -> std::io::Result<Vec<T>> {
+        self.flush()?;
+        let mut chunk = [0; 1024];
+        while self.buffer.len() <= MAX_BUFFERED_BYTES {
+            match self.stream.read(&mut chunk) {
+                Ok(0) => return Err(ErrorKind::ConnectionAborted.into()),
+                Ok(read) => self.buffer.extend_from_slice(&chunk[..read]),
+                Err(error) if error.kind() == ErrorKind::WouldBlock => break,
+                Err(error) if error.kind() == ErrorKind::Interrupted => {}
+                Err(error) => return Err(error),
+            }
+        }
+        let Some(end) = self.buffer.iter().rposition(|byte| *byte == b'\n') else {
+            if self.buffer.len() > MAX_BUFFERED_BYTES {
+                return Err(ErrorKind::InvalidData.into());
+            }
+            return Ok(Vec::new());
+        };
+        let lines: Vec<u8> = self.buffer.drain(..=end).collect();
+        Ok(lines
+            .split(|byte| *byte == b'\n')
+            .filter(|line| !line.is_empty())
+            .filter_map(|line| serde_json::from_slice(line).ok())
+            .collect())
+    }
+}
@@ -0,0 +1,527 @@
+//! Matches the players into sessions.
+
+use bevy::prelude::*;
+use std::collections::{BTreeMap, HashMap};
+use std::io::ErrorKind;
+use std::net::{IpAddr, SocketAddr, TcpListener};
+
+use crate::lobby::protocol::{ClientMessage, Connection, MemberInfo, ServerMessage, SessionInfo};
+use crate::lobby::MAX_PLAYERS;
+use crate::player::components::{Character, PlayerId};
+
+/// Tells the connected games apart.
+pub type ClientId = u64;
+
+struct Member {
+    client: ClientId,
+    /// Where the member's game receives the other players' inputs
+    rollback_address: SocketAddr,
+    player: PlayerId,
+    character: Character,
+    ready: bool,
+}
+
+struct Session {
+    name: String,
+    members: Vec<Member>,
+}
+
+impl Session {
+    fn info(&self, id: u32) -> SessionInfo {
+        SessionInfo {
+            id,
+            name: self.name.clone(),
+            members: self.members.iter().map(Member::info).collect(),
+        }
+    }
+}
+
+impl Member {
+    fn info(&self) -> MemberInfo {
+        MemberInfo {
+            player: self.player,
+            character: self.character,
+            ready: self.ready,
+        }
+    }
+}
+
+/// The sessions and their members, without the connections.
+#[derive(Default)]
+pub struct Lobby {
+    sessions: BTreeMap<u32, Session>,
+    /// The session each client is in
+    memberships: HashMap<ClientId, u32>,
+    next_session: u32,
+}
+
+impl Lobby {
+    /// Handles a message from a client and returns the messages to send to the clients.
+    pub fn handle(
+        &mut self,
+        client: ClientId,
+        address: IpAddr,
+        message: ClientMessage,
+    ) -> Vec<(ClientId, ServerMessage)> {
+        match message {
+            ClientMessage::ListSessions => {
+                let sessions = self
+                    .sessions
+                    .iter()
+                    .map(|(id, session)| session.info(*id))
+                    .collect();
+                vec![(client, ServerMessage::Sessions(sessions))]
+            }
+            ClientMessage::Create {
+                name,
+                character,
+                rollback_port,
+            } => {
+                let mut messages = self.leave(client);
+                let id = self.next_session;
+                self.next_session += 1;
+                self.sessions.insert(
+                    id,
+                    Session {
+                        name,
+                        members: Vec::new(),
+                    },
+                );
+                let rollback_address = SocketAddr::new(address, rollback_port);
+                messages.extend(self.join(client, id, character, rollback_address));
+                messages
+            }
+            ClientMessage::Join {
+                session,
+                character,
+                rollback_port,
+            } => {
+                if self.memberships.get(&client) == Some(&session) {
+                    return vec![error(client, "You are in this session already")];
+                }
+                let Some(joined) = self.sessions.get(&session) else {
+                    return vec![error(client, "The session does not exist")];
+                };
+                if joined.members.len() >= MAX_PLAYERS {
+                    return vec![error(client, "The session is full")];
+                }
+                let mut messages = self.leave(client);
+                let rollback_address = SocketAddr::new(address, rollback_port);
+                messages.extend(self.join(client, session, character, rollback_address));
+                messages
+            }
+            ClientMessage::Leave => self.leave(client),
+            ClientMessage::Ready(ready) => {
+                let Some(id) = self.memberships.get(&client).copied() else {
+                    return vec![error(client, "You are not in a session")];
+                };
+                let Some(session) = self.sessions.get_mut(&id) else {
+                    self.memberships.remove(&client);
+                    return vec![error(client, "The session does not exist anymore")];
+                };
+                for member in session.members.iter_mut() {
+                    if member.client == client {
+                        member.ready = ready;
+                    }
+                }
+                let full = session.members.len() == MAX_PLAYERS;
+                if full && session.members.iter().all(|member| member.ready) {
+                    self.start(id)
+                } else {
+                    self.broadcast(id)
+                }
+            }
+        }
+    }
+
+    /// Removes a client that disconnected from their session.
+    pub fn disconnect(&mut self, client: ClientId) -> Vec<(ClientId, ServerMessage)> {
+        self.leave(client)
+            .into_iter()
+            .filter(|(receiver, _)| *receiver != client)
+            .collect()
+    }
+
+    fn join(
+        &mut self,
+        client: ClientId,
+        id: u32,
+        character: Character,
+        rollback_address: SocketAddr,
+    ) -> Vec<(ClientId, ServerMessage)> {
+        let Some(session) = self.sessions.get_mut(&id) else {
+            return vec![error(client, "The session does not exist")];
+        };
+        // The players keep their number when the others leave
+        let Some(player) = PlayerId::ALL.into_iter().find(|player| {
+            session
+                .members
+                .iter()
+                .all(|member| member.player != *player)
+        }) else {
+            return vec![error(client, "The session is full")];
+        };
+        session.members.push(Member {
+            client,
+            rollback_address,
+            player,
+            character,
+            ready: false,
+        });
+        session.members.sort_by_key(|member| member.player.index());
+        self.memberships.insert(client, id);
+        self.broadcast(id)
+    }
+
+    fn leave(&mut self, client: ClientId) -> Vec<(ClientId, ServerMessage)> {
+        let Some(id) = self.memberships.remove(&client) else {
+            return Vec::new();
+        };
+        let mut messages = vec![(client, ServerMessage::Left)];
+        let Some(session) = self.sessions.get_mut(&id) else {
+            return messages;
+        };
+        session.members.retain(|member| member.client != client);
+        if session.members.is_empty() {
+            self.sessions.remove(&id);
+        } else {
+            messages.extend(self.broadcast(id));
+        }
+        messages
+    }
+
+    /// Tells the members of a session how it changed.
+    fn broadcast(&self, id: u32) -> Vec<(ClientId, ServerMessage)> {
+        let Some(session) = self.sessions.get(&id) else {
+            return Vec::new();
+        };
+        let info = session.info(id);
+        session
+            .members
+            .iter()
+            .map(|member| {
+                let message = ServerMessage::Session {
+                    session: info.clone(),
+                    player: member.player,
+                };
+                (member.client, message)
+            })
+            .collect()
+    }
+
+    /// Starts the game of a session, which closes it.
+    fn start(&mut self, id: u32) -> Vec<(ClientId, ServerMessage)> {
+        let Some(session) = self.sessions.remove(&id) else {
+            return Vec::new();
+        };
+        let members: Vec<_> = session.members.iter().map(Member::info).collect();
+        for member in session.members.iter() {
+            self.memberships.remove(&member.client);
+        }
+        session
+            .members
+            .iter()
+            .filter_map(|member| {
+                let peer = session
+                    .members
+                    .iter()
+                    .find(|other| other.client != member.client)?;
+                let start = ServerMessage::Start {
+                    player: member.player,
+                    peer: peer.rollback_address,
+                    members: members.clone(),
+                };
+                Some((member.client, start))
+            })
+            .collect()
+    }
+}
+
+fn error(client: ClientId, message: &str) -> (ClientId, ServerMessage) {
+    (client, ServerMessage::Error(message.to_string()))
+}
+
+/// Accepts games and runs the lobby for them.
+#[derive(Resource)]
+pub struct LobbyServer {
+    listener: TcpListener,
+    connections: BTreeMap<ClientId, Connection>,
+    lobby: Lobby,
+    next_client: ClientId,
+}
+
+impl LobbyServer {
+    pub fn bind(address: &str) -> std::io::Result<Self> {
+        let listener = TcpListener::bind(address)?;
+        listener.set_nonblocking(true)?;
+        Ok(Self {
+            listener,
+            connections: BTreeMap::new(),
+            lobby: Lobby::default(),
+            next_client: 0,
+        })
+    }
+
+    pub fn local_addr(&self) -> std::io::Result<SocketAddr> {
+        self.listener.local_addr()
+    }
+
+    /// Accepts the new games and answers the messages received since the last call.
+    pub fn poll(&mut self) {
+        loop {
+            match self.listener.accept() {
+                Ok((stream, address)) => match Connection::new(stream) {
+                    Ok(connection) => {
+                        info!("{address} connected");
+                        self.connections.insert(self.next_client, connection);
+                        self.next_client += 1;
+                    }
+                    Err(error) => warn!("Could not accept {address}: {error}"),
+                },
+                Err(error) if error.kind() == ErrorKind::WouldBlock => break,
+                Err(error) => {
+                    warn!("Could not accept a connection: {error}");
+                    break;
+                }
+            }
+        }
+
+        let mut outgoing = Vec::new();
+        let mut disconnected = Vec::new();
+        for (client, connection) in self.connections.iter_mut() {
+            let address = connection.peer_addr().map(|address| address.ip());
+            match (connection.receive::<ClientMessage>(), address) {
+                (Ok(messages), Ok(address)) => {
+                    for message in messages {
+                        outgoing.extend(self.lobby.handle(*client, address, message));
+                    }
+                }
+                _ => disconnected.push(*client),
+            }
+        }
+        for client in disconnected {
+            info!("Client {client} disconnected");
+            self.connections.remove(&client);
+            outgoing.extend(self.lobby.disconnect(client));
+        }
+        for (client, message) in outgoing {
+            if let Some(connection) = self.connections.get_mut(&client) {
+                if let Err(error) = connection.send(&message) {
+                    warn!("Could not send to client {client}: {error}");
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lobby::client::LobbyClient;
+    use crate::lobby::protocol::MAX_BUFFERED_BYTES;
+    use std::io::Write;
+    use std::net::Ipv4Addr;
+    use std::time::Duration;
+
+    const LOCALHOST: IpAddr = IpAddr::V4(Ipv4Addr::LOCALHOST);
+
+    fn create(lobby: &mut Lobby, client: ClientId) -> Vec<(ClientId, ServerMessage)> {
+        lobby.handle(
+            client,
+            LOCALHOST,
+            ClientMessage::Create {
+                name: "session".to_string(),
+                character: Character::PinkMan,
+                rollback_port: 7000,
+            },
+        )
+    }
+
+    fn join(lobby: &mut Lobby, client: ClientId, port: u16) -> Vec<(ClientId, ServerMessage)> {
+        lobby.handle(
+            client,
+            LOCALHOST,
+            ClientMessage::Join {
+                session: 0,
+                character: Character::NinjaFrog,
+                rollback_port: port,
+            },
+        )
+    }
+
+    #[test]
+    fn starts_sessions_once_everyone_is_ready() {
+        let mut lobby = Lobby::default();
+        create(&mut lobby, 1);
+        let joined = join(&mut lobby, 2, 7001);
+        // Both members learn about the new one
+        assert_eq!(joined.len(), 2);
+        let ServerMessage::Session { session, .. } = &joined[0].1 else {
+            panic!("{joined:?}");
+        };
+        assert_eq!(session.members[1].player, PlayerId::Two);
+        assert_eq!(session.members[1].character, Character::NinjaFrog);
+
+        let readied = lobby.handle(1, LOCALHOST, ClientMessage::Ready(true));
+        assert!(matches!(readied[0].1, ServerMessage::Session { .. }));
+        let started = lobby.handle(2, LOCALHOST, ClientMessage::Ready(true));
+        assert_eq!(started.len(), 2);
+        for (client, message) in started {
+            let ServerMessage::Start { player, peer, .. } = message else {
+                panic!("{message:?}");
+            };
+            let (expected_player, peer_port) = if client == 1 {
+                (PlayerId::One, 7001)
+            } else {
+                (PlayerId::Two, 7000)
+            };
+            assert_eq!(player, expected_player);
+            assert_eq!(peer, SocketAddr::new(LOCALHOST, peer_port));
+        }
+        // Started sessions can't be joined anymore
+        let sessions = lobby.handle(3, LOCALHOST, ClientMessage::ListSessions);
+        assert_eq!(sessions, vec![(3, ServerMessage::Sessions(Vec::new()))]);
+    }
+
+    #[test]
+    fn lone_hosts_cannot_start() {
+        let mut lobby = Lobby::default();
+        create(&mut lobby, 1);
+        let readied = lobby.handle(1, LOCALHOST, ClientMessage::Ready(true));
+        let [(1, ServerMessage::Session { session, .. })] = &readied[..] else {
+            panic!("{readied:?}");
+        };
+        assert!(session.members[0].ready);
+
+        // The session starts once the second player joins and readies too
+        join(&mut lobby, 2, 7001);
+        let started = lobby.handle(2, LOCALHOST, ClientMessage::Ready(true));
+        assert!(started
+            .iter()
+            .all(|(_, message)| matches!(message, ServerMessage::Start { .. })));
+    }
+
+    #[test]
+    fn sessions_hold_two_players() {
+        let mut lobby = Lobby::default();
+        create(&mut lobby, 1);
+        join(&mut lobby, 2, 7001);
+        let rejected = join(&mut lobby, 3, 7002);
+        assert!(matches!(rejected[..], [(3, ServerMessage::Error(_))]));
+
+        // The second player's place is free again once they left
+        let left = lobby.handle(2, LOCALHOST, ClientMessage::Leave);
+        assert_eq!(left[0], (2, ServerMessage::Left));
+        let joined = join(&mut lobby, 3, 7002);
+        assert!(joined.contains(&(
+            3,
+            ServerMessage::Session {
+                session: SessionInfo {
+                    id: 0,
+                    name: "session".to_string(),
+                    members: vec![
+                        MemberInfo {
+                            player: PlayerId::One,
+                            character: Character::PinkMan,
+                            ready: false,
+                        },
+                        MemberInfo {
+                            player: PlayerId::Two,
+                            character: Character::NinjaFrog,
+                            ready: false,
+                        },
+                    ],
+                },
+                player: PlayerId::Two,
+            }
+        )));
+    }
+
+    #[test]
+    fn removes_disconnected_clients() {
+        let mut lobby = Lobby::default();
+        create(&mut lobby, 1);
+        join(&mut lobby, 2, 7001);
+        let messages = lobby.disconnect(1);
+        let [(2, ServerMessage::Session { session, .. })] = &messages[..] else {
+            panic!("{messages:?}");
+        };
+        assert_eq!(session.members.len(), 1);
+        lobby.disconnect(2);
+        assert!(lobby.sessions.is_empty());
+    }
+
+    /// Polls the server until the client received a message.
+    fn receive(server: &mut LobbyServer, client: &mut LobbyClient) -> ServerMessage {
+        for _ in 0..200 {
+            server.poll();
+            if let Some(message) = client.receive().unwrap().into_iter().next() {
+                return message;
+            }
+            std::thread::sleep(Duration::from_millis(5));
+        }
+        panic!("Nothing was received");
+    }
+
+    #[test]
+    fn matches_games_on_localhost() {
+        let mut server = LobbyServer::bind("127.0.0.1:0").unwrap();
+        let address = server.local_addr().unwrap().to_string();
+        let mut host = LobbyClient::connect(&address).unwrap();
+        let mut guest = LobbyClient::connect(&address).unwrap();
+
+        host.send(&ClientMessage::Create {
+            name: "session".to_string(),
+            character: Character::VirtualGuy,
+            rollback_port: 7000,
+        })
+        .unwrap();
+        assert!(matches!(
+            receive(&mut server, &mut host),
+            ServerMessage::Session { .. }
+        ));
+
+        guest.send(&ClientMessage::ListSessions).unwrap();
+        let ServerMessage::Sessions(sessions) = receive(&mut server, &mut guest) else {
+            panic!("The sessions were not listed");
+        };
+        assert_eq!(sessions.len(), 1);
+        guest
+            .send(&ClientMessage::Join {
+                session: sessions[0].id,
+                character: Character::MaskDude,
+                rollback_port: 7001,
+            })
+            .unwrap();
+        receive(&mut server, &mut guest);
+        receive(&mut server, &mut host);
+
+        host.send(&ClientMessage::Ready(true)).unwrap();
+        receive(&mut server, &mut host);
+        receive(&mut server, &mut guest);
+        guest.send(&ClientMessage::Ready(true)).unwrap();
+        let ServerMessage::Start { player, peer, .. } = receive(&mut server, &mut guest) else {
+            panic!("The game did not start");
+        };
+        assert_eq!(player, PlayerId::Two);
+        assert_eq!(peer, SocketAddr::new(LOCALHOST, 7000));
+    }
+
+    #[test]
+    fn drops_games_that_never_end_their_line() {
+        let mut server = LobbyServer::bind("127.0.0.1:0").unwrap();
+        let mut stream = std::net::TcpStream::connect(server.local_addr().unwrap()).unwrap();
+        server.poll();
+        assert_eq!(server.connections.len(), 1);
+
+        stream.write_all(&[b'x'; MAX_BUFFERED_BYTES + 1]).unwrap();
+        for _ in 0..200 {
+            server.poll();
+            if server.connections.is_empty() {
+                return;
+            }
+            std::thread::sleep(Duration::from_millis(5));
+        }
+        panic!("The game was not dropped");
+    }
+}
@@ -0,0 +1,7 @@
+use bevy::prelude::*;
+
+use crate::lobby::server::LobbyServer;
+
+pub fn serve(mut server: ResMut<LobbyServer>) {
+    server.poll();
+}
@@ -3,8 +3,53 @@ use bevy::prelude::*;
 #[derive(Component)]
 pub struct MainMenu;
 
-#[derive(Component)]
-pub struct PlayButton;
+#[derive(Component, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MainMenuButton {
+    /// Continues the campaign from the saved level
+    Continue,
+    Play,
+    /// Plays back the last played level
+    Replay,
+    /// Finds another player to play with online
+    Lobby,
+    /// Opens the level editor
+    Editor,
+    Quit,
+    Leaderboard,
+    Achievements,
+}
 
-#[derive(Component)]
-pub struct QuitButton;
+impl MainMenuButton {
+    pub const ALL: [MainMenuButton; 8] = [
+        MainMenuButton::Continue,
+        MainMenuButton::Play,
+        MainMenuButton::Replay,
+        MainMenuButton::Lobby,
+        MainMenuButton::Editor,
+        MainMenuButton::Quit,
+        MainMenuButton::Leaderboard,
+        MainMenuButton::Achievements,
+    ];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            MainMenuButton::Continue => "Continue",
+            MainMenuButton::Play => "Play",
+            MainMenuButton::Replay => "Replay",
+            MainMenuButton::Lobby => "Online",
+            MainMenuButton::Editor => "Editor",
+            MainMenuButton::Quit => "Quit",
+            MainMenuButton::Leaderboard => "Leaderboard",
+            MainMenuButton::Achievements => "Achievements",
+        }
+    }
+
+    /// The image of the buttons shown as icons instead of a label.
+    pub fn icon(self) -> Option<&'static str> {
+        match self {
+            MainMenuButton::Leaderboard => Some("Menu/Buttons/Leaderboard.png"),
+            MainMenuButton::Achievements => Some("Menu/Buttons/Achievements.png"),
+            _ => None,
+        }
+    }
+}
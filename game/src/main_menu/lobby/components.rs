@@ -0,0 +1,57 @@
+use bevy::prelude::*;
+
+use crate::lobby::protocol::SessionInfo;
+use crate::player::components::{Character, PlayerId};
+
+/// Where the lobby server is, the lobby can only be entered with one.
+#[derive(Resource, Debug, Clone)]
+pub struct LobbySettings {
+    pub address: String,
+    /// The UDP port the game exchanges inputs with the other player on
+    pub rollback_port: u16,
+}
+
+/// What the lobby screen shows.
+#[derive(Resource, Debug, Default)]
+pub struct LobbyStatus {
+    /// The sessions that can be joined
+    pub sessions: Vec<SessionInfo>,
+    /// The session the game is in and its player in it
+    pub session: Option<(SessionInfo, PlayerId)>,
+    /// The character the session is joined with
+    pub character: Character,
+    /// The last error
+    pub message: Option<String>,
+}
+
+impl LobbyStatus {
+    /// Whether the game's player in the session is ready.
+    pub fn ready(&self) -> bool {
+        self.session.as_ref().is_some_and(|(session, player)| {
+            session
+                .members
+                .iter()
+                .any(|member| member.player == *player && member.ready)
+        })
+    }
+}
+
+#[derive(Component)]
+pub struct LobbyScreen;
+
+/// The part of the screen that is rebuilt whenever the lobby changes.
+#[derive(Component)]
+pub struct LobbyContent;
+
+#[derive(Component, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LobbyButton {
+    Refresh,
+    Create,
+    Join(u32),
+    /// Cycles through the characters
+    Character,
+    /// Toggles whether the player is ready
+    Ready,
+    Leave,
+    Back,
+}
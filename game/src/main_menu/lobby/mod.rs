@@ -0,0 +1,31 @@
+pub mod components;
+pub mod systems;
+
+use bevy::prelude::*;
+
+use crate::main_menu::lobby::components::LobbyStatus;
+use crate::AppState;
+
+/// Lists the sessions of the lobby server and joins them.
+pub struct LobbyScreenPlugin;
+
+impl Plugin for LobbyScreenPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<LobbyStatus>()
+            .add_systems(OnEnter(AppState::Lobby), (systems::connect, systems::spawn))
+            .add_systems(
+                OnExit(AppState::Lobby),
+                (systems::disconnect, systems::despawn),
+            )
+            .add_systems(
+                Update,
+                (
+                    systems::receive_lobby_messages,
+                    systems::interact_with_lobby_buttons,
+                    systems::update_content,
+                )
+                    .chain()
+                    .run_if(in_state(AppState::Lobby)),
+            );
+    }
+}
@@ -0,0 +1,329 @@
+use bevy::prelude::*;
+use std::net::{Ipv4Addr, SocketAddr};
+
+use crate::coop::components::CoOp;
+use crate::lobby::client::LobbyClient;
+use crate::lobby::protocol::{ClientMessage, MemberInfo, ServerMessage};
+use crate::lobby::MAX_PLAYERS;
+use crate::main_menu::lobby::components::{
+    LobbyButton, LobbyContent, LobbyScreen, LobbySettings, LobbyStatus,
+};
+use crate::netcode::components::{LocalPlayer, PeerConnection};
+use crate::player::components::{PlayerCharacters, PlayerId};
+use crate::transition::components::{StartTransition, TransitionStyle, TransitionTarget};
+use crate::{color, AppState};
+
+pub fn connect(
+    mut commands: Commands,
+    settings: Res<LobbySettings>,
+    mut status: ResMut<LobbyStatus>,
+) {
+    *status = LobbyStatus {
+        character: status.character,
+        ..default()
+    };
+    let result = LobbyClient::connect(&settings.address).and_then(|mut client| {
+        client.send(&ClientMessage::ListSessions)?;
+        Ok(client)
+    });
+    match result {
+        Ok(client) => commands.insert_resource(client),
+        Err(error) => {
+            warn!(
+                "Could not connect to the lobby at {}: {error}",
+                settings.address
+            );
+            status.message = Some(format!("Could not connect to {}", settings.address));
+        }
+    }
+}
+
+/// Closes the connection, which leaves the session.
+pub fn disconnect(mut commands: Commands) {
+    commands.remove_resource::<LobbyClient>();
+}
+
+pub fn spawn(mut commands: Commands) {
+    commands
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    flex_direction: FlexDirection::Column,
+                    justify_content: JustifyContent::Center,
+                    align_items: AlignItems::Center,
+                    row_gap: Val::Px(10.0),
+                    width: Val::Percent(100.0),
+                    height: Val::Percent(100.0),
+                    ..default()
+                },
+                ..default()
+            },
+            LobbyScreen,
+        ))
+        .with_children(|parent| {
+            // Title
+            parent.spawn(TextBundle::from_section(
+                "Lobby",
+                TextStyle {
+                    font_size: 64.0,
+                    color: color::PRIMARY_CONTENT,
+                    ..default()
+                },
+            ));
+
+            parent.spawn((
+                NodeBundle {
+                    style: Style {
+                        flex_direction: FlexDirection::Column,
+                        align_items: AlignItems::Center,
+                        row_gap: Val::Px(10.0),
+                        ..default()
+                    },
+                    ..default()
+                },
+                LobbyContent,
+            ));
+
+            spawn_button(parent, "Back", LobbyButton::Back);
+        });
+}
+
+pub fn despawn(mut commands: Commands, screen_query: Query<Entity, With<LobbyScreen>>) {
+    if let Ok(screen_entity) = screen_query.get_single() {
+        commands.entity(screen_entity).despawn_recursive();
+    }
+}
+
+fn spawn_button(parent: &mut ChildBuilder, label: impl Into<String>, button: LobbyButton) {
+    parent
+        .spawn((
+            ButtonBundle {
+                background_color: color::PRIMARY.into(),
+                style: Style {
+                    justify_content: JustifyContent::Center,
+                    align_items: AlignItems::Center,
+                    width: Val::Px(320.0),
+                    height: Val::Px(56.0),
+                    ..default()
+                },
+                ..default()
+            },
+            button,
+        ))
+        .with_children(|parent| {
+            parent.spawn(TextBundle::from_section(
+                label,
+                TextStyle {
+                    font_size: 28.0,
+                    color: color::PRIMARY_CONTENT,
+                    ..default()
+                },
+            ));
+        });
+}
+
+fn spawn_text(parent: &mut ChildBuilder, text: impl Into<String>, color: Color) {
+    parent.spawn(TextBundle::from_section(
+        text,
+        TextStyle {
+            font_size: 28.0,
+            color,
+            ..default()
+        },
+    ));
+}
+
+fn describe_member(member: &MemberInfo, you: PlayerId) -> String {
+    format!(
+        "Player {}{}: {} - {}",
+        member.player.index() + 1,
+        if member.player == you { " (you)" } else { "" },
+        member.character.name(),
+        if member.ready { "ready" } else { "not ready" }
+    )
+}
+
+/// Shows the sessions, or the members of the session the game is in.
+pub fn update_content(
+    mut commands: Commands,
+    status: Res<LobbyStatus>,
+    content_query: Query<Entity, With<LobbyContent>>,
+) {
+    let Ok(content) = content_query.get_single() else {
+        return;
+    };
+    if !status.is_changed() {
+        return;
+    }
+    commands
+        .entity(content)
+        .despawn_descendants()
+        .with_children(|parent| {
+            if let Some(message) = &status.message {
+                spawn_text(parent, message, color::SECONDARY);
+            }
+            match &status.session {
+                Some((session, you)) => {
+                    spawn_text(parent, &session.name, color::PRIMARY_CONTENT);
+                    for member in &session.members {
+                        spawn_text(
+                            parent,
+                            describe_member(member, *you),
+                            color::PRIMARY_CONTENT,
+                        );
+                    }
+                    // The game starts once everyone is ready
+                    let ready = if status.ready() { "Not ready" } else { "Ready" };
+                    spawn_button(parent, ready, LobbyButton::Ready);
+                    spawn_button(parent, "Leave", LobbyButton::Leave);
+                }
+                None => {
+                    let character = format!("Character: {}", status.character.name());
+                    spawn_button(parent, character, LobbyButton::Character);
+                    if status.sessions.is_empty() {
+                        spawn_text(parent, "No sessions yet", color::PRIMARY_CONTENT);
+                    }
+                    for session in &status.sessions {
+                        let label =
+                            format!("{} ({}/{MAX_PLAYERS})", session.name, session.members.len());
+                        spawn_button(parent, label, LobbyButton::Join(session.id));
+                    }
+                    spawn_button(parent, "Create", LobbyButton::Create);
+                    spawn_button(parent, "Refresh", LobbyButton::Refresh);
+                }
+            }
+        });
+}
+
+/// Connects to the other player and starts the game.
+fn start_game(
+    commands: &mut Commands,
+    rollback_port: u16,
+    player: PlayerId,
+    peer: SocketAddr,
+) -> std::io::Result<()> {
+    let local_address = (Ipv4Addr::UNSPECIFIED, rollback_port).into();
+    commands.insert_resource(PeerConnection::udp(local_address, peer)?);
+    commands.insert_resource(LocalPlayer(player));
+    commands.insert_resource(CoOp);
+    Ok(())
+}
+
+pub fn receive_lobby_messages(
+    mut commands: Commands,
+    client: Option<ResMut<LobbyClient>>,
+    mut status: ResMut<LobbyStatus>,
+    settings: Res<LobbySettings>,
+    mut player_characters: ResMut<PlayerCharacters>,
+    mut transition_events: EventWriter<StartTransition>,
+) {
+    let Some(mut client) = client else {
+        return;
+    };
+    let messages = match client.receive() {
+        Ok(messages) => messages,
+        Err(error) => {
+            warn!("Lost the connection to the lobby: {error}");
+            *status = LobbyStatus {
+                character: status.character,
+                message: Some("Lost the connection to the lobby".to_string()),
+                ..default()
+            };
+            commands.remove_resource::<LobbyClient>();
+            return;
+        }
+    };
+    for message in messages {
+        match message {
+            ServerMessage::Sessions(sessions) => status.sessions = sessions,
+            ServerMessage::Session { session, player } => {
+                status.session = Some((session, player));
+                status.message = None;
+            }
+            ServerMessage::Left => status.session = None,
+            ServerMessage::Error(error) => status.message = Some(error),
+            ServerMessage::Start {
+                player,
+                peer,
+                members,
+            } => {
+                **player_characters = members
+                    .iter()
+                    .map(|member| (member.player, member.character))
+                    .collect();
+                if let Err(error) = start_game(&mut commands, settings.rollback_port, player, peer)
+                {
+                    warn!("Could not connect to the other player: {error}");
+                    status.session = None;
+                    status.message = Some("Could not connect to the other player".to_string());
+                    return;
+                }
+                info!("Starting the game as player {}", player.index() + 1);
+                transition_events.send(StartTransition {
+                    target: TransitionTarget::App(AppState::InGame),
+                    style: TransitionStyle::Wipe,
+                });
+            }
+        }
+    }
+}
+
+type ColorForInteraction<'a> = (&'a Interaction, &'a mut BackgroundColor, &'a LobbyButton);
+
+pub fn interact_with_lobby_buttons(
+    mut button_query: Query<ColorForInteraction, Changed<Interaction>>,
+    mut client: Option<ResMut<LobbyClient>>,
+    mut status: ResMut<LobbyStatus>,
+    settings: Res<LobbySettings>,
+    mut transition_events: EventWriter<StartTransition>,
+) {
+    for (interaction, mut background_color, button) in button_query.iter_mut() {
+        match interaction {
+            Interaction::None => {
+                *background_color = color::PRIMARY.into();
+                continue;
+            }
+            Interaction::Hovered => {
+                *background_color = color::PRIMARY_HOVER.into();
+                continue;
+            }
+            Interaction::Pressed => {
+                *background_color = color::PRIMARY.into();
+            }
+        }
+        let messages = match *button {
+            LobbyButton::Back => {
+                transition_events.send(StartTransition {
+                    target: TransitionTarget::App(AppState::MainMenu),
+                    style: TransitionStyle::Fade,
+                });
+                continue;
+            }
+            LobbyButton::Character => {
+                status.character = status.character.next();
+                continue;
+            }
+            LobbyButton::Refresh => vec![ClientMessage::ListSessions],
+            LobbyButton::Create => vec![ClientMessage::Create {
+                name: format!("{}'s session", status.character.name()),
+                character: status.character,
+                rollback_port: settings.rollback_port,
+            }],
+            LobbyButton::Join(session) => vec![ClientMessage::Join {
+                session,
+                character: status.character,
+                rollback_port: settings.rollback_port,
+            }],
+            LobbyButton::Ready => vec![ClientMessage::Ready(!status.ready())],
+            LobbyButton::Leave => vec![ClientMessage::Leave, ClientMessage::ListSessions],
+        };
+        let Some(client) = client.as_deref_mut() else {
+            status.message = Some("Not connected to the lobby".to_string());
+            continue;
+        };
+        let result = messages.iter().try_for_each(|message| client.send(message));
+        if let Err(error) = result {
+            warn!("Could not send to the lobby: {error}");
+        }
+    }
+}
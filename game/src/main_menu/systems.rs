@@ -1,9 +1,29 @@
 use bevy::prelude::*;
 
-use crate::main_menu::components::{MainMenu, PlayButton, QuitButton};
-use crate::{color, AppState, GameState};
+use crate::editor::components::EditorSettings;
+use crate::main_menu::components::{MainMenu, MainMenuButton};
+use crate::main_menu::lobby::components::LobbySettings;
+use crate::save::components::{PendingStart, SaveGame};
+use crate::transition::components::{StartTransition, TransitionStyle, TransitionTarget};
+use crate::{color, AppState};
 
-pub fn spawn(mut commands: Commands) {
+pub fn spawn(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    save: Res<SaveGame>,
+    lobby_settings: Option<Res<LobbySettings>>,
+    editor_settings: Option<Res<EditorSettings>>,
+) {
+    let buttons: Vec<_> = MainMenuButton::ALL
+        .into_iter()
+        .filter(|button| match button {
+            MainMenuButton::Continue => save.current_level.is_some(),
+            // The lobby needs a lobby server
+            MainMenuButton::Lobby => lobby_settings.is_some(),
+            MainMenuButton::Editor => editor_settings.is_some(),
+            _ => true,
+        })
+        .collect();
     commands
         .spawn((
             NodeBundle {
@@ -22,149 +42,150 @@ pub fn spawn(mut commands: Commands) {
         ))
         .with_children(|parent| {
             // Title
+            parent.spawn(TextBundle::from_section(
+                "Awesome Game",
+                TextStyle {
+                    font_size: 64.0,
+                    color: color::PRIMARY_CONTENT,
+                    ..default()
+                },
+            ));
+
+            // Buttons with a label, two per row so all of them fit the window
             parent
                 .spawn(NodeBundle {
                     style: Style {
-                        flex_direction: FlexDirection::Row,
-                        justify_content: JustifyContent::Center,
-                        align_items: AlignItems::Center,
+                        display: Display::Grid,
+                        grid_template_columns: RepeatedGridTrack::px(2, 200.0),
+                        row_gap: Val::Px(10.0),
+                        column_gap: Val::Px(10.0),
                         ..default()
                     },
                     ..default()
                 })
                 .with_children(|parent| {
-                    parent.spawn(TextBundle {
-                        text: Text {
-                            justify: JustifyText::Center,
-                            sections: vec![TextSection {
-                                value: "Awesome Game".to_string(),
-                                style: TextStyle {
-                                    font_size: 64.0,
-                                    color: color::PRIMARY_CONTENT,
-                                    ..default()
-                                },
-                            }],
-                            ..default()
-                        },
-                        ..default()
-                    });
+                    for &button in buttons.iter().filter(|button| button.icon().is_none()) {
+                        spawn_label_button(parent, button);
+                    }
                 });
 
-            // Play Button
+            // Buttons with an icon
             parent
-                .spawn((
-                    ButtonBundle {
-                        background_color: color::PRIMARY.into(),
-                        style: Style {
-                            justify_content: JustifyContent::Center,
-                            align_items: AlignItems::Center,
-                            width: Val::Px(200.0),
-                            height: Val::Px(80.0),
-                            ..default()
-                        },
+                .spawn(NodeBundle {
+                    style: Style {
+                        flex_direction: FlexDirection::Row,
+                        column_gap: Val::Px(10.0),
                         ..default()
                     },
-                    PlayButton,
-                ))
+                    ..default()
+                })
                 .with_children(|parent| {
-                    parent.spawn(TextBundle {
-                        text: Text {
-                            justify: JustifyText::Center,
-                            sections: vec![TextSection {
-                                value: "Play".to_string(),
-                                style: TextStyle {
-                                    font_size: 32.0,
-                                    color: color::PRIMARY_CONTENT,
+                    for &button in &buttons {
+                        let Some(icon) = button.icon() else {
+                            continue;
+                        };
+                        parent.spawn((
+                            ButtonBundle {
+                                image: UiImage::new(asset_server.load(icon)),
+                                style: Style {
+                                    width: Val::Px(42.0),
+                                    height: Val::Px(44.0),
                                     ..default()
                                 },
-                            }],
-                            ..default()
-                        },
-                        ..default()
-                    });
+                                ..default()
+                            },
+                            button,
+                        ));
+                    }
                 });
+        });
+}
 
-            // Quit Button
-            parent
-                .spawn((
-                    ButtonBundle {
-                        background_color: color::PRIMARY.into(),
-                        style: Style {
-                            justify_content: JustifyContent::Center,
-                            align_items: AlignItems::Center,
-                            width: Val::Px(200.0),
-                            height: Val::Px(80.0),
-                            ..default()
-                        },
-                        ..default()
-                    },
-                    QuitButton,
-                ))
-                .with_children(|parent| {
-                    parent.spawn(TextBundle {
-                        text: Text {
-                            justify: JustifyText::Center,
-                            sections: vec![TextSection {
-                                value: "Quit".to_string(),
-                                style: TextStyle {
-                                    font_size: 32.0,
-                                    color: color::PRIMARY_CONTENT,
-                                    ..default()
-                                },
-                            }],
-
-                            ..default()
-                        },
-                        ..default()
-                    });
-                });
+fn spawn_label_button(parent: &mut ChildBuilder, button: MainMenuButton) {
+    parent
+        .spawn((
+            ButtonBundle {
+                background_color: color::PRIMARY.into(),
+                style: Style {
+                    justify_content: JustifyContent::Center,
+                    align_items: AlignItems::Center,
+                    height: Val::Px(64.0),
+                    ..default()
+                },
+                ..default()
+            },
+            button,
+        ))
+        .with_children(|parent| {
+            parent.spawn(TextBundle::from_section(
+                button.label(),
+                TextStyle {
+                    font_size: 32.0,
+                    color: color::PRIMARY_CONTENT,
+                    ..default()
+                },
+            ));
         });
 }
+
 pub fn despawn(mut commands: Commands, main_menu_query: Query<Entity, With<MainMenu>>) {
     if let Ok(main_menu_entity) = main_menu_query.get_single() {
         commands.entity(main_menu_entity).despawn_recursive();
     }
 }
 
-type ColorForInteraction<'a> = (&'a Interaction, &'a mut BackgroundColor);
+type ButtonForInteraction<'a> = (
+    &'a Interaction,
+    &'a mut BackgroundColor,
+    &'a mut UiImage,
+    &'a MainMenuButton,
+);
 
-pub fn interact_with_play_button(
-    mut button_query: Query<ColorForInteraction, (Changed<Interaction>, With<PlayButton>)>,
-    mut app_state_next: ResMut<NextState<AppState>>,
-    mut game_state_next: ResMut<NextState<GameState>>,
-) {
-    if let Ok((interaction, mut background_color)) = button_query.get_single_mut() {
-        match interaction {
-            Interaction::None => {
-                *background_color = color::PRIMARY.into();
-            }
-            Interaction::Pressed => {
-                *background_color = color::PRIMARY.into();
-                app_state_next.set(AppState::InGame);
-                game_state_next.set(GameState::Running);
-            }
-            Interaction::Hovered => {
-                *background_color = color::PRIMARY_HOVER.into();
-            }
-        }
-    }
-}
-pub fn interact_with_quit_button(
+pub fn interact_with_buttons(
+    mut commands: Commands,
+    mut button_query: Query<ButtonForInteraction, Changed<Interaction>>,
+    mut transition_events: EventWriter<StartTransition>,
     mut app_exit_event_writer: EventWriter<AppExit>,
-    mut button_query: Query<ColorForInteraction, (Changed<Interaction>, With<QuitButton>)>,
+    save: Res<SaveGame>,
 ) {
-    if let Ok((interaction, mut background_color)) = button_query.get_single_mut() {
-        match interaction {
-            Interaction::None => {
-                *background_color = color::PRIMARY.into();
+    for (interaction, mut background_color, mut image, button) in button_query.iter_mut() {
+        // The icons are tinted, the other buttons change their background
+        let hovered = *interaction == Interaction::Hovered;
+        if button.icon().is_some() {
+            image.color = if hovered {
+                color::PRIMARY_HOVER
+            } else {
+                Color::WHITE
+            };
+        } else if hovered {
+            *background_color = color::PRIMARY_HOVER.into();
+        } else {
+            *background_color = color::PRIMARY.into();
+        }
+        if *interaction != Interaction::Pressed {
+            continue;
+        }
+        let (state, style) = match *button {
+            MainMenuButton::Continue => {
+                if let Some(level) = &save.current_level {
+                    commands.insert_resource(PendingStart(level.clone()));
+                }
+                (AppState::InGame, TransitionStyle::Wipe)
             }
-            Interaction::Pressed => {
-                *background_color = color::PRIMARY.into();
+            MainMenuButton::Play => (AppState::InGame, TransitionStyle::Wipe),
+            MainMenuButton::Replay => (AppState::Replay, TransitionStyle::Wipe),
+            MainMenuButton::Lobby => (AppState::Lobby, TransitionStyle::Fade),
+            MainMenuButton::Editor => (AppState::Editor, TransitionStyle::Fade),
+            MainMenuButton::Leaderboard => (AppState::Leaderboard, TransitionStyle::Fade),
+            MainMenuButton::Achievements => (AppState::Achievements, TransitionStyle::Fade),
+            MainMenuButton::Quit => {
                 app_exit_event_writer.send(AppExit::Success);
+                continue;
             }
-            Interaction::Hovered => {
-                *background_color = color::PRIMARY_HOVER.into();
-            }
-        }
+        };
+        transition_events.send(StartTransition {
+            target: TransitionTarget::App(state),
+            style,
+        });
     }
 }
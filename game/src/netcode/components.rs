@@ -0,0 +1,26 @@
+use bevy::prelude::*;
+use std::net::SocketAddr;
+
+use crate::netcode::session::RollbackSession;
+use crate::netcode::snapshot::PlayerSnapshot;
+use crate::netcode::transport::{Transport, UdpTransport};
+use crate::player::components::PlayerId;
+
+/// The player played on this machine, the other one is played by the peer.
+#[derive(Resource, Debug, Clone, Copy, Deref)]
+pub struct LocalPlayer(pub PlayerId);
+
+/// The connection to the peer.
+#[derive(Resource, Deref, DerefMut)]
+pub struct PeerConnection(pub Box<dyn Transport>);
+
+impl PeerConnection {
+    /// Exchanges the inputs over UDP, from a local address to the peer's.
+    pub fn udp(local_address: SocketAddr, peer: SocketAddr) -> std::io::Result<Self> {
+        Ok(Self(Box::new(UdpTransport::new(local_address, peer)?)))
+    }
+}
+
+/// The session with the peer, started once both players are spawned.
+#[derive(Resource, Deref, DerefMut)]
+pub struct Session(pub RollbackSession<Vec<PlayerSnapshot>>);
@@ -0,0 +1,40 @@
+pub mod components;
+pub mod session;
+pub mod snapshot;
+pub mod systems;
+pub mod transport;
+
+use bevy::prelude::*;
+
+use crate::netcode::components::{PeerConnection, Session};
+use crate::{AppState, GameState};
+
+/// The port the peers exchange their inputs on, unless another one is given.
+pub const DEFAULT_ROLLBACK_PORT: u16 = 7000;
+
+/// Plays co-op with a peer over UDP, with GGPO-style rollback.
+///
+/// Both peers move the players with the same fixed [`crate::player::MovementStep`]
+/// as offline games, once a [`PeerConnection`] and the [`components::LocalPlayer`] are inserted.
+/// Needs the [`crate::coop::CoopPlugin`] for the second player.
+pub struct RollbackPlugin;
+
+impl Plugin for RollbackPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            Update,
+            systems::start_session
+                .run_if(not(resource_exists::<Session>))
+                .run_if(resource_exists::<PeerConnection>)
+                .run_if(in_state(AppState::InGame)),
+        )
+        .add_systems(
+            FixedUpdate,
+            systems::advance_session
+                .run_if(resource_exists::<Session>)
+                .run_if(in_state(AppState::InGame))
+                .run_if(in_state(GameState::Running)),
+        )
+        .add_systems(OnExit(AppState::InGame), systems::stop_session);
+    }
+}
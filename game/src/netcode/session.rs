@@ -0,0 +1,458 @@
+//! GGPO-style rollback between two peers.
+//!
+//! Each peer simulates every frame right away, predicting that the other player keeps
+//! pressing what they pressed last. When the other player's actual input arrives and
+//! differs from the prediction, the simulation is rolled back to that frame and re-simulated.
+//! The peers exchange checksums of the frames both inputs are known for to detect desyncs.
+
+use std::collections::{HashMap, VecDeque};
+
+use crate::input::bits::InputBits;
+use crate::netcode::transport::Transport;
+
+/// The game a session rolls back, which is simulated one frame at a time.
+pub trait Simulation {
+    /// Everything a frame changes, to roll back to
+    type Snapshot;
+
+    fn snapshot(&mut self) -> Self::Snapshot;
+    fn restore(&mut self, snapshot: &Self::Snapshot);
+    /// Simulates a frame with the inputs of both players.
+    fn step(&mut self, inputs: [InputBits; 2]);
+    /// Returns the checksum of the current state, which is the same on every peer.
+    fn checksum(&mut self) -> u64;
+}
+
+/// How many frames the local input is delayed by, which hides the latency of short connections
+pub const INPUT_DELAY: u32 = 2;
+/// How many frames the simulation may run ahead of the other player's inputs
+pub const MAX_PREDICTION: u32 = 8;
+/// How often the peers compare their simulations, in frames
+pub const CHECKSUM_INTERVAL: u32 = 10;
+/// The most inputs sent in one message
+const MAX_INPUTS_PER_MESSAGE: usize = 64;
+
+const INPUTS_TAG: u8 = 0;
+const CHECKSUM_TAG: u8 = 1;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Message {
+    /// The sender's inputs from the `start` frame on, sent again until they are acknowledged
+    Inputs {
+        /// How many of the receiver's inputs the sender has
+        ack: u32,
+        start: u32,
+        inputs: Vec<InputBits>,
+    },
+    /// The checksum of the state after a frame both inputs are known for
+    Checksum { frame: u32, checksum: u64 },
+}
+
+impl Message {
+    pub fn encode(&self) -> Vec<u8> {
+        match self {
+            Message::Inputs { ack, start, inputs } => {
+                let mut bytes = vec![INPUTS_TAG];
+                bytes.extend(ack.to_le_bytes());
+                bytes.extend(start.to_le_bytes());
+                bytes.extend(inputs.iter().map(|input| input.0));
+                bytes
+            }
+            Message::Checksum { frame, checksum } => {
+                let mut bytes = vec![CHECKSUM_TAG];
+                bytes.extend(frame.to_le_bytes());
+                bytes.extend(checksum.to_le_bytes());
+                bytes
+            }
+        }
+    }
+
+    /// Returns `None` for malformed messages.
+    pub fn decode(bytes: &[u8]) -> Option<Self> {
+        let u32_at = |at: usize| Some(u32::from_le_bytes(bytes.get(at..at + 4)?.try_into().ok()?));
+        match *bytes.first()? {
+            INPUTS_TAG => Some(Message::Inputs {
+                ack: u32_at(1)?,
+                start: u32_at(5)?,
+                inputs: bytes[9..].iter().copied().map(InputBits).collect(),
+            }),
+            CHECKSUM_TAG if bytes.len() == 13 => Some(Message::Checksum {
+                frame: u32_at(1)?,
+                checksum: u64::from_le_bytes(bytes[5..13].try_into().ok()?),
+            }),
+            _ => None,
+        }
+    }
+}
+
+pub struct RollbackSession<S> {
+    /// The index of the local player, 0 or 1
+    pub local_player: usize,
+    /// The frame that is simulated next
+    frame: u32,
+    local_inputs: Vec<InputBits>,
+    /// The other player's inputs, without gaps from the first frame
+    remote_inputs: Vec<InputBits>,
+    /// The other player's inputs the simulated frames used, which may have been predicted
+    used_remote_inputs: Vec<InputBits>,
+    /// How many of the local inputs the other peer has
+    acknowledged: u32,
+    /// The states before the most recent frames and those frames, to roll back to
+    snapshots: VecDeque<(u32, S)>,
+    /// The checksums of the frames that may still be simulated again
+    unconfirmed_checksums: HashMap<u32, u64>,
+    checksums: HashMap<u32, u64>,
+    remote_checksums: HashMap<u32, u64>,
+    /// The frames up to which both inputs are known and the checksums were taken
+    confirmed: u32,
+    /// How many checksums matched the other peer's
+    pub verified: u32,
+    /// The first frame the peers simulated differently
+    pub desync: Option<u32>,
+    /// How many frames were re-simulated in total
+    pub resimulated: u32,
+}
+
+impl<S> RollbackSession<S> {
+    pub fn new(local_player: usize) -> Self {
+        Self {
+            local_player,
+            frame: 0,
+            local_inputs: Vec::new(),
+            remote_inputs: Vec::new(),
+            used_remote_inputs: Vec::new(),
+            acknowledged: 0,
+            snapshots: VecDeque::new(),
+            unconfirmed_checksums: HashMap::new(),
+            checksums: HashMap::new(),
+            remote_checksums: HashMap::new(),
+            confirmed: 0,
+            verified: 0,
+            desync: None,
+            resimulated: 0,
+        }
+    }
+
+    /// The frame that is simulated next.
+    pub fn frame(&self) -> u32 {
+        self.frame
+    }
+
+    /// Exchanges inputs with the other peer and simulates the next frame.
+    ///
+    /// Returns whether the frame was simulated, which waits while the other player's inputs
+    /// lag too far behind.
+    pub fn advance(
+        &mut self,
+        local_input: InputBits,
+        simulation: &mut impl Simulation<Snapshot = S>,
+        transport: &mut dyn Transport,
+    ) -> bool {
+        let rollback_frame = self.receive(transport);
+        if let Some(frame) = rollback_frame {
+            self.roll_back(frame, simulation);
+        }
+
+        let frame = self.frame();
+        let stalled = frame >= self.remote_inputs.len() as u32 + MAX_PREDICTION;
+        if !stalled {
+            // The frames before the delay are played without local input
+            let delayed = (frame + INPUT_DELAY) as usize;
+            self.local_inputs.resize(delayed, InputBits::default());
+            self.local_inputs.push(local_input);
+            self.simulate(simulation);
+        }
+
+        self.confirm(transport);
+        self.send_inputs(transport);
+        !stalled
+    }
+
+    /// Handles the received messages and returns the first frame that was mispredicted.
+    fn receive(&mut self, transport: &mut dyn Transport) -> Option<u32> {
+        let mut rollback_frame = None;
+        for message in transport
+            .receive()
+            .iter()
+            .filter_map(|bytes| Message::decode(bytes))
+        {
+            match message {
+                Message::Inputs { ack, start, inputs } => {
+                    self.acknowledged = self.acknowledged.max(ack);
+                    for (frame, input) in (start..).zip(inputs) {
+                        // Inputs after a gap are sent again until they are acknowledged
+                        if frame != self.remote_inputs.len() as u32 {
+                            continue;
+                        }
+                        self.remote_inputs.push(input);
+                        let mispredicted = self
+                            .used_remote_inputs
+                            .get(frame as usize)
+                            .is_some_and(|used| *used != input);
+                        if mispredicted && rollback_frame.is_none() {
+                            rollback_frame = Some(frame);
+                        }
+                    }
+                }
+                Message::Checksum { frame, checksum } => {
+                    self.remote_checksums.insert(frame, checksum);
+                }
+            }
+        }
+        rollback_frame
+    }
+
+    /// The other player's input for a frame, predicted from their last one when unknown.
+    fn remote_input(&self, frame: u32) -> InputBits {
+        self.remote_inputs
+            .get(frame as usize)
+            .or(self.remote_inputs.last())
+            .copied()
+            .unwrap_or_default()
+    }
+
+    fn inputs(&self, frame: u32) -> [InputBits; 2] {
+        let local = self.local_inputs[frame as usize];
+        let remote = self.remote_input(frame);
+        if self.local_player == 0 {
+            [local, remote]
+        } else {
+            [remote, local]
+        }
+    }
+
+    fn simulate(&mut self, simulation: &mut impl Simulation<Snapshot = S>) {
+        let frame = self.frame();
+        self.snapshots.push_back((frame, simulation.snapshot()));
+        if self.snapshots.len() > MAX_PREDICTION as usize + 1 {
+            self.snapshots.pop_front();
+        }
+        self.used_remote_inputs.truncate(frame as usize);
+        self.used_remote_inputs.push(self.remote_input(frame));
+        simulation.step(self.inputs(frame));
+        if (frame + 1) % CHECKSUM_INTERVAL == 0 {
+            self.unconfirmed_checksums
+                .insert(frame, simulation.checksum());
+        }
+        self.frame += 1;
+    }
+
+    /// Restores the state before the frame and simulates the frames since again.
+    fn roll_back(&mut self, frame: u32, simulation: &mut impl Simulation<Snapshot = S>) {
+        let current = self.frame();
+        let Some(index) = self
+            .snapshots
+            .iter()
+            .position(|(snapshot_frame, _)| *snapshot_frame == frame)
+        else {
+            // The frame can no longer be corrected, so the peers will disagree from here on
+            self.report_desync(frame);
+            return;
+        };
+        self.snapshots.truncate(index + 1);
+        if let Some((_, snapshot)) = self.snapshots.pop_back() {
+            simulation.restore(&snapshot);
+        }
+        self.frame = frame;
+        while self.frame() < current {
+            self.simulate(simulation);
+            self.resimulated += 1;
+        }
+    }
+
+    /// Takes the checksums of the frames both inputs are known for and compares them.
+    fn confirm(&mut self, transport: &mut dyn Transport) {
+        let confirmed = self.frame().min(self.remote_inputs.len() as u32);
+        for frame in self.confirmed..confirmed {
+            let Some(checksum) = self.unconfirmed_checksums.remove(&frame) else {
+                continue;
+            };
+            self.checksums.insert(frame, checksum);
+            transport.send(Message::Checksum { frame, checksum }.encode());
+        }
+        self.confirmed = self.confirmed.max(confirmed);
+
+        let compared: Vec<u32> = self
+            .remote_checksums
+            .keys()
+            .filter(|frame| self.checksums.contains_key(frame))
+            .copied()
+            .collect();
+        for frame in compared {
+            let remote = self.remote_checksums.remove(&frame);
+            if remote == self.checksums.remove(&frame) {
+                self.verified += 1;
+            } else {
+                self.report_desync(frame);
+            }
+        }
+    }
+
+    /// Remembers the frame if it is the first one the peers simulated differently.
+    fn report_desync(&mut self, frame: u32) {
+        if self.desync.map_or(true, |desync| frame < desync) {
+            self.desync = Some(frame);
+        }
+    }
+
+    fn send_inputs(&mut self, transport: &mut dyn Transport) {
+        let start = self.acknowledged.min(self.local_inputs.len() as u32);
+        let inputs: Vec<_> = self.local_inputs[start as usize..]
+            .iter()
+            .take(MAX_INPUTS_PER_MESSAGE)
+            .copied()
+            .collect();
+        let message = Message::Inputs {
+            ack: self.remote_inputs.len() as u32,
+            start,
+            inputs,
+        };
+        transport.send(message.encode());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hash::fnv1a;
+    use crate::netcode::transport::{LinkConditions, LoopbackTransport};
+    use crate::Action;
+
+    /// Two players walking along a line, who are stopped by a wall.
+    struct Line {
+        positions: [i32; 2],
+        wall: Option<i32>,
+    }
+
+    impl Line {
+        fn new(wall: Option<i32>) -> Self {
+            Self {
+                positions: [0, 16],
+                wall,
+            }
+        }
+    }
+
+    impl Simulation for Line {
+        type Snapshot = [i32; 2];
+
+        fn snapshot(&mut self) -> [i32; 2] {
+            self.positions
+        }
+
+        fn restore(&mut self, snapshot: &[i32; 2]) {
+            self.positions = *snapshot;
+        }
+
+        fn step(&mut self, inputs: [InputBits; 2]) {
+            for (position, input) in self.positions.iter_mut().zip(inputs) {
+                let speed = if input.pressed(Action::Jump) { 2 } else { 1 };
+                let direction = i32::from(input.pressed(Action::Right))
+                    - i32::from(input.pressed(Action::Left));
+                let next = *position + direction * speed;
+                if self.wall.map_or(true, |wall| (next - wall).abs() > 1) {
+                    *position = next;
+                }
+            }
+        }
+
+        fn checksum(&mut self) -> u64 {
+            let bytes: Vec<u8> = self
+                .positions
+                .iter()
+                .flat_map(|x| x.to_le_bytes())
+                .collect();
+            fnv1a(&bytes)
+        }
+    }
+
+    /// Scripted inputs that change often, so predictions fail.
+    fn scripted_input(player: usize, frame: u32) -> InputBits {
+        let phase = (frame / (7 + player as u32 * 5)) % 4;
+        match phase {
+            0 => InputBits::from_actions([Action::Right]),
+            1 => InputBits::from_actions([Action::Right, Action::Jump]),
+            2 => InputBits::from_actions([Action::Left]),
+            _ => InputBits::default(),
+        }
+    }
+
+    /// Runs both peers over the simulated connection for a number of ticks.
+    fn run(
+        conditions: LinkConditions,
+        ticks: u32,
+        mut lines: [Line; 2],
+    ) -> [RollbackSession<[i32; 2]>; 2] {
+        let (a, b) = LoopbackTransport::pair(conditions);
+        let mut transports = [a, b];
+        let mut sessions = [RollbackSession::new(0), RollbackSession::new(1)];
+        for _ in 0..ticks {
+            for player in 0..2 {
+                let input = scripted_input(player, sessions[player].frame());
+                sessions[player].advance(input, &mut lines[player], &mut transports[player]);
+            }
+        }
+        sessions
+    }
+
+    #[test]
+    fn encodes_messages() {
+        let messages = [
+            Message::Inputs {
+                ack: 3,
+                start: 70_000,
+                inputs: vec![InputBits(1), InputBits(6)],
+            },
+            Message::Checksum {
+                frame: 9,
+                checksum: u64::MAX - 1,
+            },
+        ];
+        for message in messages {
+            assert_eq!(Message::decode(&message.encode()), Some(message));
+        }
+        assert_eq!(Message::decode(&[]), None);
+        assert_eq!(Message::decode(&[CHECKSUM_TAG, 1]), None);
+        assert_eq!(Message::decode(&[7]), None);
+    }
+
+    #[test]
+    fn peers_stay_in_sync_over_a_perfect_connection() {
+        let [a, b] = run(
+            LinkConditions::default(),
+            300,
+            [Line::new(None), Line::new(None)],
+        );
+        assert_eq!(a.desync, None);
+        assert_eq!(b.desync, None);
+        assert!(a.verified >= 25, "{} checksums verified", a.verified);
+        assert!(a.frame() >= 295);
+    }
+
+    #[test]
+    fn peers_stay_in_sync_despite_latency_and_loss() {
+        let conditions = LinkConditions {
+            latency: 4,
+            loss: 0.25,
+            seed: 7,
+        };
+        let [a, b] = run(conditions, 600, [Line::new(None), Line::new(None)]);
+        assert_eq!(a.desync, None);
+        assert_eq!(b.desync, None);
+        assert!(a.verified >= 20, "{} checksums verified", a.verified);
+        assert!(b.verified >= 20, "{} checksums verified", b.verified);
+        // The predictions failed and were corrected
+        assert!(a.resimulated > 0 && b.resimulated > 0);
+        // The peers only wait for each other now and then
+        assert!(a.frame() >= 300, "stalled at frame {}", a.frame());
+    }
+
+    #[test]
+    fn detects_desyncs() {
+        // A wall only one peer has makes their simulations differ
+        let lines = [Line::new(None), Line::new(Some(4))];
+        let [a, b] = run(LinkConditions::default(), 300, lines);
+        assert!(a.desync.is_some());
+        assert!(b.desync.is_some());
+    }
+}
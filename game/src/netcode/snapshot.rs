@@ -0,0 +1,162 @@
+//! Rolls the players back by snapshotting what the movement step changes about them.
+
+use bevy::prelude::*;
+use bevy_rapier2d::prelude::*;
+use std::hash::Hasher;
+
+use crate::hash::Fnv1a;
+use crate::input::bits::InputBits;
+use crate::netcode::session::Simulation;
+use crate::player::components::{
+    Breath, Health, Invulnerable, Knockback, Player, PlayerId, PlayerInput, Surroundings,
+};
+use crate::player::MovementStep;
+
+/// What the movement step changes about a player.
+#[derive(Debug, Clone)]
+pub struct PlayerSnapshot {
+    pub entity: Entity,
+    pub transform: Transform,
+    pub global_transform: GlobalTransform,
+    pub velocity: Velocity,
+    /// The translation the character controller has yet to apply
+    pub controller_translation: Option<Vec2>,
+    pub input: PlayerInput,
+    pub surroundings: Surroundings,
+    pub health: Health,
+    pub breath: Breath,
+    pub knockback: Option<Knockback>,
+    pub invulnerable: Option<Invulnerable>,
+}
+
+type SnapshottedPlayer<'a> = (
+    Entity,
+    &'a Transform,
+    &'a GlobalTransform,
+    &'a Velocity,
+    &'a KinematicCharacterController,
+    &'a PlayerInput,
+    &'a Surroundings,
+    &'a Health,
+    &'a Breath,
+    Option<&'a Knockback>,
+    Option<&'a Invulnerable>,
+);
+
+/// Simulates the frames of a session by running the [`MovementStep`] on the players.
+pub struct WorldSimulation<'w>(pub &'w mut World);
+
+impl Simulation for WorldSimulation<'_> {
+    type Snapshot = Vec<PlayerSnapshot>;
+
+    fn snapshot(&mut self) -> Vec<PlayerSnapshot> {
+        self.0
+            .query_filtered::<SnapshottedPlayer, With<Player>>()
+            .iter(self.0)
+            .map(
+                |(
+                    entity,
+                    transform,
+                    global_transform,
+                    velocity,
+                    controller,
+                    input,
+                    surroundings,
+                    health,
+                    breath,
+                    knockback,
+                    invulnerable,
+                )| PlayerSnapshot {
+                    entity,
+                    transform: *transform,
+                    global_transform: *global_transform,
+                    velocity: *velocity,
+                    controller_translation: controller.translation,
+                    input: *input,
+                    surroundings: *surroundings,
+                    health: *health,
+                    breath: breath.clone(),
+                    knockback: knockback.cloned(),
+                    invulnerable: invulnerable.cloned(),
+                },
+            )
+            .collect()
+    }
+
+    fn restore(&mut self, snapshot: &Vec<PlayerSnapshot>) {
+        for player in snapshot {
+            let Some(mut entity) = self.0.get_entity_mut(player.entity) else {
+                continue;
+            };
+            // Changing the transform also moves the player's body in the physics
+            if let Some(mut transform) = entity.get_mut::<Transform>() {
+                *transform = player.transform;
+            }
+            if let Some(mut global_transform) = entity.get_mut::<GlobalTransform>() {
+                *global_transform = player.global_transform;
+            }
+            if let Some(mut velocity) = entity.get_mut::<Velocity>() {
+                *velocity = player.velocity;
+            }
+            if let Some(mut controller) = entity.get_mut::<KinematicCharacterController>() {
+                controller.translation = player.controller_translation;
+            }
+            if let Some(mut input) = entity.get_mut::<PlayerInput>() {
+                *input = player.input;
+            }
+            if let Some(mut surroundings) = entity.get_mut::<Surroundings>() {
+                *surroundings = player.surroundings;
+            }
+            if let Some(mut health) = entity.get_mut::<Health>() {
+                *health = player.health;
+            }
+            if let Some(mut breath) = entity.get_mut::<Breath>() {
+                *breath = player.breath.clone();
+            }
+            match &player.knockback {
+                Some(knockback) => entity.insert(knockback.clone()),
+                None => entity.remove::<Knockback>(),
+            };
+            match &player.invulnerable {
+                Some(invulnerable) => entity.insert(invulnerable.clone()),
+                None => entity.remove::<Invulnerable>(),
+            };
+        }
+    }
+
+    fn step(&mut self, inputs: [InputBits; 2]) {
+        let mut player_query = self
+            .0
+            .query_filtered::<(&mut PlayerInput, &PlayerId), With<Player>>();
+        for (mut input, id) in player_query.iter_mut(self.0) {
+            input.advance(inputs[id.index()]);
+        }
+        self.0.run_schedule(MovementStep);
+    }
+
+    /// Hashes where the players are, how fast they move and how much health they have left.
+    fn checksum(&mut self) -> u64 {
+        let mut players: Vec<_> = self
+            .0
+            .query_filtered::<(&PlayerId, &Transform, &Velocity, &Health), With<Player>>()
+            .iter(self.0)
+            .map(|(id, transform, velocity, health)| {
+                (
+                    id.index(),
+                    transform.translation,
+                    velocity.linvel,
+                    health.current,
+                )
+            })
+            .collect();
+        players.sort_by_key(|(index, ..)| *index);
+        let mut hasher = Fnv1a::default();
+        for (_, translation, velocity, health) in players {
+            for value in [translation.x, translation.y, velocity.x, velocity.y] {
+                hasher.write(&value.to_bits().to_le_bytes());
+            }
+            hasher.write(&health.to_le_bytes());
+        }
+        hasher.finish()
+    }
+}
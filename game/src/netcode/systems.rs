@@ -0,0 +1,61 @@
+use bevy::prelude::*;
+use leafwing_input_manager::prelude::*;
+
+use crate::input::components::InputSettings;
+use crate::netcode::components::{LocalPlayer, PeerConnection, Session};
+use crate::netcode::session::RollbackSession;
+use crate::netcode::snapshot::WorldSimulation;
+use crate::player::components::{LatchedInput, Player, PlayerId};
+use crate::Action;
+
+/// Starts the session once both players are spawned, where they are the same for both peers.
+pub fn start_session(
+    mut commands: Commands,
+    player_query: Query<&PlayerId, With<Player>>,
+    local_player: Res<LocalPlayer>,
+) {
+    if !PlayerId::ALL
+        .iter()
+        .all(|player| player_query.iter().any(|id| id == player))
+    {
+        return;
+    }
+    info!(
+        "Starting the rollback session as player {}",
+        local_player.index() + 1
+    );
+    commands.insert_resource(Session(RollbackSession::new(local_player.index())));
+}
+
+pub fn stop_session(mut commands: Commands) {
+    commands.remove_resource::<Session>();
+}
+
+/// Sends the local player's input to the peer and simulates the next frame,
+/// rolling back first when the peer's inputs were mispredicted.
+pub fn advance_session(world: &mut World, mut reported_desync: Local<bool>) {
+    let local_player = **world.resource::<LocalPlayer>();
+    let input_settings = *world.resource::<InputSettings>();
+    let mut player_query = world
+        .query_filtered::<(&ActionState<Action>, &mut LatchedInput, &PlayerId), With<Player>>();
+    let input = player_query
+        .iter_mut(world)
+        .find(|(.., id)| **id == local_player)
+        .map(|(action_state, mut latched, _)| latched.take(action_state, &input_settings))
+        .unwrap_or_default();
+
+    world.resource_scope(|world, mut session: Mut<Session>| {
+        world.resource_scope(|world, mut connection: Mut<PeerConnection>| {
+            let mut simulation = WorldSimulation(world);
+            if !session.advance(input, &mut simulation, connection.0.as_mut()) {
+                debug!("Waiting for the peer at frame {}", session.frame());
+            }
+        });
+        if let Some(frame) = session.desync {
+            if !*reported_desync {
+                warn!("The game went out of sync with the peer at frame {frame}");
+                *reported_desync = true;
+            }
+        }
+    });
+}
@@ -0,0 +1,143 @@
+//! Sends the messages of a session to the other peer.
+
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex};
+
+/// An unreliable, unordered channel to the other peer, like UDP.
+pub trait Transport: Send + Sync {
+    fn send(&mut self, message: Vec<u8>);
+    /// Returns the messages received since the last call.
+    fn receive(&mut self) -> Vec<Vec<u8>>;
+}
+
+pub use udp::UdpTransport;
+
+mod udp {
+    use bevy::prelude::*;
+    use std::io::ErrorKind;
+    use std::net::{SocketAddr, UdpSocket};
+
+    use super::Transport;
+
+    /// The largest message that is received, messages are much smaller
+    const MAX_MESSAGE_SIZE: usize = 1500;
+
+    pub struct UdpTransport {
+        socket: UdpSocket,
+    }
+
+    impl UdpTransport {
+        /// Binds the local address and exchanges messages with the peer only.
+        pub fn new(local: SocketAddr, peer: SocketAddr) -> std::io::Result<Self> {
+            let socket = UdpSocket::bind(local)?;
+            socket.connect(peer)?;
+            socket.set_nonblocking(true)?;
+            Ok(Self { socket })
+        }
+    }
+
+    impl Transport for UdpTransport {
+        fn send(&mut self, message: Vec<u8>) {
+            // The peer may not be listening yet, the message is sent again anyway
+            if let Err(error) = self.socket.send(&message) {
+                debug!("Could not send to the peer: {error}");
+            }
+        }
+
+        fn receive(&mut self) -> Vec<Vec<u8>> {
+            let mut messages = Vec::new();
+            let mut buffer = [0; MAX_MESSAGE_SIZE];
+            loop {
+                match self.socket.recv(&mut buffer) {
+                    Ok(size) => messages.push(buffer[..size].to_vec()),
+                    Err(error) if error.kind() == ErrorKind::WouldBlock => break,
+                    Err(error) => {
+                        debug!("Could not receive from the peer: {error}");
+                        break;
+                    }
+                }
+            }
+            messages
+        }
+    }
+}
+
+/// The latency and packet loss of a simulated connection.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct LinkConditions {
+    /// How many calls to `receive` a message takes to arrive
+    pub latency: u32,
+    /// The chance of a message getting lost, from 0 to 1
+    pub loss: f32,
+    /// Seeds the losses, so runs are reproducible
+    pub seed: u64,
+}
+
+#[derive(Default)]
+struct Link {
+    /// The messages in flight to each endpoint, with the time they arrive at
+    queues: [VecDeque<(u32, Vec<u8>)>; 2],
+    random: u64,
+}
+
+impl Link {
+    /// Returns a number from 0 to 1 with xorshift, which is the same on every platform.
+    fn next_random(&mut self) -> f32 {
+        self.random ^= self.random << 13;
+        self.random ^= self.random >> 7;
+        self.random ^= self.random << 17;
+        (self.random >> 40) as f32 / (1u64 << 24) as f32
+    }
+}
+
+/// One end of a simulated connection in memory, to test sessions without a network.
+///
+/// Every call to `receive` advances the endpoint's clock by one.
+pub struct LoopbackTransport {
+    link: Arc<Mutex<Link>>,
+    endpoint: usize,
+    conditions: LinkConditions,
+    clock: u32,
+}
+
+impl LoopbackTransport {
+    pub fn pair(conditions: LinkConditions) -> (Self, Self) {
+        let link = Arc::new(Mutex::new(Link {
+            // Xorshift never leaves zero
+            random: conditions.seed | 1,
+            ..Default::default()
+        }));
+        let endpoint = |endpoint| LoopbackTransport {
+            link: link.clone(),
+            endpoint,
+            conditions,
+            clock: 0,
+        };
+        (endpoint(0), endpoint(1))
+    }
+}
+
+impl Transport for LoopbackTransport {
+    fn send(&mut self, message: Vec<u8>) {
+        let mut link = self.link.lock().unwrap();
+        if link.next_random() < self.conditions.loss {
+            return;
+        }
+        let arrival = self.clock + self.conditions.latency;
+        link.queues[1 - self.endpoint].push_back((arrival, message));
+    }
+
+    fn receive(&mut self) -> Vec<Vec<u8>> {
+        self.clock += 1;
+        let mut link = self.link.lock().unwrap();
+        let queue = &mut link.queues[self.endpoint];
+        let mut received = Vec::new();
+        while queue
+            .front()
+            .is_some_and(|(arrival, _)| *arrival <= self.clock)
+        {
+            received.extend(queue.pop_front().map(|(_, message)| message));
+        }
+        received
+    }
+}
@@ -0,0 +1,99 @@
+use bevy::prelude::*;
+use serde::Deserialize;
+use std::collections::HashMap;
+
+/// The kinds of particles, each is configured by a [`ParticleEffect`].
+#[derive(Deserialize, Default, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ParticleKind {
+    #[default]
+    Dust,
+    Confetti,
+    Sparkles,
+}
+
+/// How the particles of a kind look and move.
+#[derive(Deserialize, Debug, Clone)]
+pub struct ParticleEffect {
+    /// The image of the particles, relative to the assets
+    pub texture: String,
+    /// The size of a frame, the frames are next to each other in the image
+    pub frame_size: (u32, u32),
+    /// How many frames the image has, each particle picks one at random
+    pub frames: u32,
+    /// How many particles a burst emits
+    pub burst: u32,
+    /// How many particles an active emitter emits per second
+    pub rate: f32,
+    /// The range of the particles' lifetime in seconds
+    pub lifetime: (f32, f32),
+    /// The range of the particles' initial speed in pixels per second
+    pub speed: (f32, f32),
+    /// The direction the particles are emitted in
+    pub direction: (f32, f32),
+    /// How far the particles deviate from the direction in radians, to either side
+    pub spread: f32,
+    /// The vertical acceleration in pixels per second squared
+    pub gravity: f32,
+    /// The size of the particles when emitted and when they die
+    pub size: (f32, f32),
+    /// The tint of the particles, values above 1 bloom
+    pub color: (f32, f32, f32, f32),
+    /// Whether the particles fade out over their lifetime
+    pub fade: bool,
+}
+
+/// The particle effects loaded from a `.particles.ron` file.
+#[derive(Asset, TypePath, Deserialize, Debug, Deref)]
+pub struct ParticleEffects(pub HashMap<ParticleKind, ParticleEffect>);
+
+/// The handle to the loaded particle effects.
+#[derive(Resource, Debug, Deref)]
+pub struct ParticleEffectsHandle(pub Handle<ParticleEffects>);
+
+/// Emits a burst of particles.
+#[derive(Event, Debug, Clone, Copy)]
+pub struct SpawnParticles {
+    pub kind: ParticleKind,
+    pub position: Vec2,
+}
+
+/// Continuously emits particles at the entity it is attached to, while active.
+#[derive(Component, Default, Debug, Clone)]
+pub struct ParticleEmitter {
+    pub kind: ParticleKind,
+    pub active: bool,
+    /// Where the particles are emitted, relative to the entity
+    pub offset: Vec2,
+    /// The fraction of a particle left over from previous frames
+    pub pending: f32,
+}
+
+impl ParticleEmitter {
+    pub fn new(kind: ParticleKind) -> Self {
+        Self {
+            kind,
+            active: false,
+            offset: Vec2::ZERO,
+            pending: 0.,
+        }
+    }
+}
+
+/// A live particle.
+#[derive(Component, Debug, Clone)]
+pub struct Particle {
+    pub velocity: Vec2,
+    pub gravity: f32,
+    pub lifetime: Timer,
+    pub size: (f32, f32),
+    pub color: Color,
+    pub fade: bool,
+}
+
+/// The hidden particle entities, that are reused instead of spawning new ones.
+#[derive(Resource, Default, Debug)]
+pub struct ParticlePool {
+    pub free: Vec<Entity>,
+    /// How many particles are alive
+    pub live: usize,
+}
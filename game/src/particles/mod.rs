@@ -0,0 +1,39 @@
+pub mod components;
+pub mod systems;
+
+use bevy::prelude::*;
+
+use crate::particles::components::{ParticleEffects, ParticlePool, SpawnParticles};
+use crate::{AppState, GameState};
+
+/// The file the particle effects are configured in.
+pub const PARTICLE_EFFECTS_PATH: &str = "effects.particles.ron";
+
+/// How many particles can be alive at once, further particles are not emitted.
+pub const MAX_PARTICLES: usize = 512;
+
+/// In front of the levels.
+pub const PARTICLE_Z: f32 = 10.;
+
+pub struct ParticlesPlugin;
+
+impl Plugin for ParticlesPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_asset::<ParticleEffects>()
+            .init_asset_loader::<systems::ParticleEffectsLoader>()
+            .add_event::<SpawnParticles>()
+            .init_resource::<ParticlePool>()
+            .add_systems(Startup, systems::load_particle_effects)
+            .add_systems(
+                Update,
+                (
+                    systems::emit_particles,
+                    systems::spawn_particles,
+                    systems::update_particles,
+                )
+                    .chain()
+                    .run_if(in_state(GameState::Running)),
+            )
+            .add_systems(OnExit(AppState::InGame), systems::despawn);
+    }
+}
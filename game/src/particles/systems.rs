@@ -0,0 +1,204 @@
+use bevy::asset::io::Reader;
+use bevy::asset::{AssetLoader, AsyncReadExt, LoadContext};
+use bevy::ecs::system::SystemParam;
+use bevy::prelude::*;
+use rand::Rng;
+
+use crate::particles::components::{
+    Particle, ParticleEffect, ParticleEffects, ParticleEffectsHandle, ParticleEmitter,
+    ParticleKind, ParticlePool, SpawnParticles,
+};
+use crate::particles::{MAX_PARTICLES, PARTICLE_EFFECTS_PATH, PARTICLE_Z};
+
+/// Loads particle effects from RON files.
+#[derive(Default)]
+pub struct ParticleEffectsLoader;
+
+impl AssetLoader for ParticleEffectsLoader {
+    type Asset = ParticleEffects;
+    type Settings = ();
+    type Error = std::io::Error;
+
+    async fn load<'a>(
+        &'a self,
+        reader: &'a mut Reader<'_>,
+        _: &'a Self::Settings,
+        _: &'a mut LoadContext<'_>,
+    ) -> Result<Self::Asset, Self::Error> {
+        let mut bytes = Vec::new();
+        reader.read_to_end(&mut bytes).await?;
+        ron::de::from_bytes(&bytes)
+            .map_err(|error| std::io::Error::new(std::io::ErrorKind::InvalidData, error))
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["particles.ron"]
+    }
+}
+
+pub fn load_particle_effects(mut commands: Commands, asset_server: Res<AssetServer>) {
+    commands.insert_resource(ParticleEffectsHandle(
+        asset_server.load(PARTICLE_EFFECTS_PATH),
+    ));
+}
+
+pub fn despawn(
+    mut commands: Commands,
+    particle_query: Query<Entity, With<Particle>>,
+    mut pool: ResMut<ParticlePool>,
+) {
+    for particle in particle_query.iter() {
+        commands.entity(particle).despawn();
+    }
+    *pool = ParticlePool::default();
+}
+
+/// Emits particles, reusing the entities of dead particles.
+#[derive(SystemParam)]
+pub struct ParticleSpawner<'w, 's> {
+    commands: Commands<'w, 's>,
+    pool: ResMut<'w, ParticlePool>,
+    effects: Res<'w, Assets<ParticleEffects>>,
+    effects_handle: Option<Res<'w, ParticleEffectsHandle>>,
+    asset_server: Res<'w, AssetServer>,
+}
+
+impl ParticleSpawner<'_, '_> {
+    /// Returns the configuration of a kind of particles, once it is loaded.
+    pub fn effect(&self, kind: ParticleKind) -> Option<&ParticleEffect> {
+        self.effects_handle
+            .as_ref()
+            .and_then(|handle| self.effects.get(&handle.0))
+            .and_then(|effects| effects.get(&kind))
+    }
+
+    /// Emits particles of a kind at a position, as long as the pool is not exhausted.
+    pub fn spawn(&mut self, kind: ParticleKind, position: Vec2, count: u32) {
+        let Some(effect) = self.effect(kind).cloned() else {
+            return;
+        };
+        let texture: Handle<Image> = self.asset_server.load(&effect.texture);
+        let mut rng = rand::thread_rng();
+
+        for _ in 0..count {
+            if self.pool.live >= MAX_PARTICLES {
+                return;
+            }
+            self.pool.live += 1;
+
+            let angle = Vec2::from(effect.direction).to_angle()
+                + rng.gen_range(-effect.spread..=effect.spread);
+            let speed = rng.gen_range(effect.speed.0..=effect.speed.1);
+            let lifetime = rng.gen_range(effect.lifetime.0..=effect.lifetime.1);
+            let (width, height) = effect.frame_size;
+            let frame = rng.gen_range(0..effect.frames.max(1));
+            let min = Vec2::new((frame * width) as f32, 0.);
+            let (red, green, blue, alpha) = effect.color;
+            let color = Color::linear_rgba(red, green, blue, alpha);
+
+            let particle = Particle {
+                velocity: Vec2::from_angle(angle) * speed,
+                gravity: effect.gravity,
+                lifetime: Timer::from_seconds(lifetime, TimerMode::Once),
+                size: effect.size,
+                color,
+                fade: effect.fade,
+            };
+            let sprite = SpriteBundle {
+                sprite: Sprite {
+                    color,
+                    custom_size: Some(Vec2::splat(effect.size.0)),
+                    rect: Some(Rect::from_corners(
+                        min,
+                        min + Vec2::new(width as f32, height as f32),
+                    )),
+                    ..default()
+                },
+                texture: texture.clone(),
+                transform: Transform::from_translation(position.extend(PARTICLE_Z)),
+                ..default()
+            };
+            match self.pool.free.pop() {
+                Some(entity) => {
+                    self.commands.entity(entity).insert((sprite, particle));
+                }
+                None => {
+                    self.commands.spawn((sprite, particle));
+                }
+            }
+        }
+    }
+}
+
+/// Lets the active emitters emit particles at their rate.
+pub fn emit_particles(
+    mut emitter_query: Query<(&mut ParticleEmitter, &GlobalTransform)>,
+    time: Res<Time>,
+    mut spawner: ParticleSpawner,
+) {
+    for (mut emitter, transform) in emitter_query.iter_mut() {
+        if !emitter.active {
+            emitter.pending = 0.;
+            continue;
+        }
+        let Some(rate) = spawner.effect(emitter.kind).map(|effect| effect.rate) else {
+            continue;
+        };
+        emitter.pending += rate * time.delta_seconds();
+        let count = emitter.pending.floor();
+        emitter.pending -= count;
+        let position = transform.translation().truncate() + emitter.offset;
+        spawner.spawn(emitter.kind, position, count as u32);
+    }
+}
+
+/// Emits the bursts of particles that were requested.
+pub fn spawn_particles(
+    mut spawn_events: EventReader<SpawnParticles>,
+    mut spawner: ParticleSpawner,
+) {
+    for SpawnParticles { kind, position } in spawn_events.read() {
+        let burst = spawner.effect(*kind).map_or(0, |effect| effect.burst);
+        spawner.spawn(*kind, *position, burst);
+    }
+}
+
+/// Moves, shrinks and fades the particles, and returns the dead ones to the pool.
+pub fn update_particles(
+    mut particle_query: Query<(
+        Entity,
+        &mut Particle,
+        &mut Transform,
+        &mut Sprite,
+        &mut Visibility,
+    )>,
+    mut pool: ResMut<ParticlePool>,
+    time: Res<Time>,
+) {
+    for (entity, mut particle, mut transform, mut sprite, mut visibility) in
+        particle_query.iter_mut()
+    {
+        if particle.lifetime.finished() {
+            continue;
+        }
+        particle.lifetime.tick(time.delta());
+        if particle.lifetime.just_finished() {
+            *visibility = Visibility::Hidden;
+            pool.free.push(entity);
+            pool.live -= 1;
+            continue;
+        }
+
+        let delta = time.delta_seconds();
+        particle.velocity.y += particle.gravity * delta;
+        transform.translation += (particle.velocity * delta).extend(0.);
+
+        let progress = particle.lifetime.fraction();
+        sprite.custom_size = Some(Vec2::splat(particle.size.0.lerp(particle.size.1, progress)));
+        if particle.fade {
+            sprite.color = particle
+                .color
+                .with_alpha(particle.color.alpha() * (1. - progress));
+        }
+    }
+}
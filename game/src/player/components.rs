@@ -2,46 +2,325 @@ use bevy::prelude::*;
 use bevy_ecs_ldtk::prelude::*;
 use bevy_rapier2d::prelude::*;
 
-use leafwing_input_manager::InputManagerBundle;
+use leafwing_input_manager::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 
 use crate::animation::components::{AnimationIndices, AnimationTimer};
+use crate::camera::components::CameraTarget;
+use crate::collider::{ONE_WAY_PLATFORM_GROUP, PLAYER_GROUP};
+use crate::input::bits::InputBits;
+use crate::input::components::InputSettings;
+use crate::lighting::components::PointLight2d;
+use crate::particles::components::{ParticleEmitter, ParticleKind};
+use crate::player::{ANIMATION_SECONDS, BREATH_SECONDS, MAX_HEALTH};
 use crate::{Action, ColliderBundle};
 
-#[derive(Default, Hash, PartialEq, Eq)]
+#[derive(Default, Hash, PartialEq, Eq, Clone, Copy, Debug, Serialize, Deserialize)]
 pub enum Movement {
     #[default]
     Idle,
     Run,
     Jump,
     Fall,
+    Hit,
+}
+
+impl Movement {
+    /// The number of frames in the sprite sheet of the movement.
+    pub const fn frames(&self) -> usize {
+        match self {
+            Movement::Idle => 11,
+            Movement::Run => 12,
+            Movement::Jump | Movement::Fall => 1,
+            Movement::Hit => 7,
+        }
+    }
+
+    /// The index of the last frame in the sprite sheet of the movement.
+    pub const fn last_frame(&self) -> usize {
+        self.frames() - 1
+    }
 }
 
 #[derive(Default, Component)]
 pub struct Player;
 
+/// The character a player plays as, which only changes how they look.
+#[derive(Default, Component, Hash, PartialEq, Eq, Clone, Copy, Debug, Serialize, Deserialize)]
+pub enum Character {
+    #[default]
+    MaskDude,
+    NinjaFrog,
+    PinkMan,
+    VirtualGuy,
+}
+
+impl Character {
+    pub const ALL: [Character; 4] = [
+        Character::MaskDude,
+        Character::NinjaFrog,
+        Character::PinkMan,
+        Character::VirtualGuy,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Character::MaskDude => "Mask Dude",
+            Character::NinjaFrog => "Ninja Frog",
+            Character::PinkMan => "Pink Man",
+            Character::VirtualGuy => "Virtual Guy",
+        }
+    }
+
+    /// The character after this one, to cycle through them.
+    pub fn next(self) -> Self {
+        let index = Self::ALL.iter().position(|character| *character == self);
+        Self::ALL[index.map_or(0, |index| (index + 1) % Self::ALL.len())]
+    }
+}
+
+/// The characters chosen for the players, the others play the default one.
+#[derive(Resource, Default, Debug, Deref, DerefMut)]
+pub struct PlayerCharacters(pub HashMap<PlayerId, Character>);
+
+/// Which of the local players a player is.
+///
+/// The world follows the first player: the level selection, saves and runs go by them.
+#[derive(Default, Component, Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum PlayerId {
+    #[default]
+    One,
+    Two,
+}
+
+impl PlayerId {
+    pub const ALL: [PlayerId; 2] = [PlayerId::One, PlayerId::Two];
+
+    /// The index of the player, from 0.
+    pub fn index(self) -> usize {
+        match self {
+            PlayerId::One => 0,
+            PlayerId::Two => 1,
+        }
+    }
+}
+
+/// Sent when the player jumps.
+#[derive(Event, Debug, Clone, Copy)]
+pub struct Jumped;
+
+/// Sent when the player dies.
+#[derive(Event, Debug, Clone, Copy)]
+pub struct Died;
+
+/// Sent when the player runs out of breath under water.
+#[derive(Event, Debug, Clone, Copy)]
+pub struct Drowned;
+
+/// Sent when the player lands fast enough to raise dust.
+#[derive(Event, Debug, Clone, Copy)]
+pub struct Landed {
+    /// Whether the landing was hard enough to shake the camera
+    pub hard: bool,
+}
+
+/// Damages an entity with `Health`.
+#[derive(Event, Debug, Clone, Copy)]
+pub struct DamageEvent {
+    pub target: Entity,
+    pub amount: u32,
+    /// Where the damage came from, the target is knocked away from it
+    pub source: Vec2,
+}
+
+#[derive(Component, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Health {
+    pub current: u32,
+    pub max: u32,
+}
+
+impl Health {
+    pub fn new(max: u32) -> Self {
+        Self { current: max, max }
+    }
+}
+
+impl Default for Health {
+    fn default() -> Self {
+        Self::new(MAX_HEALTH)
+    }
+}
+
+/// Protects from damage until the timer finishes, while the sprite flashes.
+#[derive(Component, Debug, Clone)]
+pub struct Invulnerable {
+    pub timer: Timer,
+}
+
+/// Pushes the entity through its character controller, ignoring the input.
+#[derive(Component, Debug, Clone)]
+pub struct Knockback {
+    pub velocity: Vec2,
+    pub timer: Timer,
+}
+
+/// Plays the hit animation once, before returning to the animation of the movement.
+#[derive(Component, Debug, Deref, DerefMut)]
+pub struct HitAnimation(pub Timer);
+
+/// The volumes the player is currently inside of.
+#[derive(Default, Component, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Surroundings {
+    pub on_ladder: bool,
+    pub in_water: bool,
+    /// Whether the player is inside a one-way platform rather than standing on it
+    pub in_platform: bool,
+}
+
+/// How long the player can stay under water.
+#[derive(Default, Component, Debug, Clone, Deref, DerefMut)]
+pub struct Breath(pub Timer);
+
+/// The input the player is moved by in a fixed step, and the one of the step before.
+#[derive(Default, Component, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PlayerInput {
+    pub current: InputBits,
+    pub previous: InputBits,
+}
+
+impl PlayerInput {
+    /// Moves on to the input of the next step.
+    pub fn advance(&mut self, input: InputBits) {
+        self.previous = self.current;
+        self.current = input;
+    }
+
+    pub fn pressed(&self, action: Action) -> bool {
+        self.current.pressed(action)
+    }
+
+    pub fn just_pressed(&self, action: Action) -> bool {
+        self.current.pressed(action) && !self.previous.pressed(action)
+    }
+
+    pub fn just_released(&self, action: Action) -> bool {
+        !self.current.pressed(action) && self.previous.pressed(action)
+    }
+
+    /// Whether the stick was let go of in this step.
+    pub fn stick_released(&self) -> bool {
+        self.current.stick() == 0. && self.previous.stick() != 0.
+    }
+}
+
+/// The actions pressed since the last fixed step, so presses shorter than a step still count.
+#[derive(Default, Component, Debug, Clone, Copy)]
+pub struct LatchedInput(pub InputBits);
+
+impl LatchedInput {
+    /// Returns the input of the next step, which starts latching over.
+    pub fn take(
+        &mut self,
+        action_state: &ActionState<Action>,
+        input_settings: &InputSettings,
+    ) -> InputBits {
+        let input = InputBits::from_action_state(action_state, input_settings);
+        input.with_actions_of(std::mem::take(&mut self.0))
+    }
+}
+
 #[derive(Default, LdtkEntity, Bundle)]
 pub struct PlayerBundle {
     fruit: Player,
+    id: PlayerId,
+    character: Character,
+    camera_target: CameraTarget,
+    // The player is a child of the world instead of a level,
+    // so they are not despawned when their original level is unloaded
+    #[worldly]
+    worldly: Worldly,
     #[sprite_sheet_bundle]
     sprite_sheet_bundle: LdtkSpriteSheetBundle,
     #[from_entity_instance]
     collider: ColliderBundle,
+    #[with(collision_groups)]
+    collision_groups: CollisionGroups,
     #[with(animation_indices)]
     animation_indices: AnimationIndices,
     #[with(animation_timer)]
     animation_timer: AnimationTimer,
     #[with(input_manager)]
     input_manager: InputManagerBundle<Action>,
+    input: PlayerInput,
+    latched_input: LatchedInput,
     #[with(character_controller)]
     textures: KinematicCharacterController,
+    surroundings: Surroundings,
+    #[with(breath)]
+    breath: Breath,
+    health: Health,
+    #[with(dust_emitter)]
+    dust_emitter: ParticleEmitter,
+    #[with(lantern)]
+    lantern: PointLight2d,
+}
+
+impl PlayerBundle {
+    /// The second player of a co-op game, built from the LDtk entity of the first one.
+    pub fn player_two(
+        entity_instance: &EntityInstance,
+        sprite_sheet_bundle: LdtkSpriteSheetBundle,
+    ) -> Self {
+        Self {
+            id: PlayerId::Two,
+            sprite_sheet_bundle,
+            collider: ColliderBundle::from(entity_instance),
+            collision_groups: collision_groups(entity_instance),
+            animation_indices: animation_indices(entity_instance),
+            animation_timer: animation_timer(entity_instance),
+            input_manager: InputManagerBundle {
+                input_map: Action::player_two(),
+                ..Default::default()
+            },
+            textures: character_controller(entity_instance),
+            breath: breath(entity_instance),
+            dust_emitter: dust_emitter(entity_instance),
+            lantern: lantern(entity_instance),
+            ..default()
+        }
+    }
+}
+
+fn collision_groups(_: &EntityInstance) -> CollisionGroups {
+    CollisionGroups::new(PLAYER_GROUP, Group::ALL)
 }
 
 fn animation_indices(_: &EntityInstance) -> AnimationIndices {
-    AnimationIndices { first: 0, last: 10 }
+    AnimationIndices {
+        first: 0,
+        last: Movement::Idle.last_frame(),
+    }
 }
 
 fn animation_timer(_: &EntityInstance) -> AnimationTimer {
-    AnimationTimer(Timer::from_seconds(0.1, TimerMode::Repeating))
+    AnimationTimer(Timer::from_seconds(ANIMATION_SECONDS, TimerMode::Repeating))
+}
+
+fn breath(_: &EntityInstance) -> Breath {
+    Breath(Timer::from_seconds(BREATH_SECONDS, TimerMode::Once))
+}
+
+fn dust_emitter(_: &EntityInstance) -> ParticleEmitter {
+    ParticleEmitter::new(ParticleKind::Dust)
+}
+
+fn lantern(_: &EntityInstance) -> PointLight2d {
+    PointLight2d {
+        color: Color::srgb(1., 0.85, 0.6),
+        intensity: 1.2,
+        radius: 96.,
+    }
 }
 
 fn input_manager(_: &EntityInstance) -> InputManagerBundle<Action> {
@@ -60,6 +339,22 @@ fn character_controller(_: &EntityInstance) -> KinematicCharacterController {
             min_width: CharacterLength::Relative(0.5),
             include_dynamic_bodies: true,
         }),
+        filter_groups: Some(solid_ground_filter(false)),
         ..default()
     }
 }
+
+/// The collision groups the player's controller collides with.
+///
+/// The other players are never collided with.
+/// When passing through, one-way platforms are excluded, so the player moves through them.
+pub fn solid_ground_filter(pass_through_platforms: bool) -> CollisionGroups {
+    if pass_through_platforms {
+        CollisionGroups::new(
+            Group::ALL,
+            Group::ALL - ONE_WAY_PLATFORM_GROUP - PLAYER_GROUP,
+        )
+    } else {
+        CollisionGroups::new(Group::ALL, Group::ALL - PLAYER_GROUP)
+    }
+}
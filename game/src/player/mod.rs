@@ -1,22 +1,81 @@
 pub mod components;
 pub mod systems;
 
+use crate::netcode::components::PeerConnection;
+use crate::player::components::{DamageEvent, Died, Drowned, Jumped, Landed, PlayerCharacters};
 use crate::{AppState, GameState};
+use bevy::ecs::schedule::ScheduleLabel;
 use bevy::prelude::*;
+use bevy_rapier2d::prelude::*;
 
 pub const PLAYER_WIDTH: f32 = 32.0;
 pub const PLAYER_HEIGHT: f32 = 32.0;
+pub const BREATH_SECONDS: f32 = 10.0;
+pub const MAX_HEALTH: u32 = 3;
+/// How long each frame of the players' animations is shown
+pub const ANIMATION_SECONDS: f32 = 0.1;
+/// The rate the players are moved and the physics are stepped at, on every peer.
+pub const STEPS_PER_SECOND: u32 = 60;
+
+/// One fixed step of the players' movement, the damage they take and the physics.
+///
+/// It runs in [`FixedUpdate`], or as often as the netcode rolls back and simulates again.
+#[derive(ScheduleLabel, Debug, Clone, PartialEq, Eq, Hash)]
+pub struct MovementStep;
 
 pub struct PlayerPlugin;
 
 impl Plugin for PlayerPlugin {
     fn build(&self, app: &mut App) {
-        app.add_systems(
-            Update,
-            (systems::move_player, systems::collect_fruits)
-                .chain()
-                .run_if(in_state(GameState::Running)),
-        )
-        .add_systems(OnExit(AppState::InGame), systems::despawn);
+        app.init_resource::<PlayerCharacters>()
+            .insert_resource(Time::<Fixed>::from_hz(STEPS_PER_SECOND.into()))
+            .add_event::<Jumped>()
+            .add_event::<Died>()
+            .add_event::<Drowned>()
+            .add_event::<Landed>()
+            .add_event::<DamageEvent>()
+            .add_systems(
+                MovementStep,
+                (
+                    systems::detect_surroundings,
+                    systems::touch_traps,
+                    systems::update_breath,
+                    systems::take_damage,
+                    systems::update_invulnerability,
+                    systems::move_player,
+                    systems::apply_knockback,
+                )
+                    .chain()
+                    .before(PhysicsSet::SyncBackend),
+            )
+            .add_systems(
+                FixedUpdate,
+                // The netcode steps the players of an online game itself
+                (systems::read_input, systems::run_movement_step)
+                    .chain()
+                    .run_if(in_state(GameState::Running))
+                    .run_if(not(resource_exists::<PeerConnection>)),
+            )
+            .add_systems(
+                Update,
+                (
+                    systems::land,
+                    systems::raise_dust,
+                    systems::collect_fruits,
+                    systems::flash_invulnerable,
+                )
+                    .chain()
+                    .run_if(in_state(GameState::Running)),
+            )
+            // Presses shorter than a step are only seen by the action state of one frame
+            .add_systems(
+                PostUpdate,
+                systems::latch_input.run_if(in_state(GameState::Running)),
+            )
+            .add_systems(
+                Update,
+                systems::choose_characters.run_if(in_state(AppState::InGame)),
+            )
+            .add_systems(OnExit(AppState::InGame), systems::despawn);
     }
 }
@@ -1,74 +1,232 @@
 use bevy::prelude::*;
+use bevy_ecs_ldtk::prelude::*;
 use bevy_rapier2d::prelude::*;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 
 use leafwing_input_manager::prelude::*;
 
-use crate::fruit::components::Fruit;
-use crate::player::components::{Movement, Player};
+use crate::camera::components::{CameraShake, CameraZoomPulse, CameraZoomTo};
+use crate::fruit::components::{Fruit, FruitCollected};
+use crate::input::bits::InputBits;
+use crate::input::components::InputSettings;
+use crate::lighting::components::{FadingLight, PointLight2d};
+use crate::particles::components::{ParticleEmitter, ParticleKind, SpawnParticles};
+use crate::player::components::{
+    solid_ground_filter, Breath, Character, DamageEvent, Died, Drowned, Health, HitAnimation,
+    Invulnerable, Jumped, Knockback, Landed, LatchedInput, Movement, Player, PlayerCharacters,
+    PlayerId, PlayerInput, Surroundings,
+};
+use crate::player::{MovementStep, ANIMATION_SECONDS, PLAYER_HEIGHT, PLAYER_WIDTH};
+use crate::transition::components::{StartTransition, TransitionStyle, TransitionTarget};
+use crate::trap::components::FireTrap;
+use crate::world::components::{LadderVolume, OneWayPlatformCollider, WaterVolume};
 use crate::{Action, Tilesets};
 
 pub fn load_player_tilesets(
     asset_server: Res<AssetServer>,
-    mut tileset: ResMut<Tilesets<Movement>>,
+    mut tileset: ResMut<Tilesets<(Character, Movement)>>,
 ) {
-    let idle_tileset = asset_server.load("Main Characters/Mask Dude/Idle (32x32).png");
-    let run_tileset = asset_server.load("Main Characters/Mask Dude/Run (32x32).png");
-    let jump_tileset = asset_server.load("Main Characters/Mask Dude/Jump (32x32).png");
-    let fall_tileset = asset_server.load("Main Characters/Mask Dude/Fall (32x32).png");
-    *tileset = Tilesets(HashMap::from([
-        (Movement::Idle, idle_tileset),
-        (Movement::Run, run_tileset),
-        (Movement::Jump, jump_tileset),
-        (Movement::Fall, fall_tileset),
-    ]));
+    let movements = [
+        (Movement::Idle, "Idle"),
+        (Movement::Run, "Run"),
+        (Movement::Jump, "Jump"),
+        (Movement::Fall, "Fall"),
+        (Movement::Hit, "Hit"),
+    ];
+    *tileset = Tilesets(
+        Character::ALL
+            .into_iter()
+            .flat_map(|character| {
+                movements.map(|(movement, name)| {
+                    let path = format!("Main Characters/{}/{name} (32x32).png", character.name());
+                    ((character, movement), asset_server.load(path))
+                })
+            })
+            .collect(),
+    );
+}
+
+/// Dresses the spawned players as the characters chosen for them.
+pub fn choose_characters(
+    mut player_query: Query<(&PlayerId, &mut Character), Added<Player>>,
+    player_characters: Res<PlayerCharacters>,
+) {
+    for (id, mut character) in player_query.iter_mut() {
+        *character = player_characters.get(id).copied().unwrap_or_default();
+    }
 }
 
 pub fn despawn(mut commands: Commands, enemy_entity_query: Query<Entity, With<Player>>) {
-    if let Ok(player_entity) = enemy_entity_query.get_single() {
+    for player_entity in enemy_entity_query.iter() {
         commands.entity(player_entity).despawn();
     }
 }
 
 // http://www.mathforgameprogrammers.com/gdc2016/GDC2016_Pittman_Kyle_BuildingABetterJump.pdf
 const METER: f32 = 16.;
-const V_X: f32 = 10. * METER;
+pub const V_X: f32 = 10. * METER;
 const HEIGHT: f32 = 5. * METER;
 const DISTANCE_AT_HEIGHT: f32 = 2.5 * METER;
 
-const V_0: f32 = (2. * HEIGHT * V_X) / DISTANCE_AT_HEIGHT;
-const GRAVITY: f32 = (-2. * HEIGHT * (V_X * V_X)) / (DISTANCE_AT_HEIGHT * DISTANCE_AT_HEIGHT);
+pub const V_0: f32 = (2. * HEIGHT * V_X) / DISTANCE_AT_HEIGHT;
+pub const GRAVITY: f32 = (-2. * HEIGHT * (V_X * V_X)) / (DISTANCE_AT_HEIGHT * DISTANCE_AT_HEIGHT);
+
+/// Landing faster than this raises dust
+const LANDING_SPEED: f32 = 0.5 * V_0;
+/// Landing faster than jumping off shakes the camera
+const HARD_LANDING_SPEED: f32 = 1.25 * V_0;
+/// Running faster than this raises dust
+const RUNNING_SPEED: f32 = 0.5 * V_X;
+
+/// How long the light of a collected fruit takes to fade
+const FRUIT_FLASH_SECONDS: f32 = 0.5;
+
+const CLIMB_SPEED: f32 = 5. * METER;
+
+/// The velocity the player is knocked away from damage with
+const KNOCKBACK_VELOCITY: Vec2 = Vec2::new(0.5 * V_X, 0.4 * V_0);
+const KNOCKBACK_SECONDS: f32 = 0.25;
+const INVULNERABLE_SECONDS: f32 = 1.5;
+/// How long the invulnerable player is shown and hidden while flashing
+const FLASH_SECONDS: f32 = 0.1;
+const FLASH_ALPHA: f32 = 0.2;
+/// How long the hit animation plays once
+const HIT_SECONDS: f32 = Movement::Hit.frames() as f32 * ANIMATION_SECONDS;
+const TRAP_DAMAGE: u32 = 1;
+
+/// How far (in pixels) the player's feet may sink below the surface of a one-way platform
+/// while still standing on it.
+const ONE_WAY_PLATFORM_TOLERANCE: f32 = 1.;
+
+const WATER_GRAVITY_SCALE: f32 = 0.25;
+const WATER_SPEED_SCALE: f32 = 0.5;
+const WATER_JUMP_SCALE: f32 = 0.5;
+const WATER_MAX_SINK_SPEED: f32 = 4. * METER;
+
+/// Updates the ladders, water volumes and one-way platforms the player is inside of.
+pub fn detect_surroundings(
+    mut player_query: Query<(Entity, &GlobalTransform, &mut Surroundings), With<Player>>,
+    ladder_query: Query<Entity, With<LadderVolume>>,
+    water_query: Query<Entity, With<WaterVolume>>,
+    platform_query: Query<(&OneWayPlatformCollider, &GlobalTransform)>,
+    rapier_context: Res<RapierContext>,
+) {
+    let half_size = Vec2::new(PLAYER_WIDTH, PLAYER_HEIGHT) / 2.;
+    for (player, transform, mut surroundings) in player_query.iter_mut() {
+        let intersects = |volume| rapier_context.intersection_pair(player, volume) == Some(true);
+        let on_ladder = ladder_query.iter().any(intersects);
+        let in_water = water_query.iter().any(intersects);
+        let position = transform.translation().truncate();
+        let in_platform = platform_query.iter().any(|(platform, platform_transform)| {
+            platform.overlaps(
+                platform_transform.translation().truncate(),
+                position,
+                half_size,
+                ONE_WAY_PLATFORM_TOLERANCE,
+            )
+        });
+        surroundings.set_if_neq(Surroundings {
+            on_ladder,
+            in_water,
+            in_platform,
+        });
+    }
+}
+
+/// Remembers the actions pressed between the fixed steps.
+pub fn latch_input(
+    mut player_query: Query<(&ActionState<Action>, &mut LatchedInput), With<Player>>,
+    input_settings: Res<InputSettings>,
+) {
+    for (action_state, mut latched) in player_query.iter_mut() {
+        let input = InputBits::from_action_state(action_state, &input_settings);
+        latched.0 = latched.0.with_actions_of(input);
+    }
+}
+
+/// Reads the input the players are moved by in the next fixed step.
+pub fn read_input(
+    mut player_query: Query<(&ActionState<Action>, &mut LatchedInput, &mut PlayerInput)>,
+    input_settings: Res<InputSettings>,
+) {
+    for (action_state, mut latched, mut input) in player_query.iter_mut() {
+        input.advance(latched.take(action_state, &input_settings));
+    }
+}
+
+/// Moves the players and steps the physics once.
+pub fn run_movement_step(world: &mut World) {
+    world.run_schedule(MovementStep);
+}
+
+type MovingPlayer<'a> = (
+    &'a PlayerInput,
+    &'a mut KinematicCharacterController,
+    &'a Velocity,
+    &'a Surroundings,
+);
 
 pub fn move_player(
-    mut player_query: Query<
-        (
-            &ActionState<Action>,
-            &mut KinematicCharacterController,
-            &Velocity,
-        ),
-        With<Player>,
-    >,
+    mut player_query: Query<MovingPlayer, With<Player>>,
+    mut jumped_events: EventWriter<Jumped>,
     time: Res<Time>,
 ) {
-    if let Ok((action, mut controller, velocity)) = player_query.get_single_mut() {
+    for (input, mut controller, velocity, surroundings) in player_query.iter_mut() {
         let mut velocity = velocity.linvel;
 
-        if action.just_pressed(&Action::Jump) {
-            velocity.y = V_0;
+        let (gravity, v_x, v_0) = if surroundings.in_water {
+            (
+                GRAVITY * WATER_GRAVITY_SCALE,
+                V_X * WATER_SPEED_SCALE,
+                V_0 * WATER_JUMP_SCALE,
+            )
         } else {
-            velocity.y += GRAVITY * time.delta_seconds();
+            (GRAVITY, V_X, V_0)
+        };
+
+        if surroundings.on_ladder {
+            // Climbing ignores gravity, the player stays where they let go
+            velocity.y = if input.pressed(Action::Jump) {
+                CLIMB_SPEED
+            } else if input.pressed(Action::Fall) {
+                -CLIMB_SPEED
+            } else {
+                0.
+            };
+        } else if input.just_pressed(Action::Jump) {
+            velocity.y = v_0;
+            jumped_events.send(Jumped);
+        } else {
+            velocity.y += gravity * time.delta_seconds();
+        }
+
+        if surroundings.in_water {
+            velocity.y = velocity.y.max(-WATER_MAX_SINK_SPEED);
         }
 
-        if action.pressed(&Action::Left) {
-            velocity.x = -V_X;
-        } else if action.pressed(&Action::Right) {
-            velocity.x = V_X;
+        // The stick moves the player slower the less it is pushed
+        let stick = input.current.stick();
+        if input.pressed(Action::Left) {
+            velocity.x = -v_x;
+        } else if input.pressed(Action::Right) {
+            velocity.x = v_x;
+        } else if stick != 0. {
+            velocity.x = stick * v_x;
         }
 
-        if action.just_released(&Action::Left) || action.just_released(&Action::Right) {
+        if input.just_released(Action::Left)
+            || input.just_released(Action::Right)
+            || input.stick_released()
+        {
             velocity.x = 0.;
         }
 
+        // One-way platforms only stop this player when they land on top of them,
+        // and holding fall drops the player through them
+        let pass_through_platforms =
+            velocity.y > 0. || surroundings.in_platform || input.pressed(Action::Fall);
+        controller.filter_groups = Some(solid_ground_filter(pass_through_platforms));
+
         let translation_change = velocity * time.delta_seconds();
         controller.translation = match controller.translation {
             Some(existing_translation) => Some(existing_translation + translation_change),
@@ -77,20 +235,311 @@ pub fn move_player(
     }
 }
 
+/// Raises dust when the player lands and shakes the camera when they land hard.
+pub fn land(
+    player_query: Query<
+        (
+            Entity,
+            &Velocity,
+            &KinematicCharacterControllerOutput,
+            &GlobalTransform,
+        ),
+        With<Player>,
+    >,
+    mut fall_speeds: Local<HashMap<Entity, f32>>,
+    mut shake_events: EventWriter<CameraShake>,
+    mut pulse_events: EventWriter<CameraZoomPulse>,
+    mut particle_events: EventWriter<SpawnParticles>,
+    mut landed_events: EventWriter<Landed>,
+) {
+    for (player, velocity, output, transform) in player_query.iter() {
+        let fall_speed = fall_speeds.entry(player).or_default();
+        if !output.grounded {
+            // The velocity is already reset in the frame the player lands
+            *fall_speed = -velocity.linvel.y;
+        } else if *fall_speed > 0. {
+            if *fall_speed > LANDING_SPEED {
+                particle_events.send(SpawnParticles {
+                    kind: ParticleKind::Dust,
+                    position: transform.translation().truncate()
+                        - Vec2::new(0., PLAYER_HEIGHT / 2.),
+                });
+                landed_events.send(Landed {
+                    hard: *fall_speed > HARD_LANDING_SPEED,
+                });
+            }
+            if *fall_speed > HARD_LANDING_SPEED {
+                let trauma = (*fall_speed / HARD_LANDING_SPEED - 1.).clamp(0.2, 0.6);
+                shake_events.send(CameraShake { trauma });
+                pulse_events.send(CameraZoomPulse {
+                    scale: 0.97,
+                    duration: 0.2,
+                });
+            }
+            *fall_speed = 0.;
+        }
+    }
+}
+
+/// Raises dust behind the running player and along the wall they slide down.
+pub fn raise_dust(
+    mut player_query: Query<
+        (
+            &mut ParticleEmitter,
+            &Velocity,
+            &KinematicCharacterControllerOutput,
+        ),
+        With<Player>,
+    >,
+) {
+    for (mut emitter, velocity, output) in player_query.iter_mut() {
+        let running = output.grounded && velocity.linvel.x.abs() > RUNNING_SPEED;
+        // Pushing against a wall while falling, so the wall stops the horizontal movement
+        let wall_sliding = !output.grounded
+            && velocity.linvel.y < 0.
+            && output.desired_translation.x != 0.
+            && output.effective_translation.x.abs() < output.desired_translation.x.abs() / 2.;
+
+        emitter.active = running || wall_sliding;
+        emitter.offset = if wall_sliding {
+            Vec2::new(
+                output.desired_translation.x.signum() * PLAYER_WIDTH / 4.,
+                0.,
+            )
+        } else {
+            Vec2::new(0., -PLAYER_HEIGHT / 2.)
+        };
+    }
+}
+
+type BreathingPlayer<'a> = (
+    Entity,
+    &'a mut Breath,
+    &'a Surroundings,
+    &'a Health,
+    &'a GlobalTransform,
+);
+
+/// Drains the player's breath while in water and refills it when surfacing.
+///
+/// A player out of breath drowns, taking all their health as damage
+/// as soon as they are no longer invulnerable.
+pub fn update_breath(
+    mut player_query: Query<BreathingPlayer, With<Player>>,
+    mut damage_events: EventWriter<DamageEvent>,
+    mut drowned_events: EventWriter<Drowned>,
+    mut shake_events: EventWriter<CameraShake>,
+    mut zoom_events: EventWriter<CameraZoomTo>,
+    time: Res<Time>,
+) {
+    for (player, mut breath, surroundings, health, transform) in player_query.iter_mut() {
+        if surroundings.in_water {
+            breath.tick(time.delta());
+            if breath.just_finished() {
+                drowned_events.send(Drowned);
+                shake_events.send(CameraShake { trauma: 0.8 });
+                zoom_events.send(CameraZoomTo {
+                    scale: 0.75,
+                    duration: 1.,
+                });
+            }
+            if breath.finished() {
+                damage_events.send(DamageEvent {
+                    target: player,
+                    amount: health.max,
+                    source: transform.translation().truncate(),
+                });
+            }
+        } else {
+            if breath.finished() {
+                zoom_events.send(CameraZoomTo {
+                    scale: 1.,
+                    duration: 0.5,
+                });
+            }
+            breath.reset();
+        }
+    }
+}
+
+type CollectingPlayer<'a> = (
+    &'a KinematicCharacterControllerOutput,
+    &'a GlobalTransform,
+    &'a PlayerId,
+);
+
 pub fn collect_fruits(
     mut commands: Commands,
     character_controller_outputs: Query<
-        &KinematicCharacterControllerOutput,
+        CollectingPlayer,
         (With<Player>, Changed<KinematicCharacterControllerOutput>),
     >,
-    fruits: Query<Entity, With<Fruit>>,
+    fruits: Query<(&GlobalTransform, &EntityIid), With<Fruit>>,
+    mut particle_events: EventWriter<SpawnParticles>,
+    mut collected_events: EventWriter<FruitCollected>,
 ) {
-    if let Ok(output) = character_controller_outputs.get_single() {
+    // Both players may touch the same fruit in a frame
+    let mut collected = HashSet::new();
+    let mut last_collector = None;
+    for (output, player, player_id) in character_controller_outputs.iter() {
         for collision in &output.collisions {
-            if fruits.get(collision.entity).is_ok() {
-                info!("Fruit collected");
-                commands.entity(collision.entity).despawn()
+            let Ok((fruit, iid)) = fruits.get(collision.entity) else {
+                continue;
+            };
+            if !collected.insert(collision.entity) {
+                continue;
             }
+            info!("Fruit collected");
+            collected_events.send(FruitCollected {
+                fruit: collision.entity,
+                iid: iid.to_string(),
+                position: fruit.translation().truncate(),
+                player: *player_id,
+            });
+            particle_events.send(SpawnParticles {
+                kind: ParticleKind::Sparkles,
+                position: fruit.translation().truncate(),
+            });
+            commands.entity(collision.entity).despawn();
+            // A flash of light where the fruit was
+            commands.spawn((
+                TransformBundle::from_transform(Transform::from_translation(fruit.translation())),
+                PointLight2d {
+                    color: Color::srgb(1., 0.9, 0.5),
+                    intensity: 2.,
+                    radius: 64.,
+                },
+                FadingLight {
+                    timer: Timer::from_seconds(FRUIT_FLASH_SECONDS, TimerMode::Once),
+                    intensity: 2.,
+                },
+            ));
+            last_collector = Some(player.translation().truncate());
+        }
+    }
+    // Collecting every fruit completes the level
+    if let Some(position) = last_collector.filter(|_| collected.len() == fruits.iter().len()) {
+        info!("All fruits collected");
+        particle_events.send(SpawnParticles {
+            kind: ParticleKind::Confetti,
+            position,
+        });
+    }
+}
+
+/// Overrides the movement of knocked back entities until the knockback ends.
+pub fn apply_knockback(
+    mut commands: Commands,
+    mut knockback_query: Query<(Entity, &mut Knockback, &mut KinematicCharacterController)>,
+    time: Res<Time>,
+) {
+    for (entity, mut knockback, mut controller) in knockback_query.iter_mut() {
+        knockback.velocity.y += GRAVITY * time.delta_seconds();
+        controller.translation = Some(knockback.velocity * time.delta_seconds());
+        if knockback.timer.tick(time.delta()).finished() {
+            commands.entity(entity).remove::<Knockback>();
+        }
+    }
+}
+
+/// Damages the player touching a trap.
+pub fn touch_traps(
+    player_query: Query<Entity, (With<Player>, Without<Invulnerable>)>,
+    trap_query: Query<(Entity, &GlobalTransform), With<FireTrap>>,
+    rapier_context: Res<RapierContext>,
+    mut damage_events: EventWriter<DamageEvent>,
+) {
+    for player in player_query.iter() {
+        if let Some((_, trap)) = trap_query
+            .iter()
+            .find(|(trap, _)| rapier_context.intersection_pair(player, *trap) == Some(true))
+        {
+            damage_events.send(DamageEvent {
+                target: player,
+                amount: TRAP_DAMAGE,
+                source: trap.translation().truncate(),
+            });
+        }
+    }
+}
+
+type DamagedPlayer<'a> = (&'a mut Health, &'a GlobalTransform, Has<Invulnerable>);
+
+/// Knocks the damaged player back and makes them invulnerable for a while,
+/// or respawns them when they run out of health.
+pub fn take_damage(
+    mut commands: Commands,
+    mut damage_events: EventReader<DamageEvent>,
+    mut player_query: Query<DamagedPlayer, With<Player>>,
+    mut died_events: EventWriter<Died>,
+    mut shake_events: EventWriter<CameraShake>,
+    mut transition_events: EventWriter<StartTransition>,
+) {
+    let mut damaged = Vec::new();
+    for damage in damage_events.read() {
+        let Ok((mut health, transform, invulnerable)) = player_query.get_mut(damage.target) else {
+            continue;
+        };
+        // The invulnerability is only added at the end of the step
+        if invulnerable || damaged.contains(&damage.target) {
+            continue;
+        }
+        damaged.push(damage.target);
+        health.current = health.current.saturating_sub(damage.amount);
+
+        let direction = if transform.translation().x < damage.source.x {
+            -1.
+        } else {
+            1.
+        };
+        commands.entity(damage.target).insert((
+            Knockback {
+                velocity: Vec2::new(direction * KNOCKBACK_VELOCITY.x, KNOCKBACK_VELOCITY.y),
+                timer: Timer::from_seconds(KNOCKBACK_SECONDS, TimerMode::Once),
+            },
+            Invulnerable {
+                timer: Timer::from_seconds(INVULNERABLE_SECONDS, TimerMode::Once),
+            },
+            HitAnimation(Timer::from_seconds(HIT_SECONDS, TimerMode::Once)),
+        ));
+        shake_events.send(CameraShake { trauma: 0.4 });
+
+        if health.current == 0 {
+            died_events.send(Died);
+            transition_events.send(StartTransition {
+                target: TransitionTarget::Respawn,
+                style: TransitionStyle::Wipe,
+            });
+        }
+    }
+}
+
+/// Ends the invulnerability once its timer finishes.
+pub fn update_invulnerability(
+    mut commands: Commands,
+    mut invulnerable_query: Query<(Entity, &mut Invulnerable)>,
+    time: Res<Time>,
+) {
+    for (entity, mut invulnerable) in invulnerable_query.iter_mut() {
+        if invulnerable.timer.tick(time.delta()).finished() {
+            commands.entity(entity).remove::<Invulnerable>();
+        }
+    }
+}
+
+/// Flashes the invulnerable player until the invulnerability ends.
+///
+/// The flashing follows the invulnerability's timer, so it also follows a rollback.
+pub fn flash_invulnerable(
+    mut player_query: Query<(Option<&Invulnerable>, &mut Sprite), With<Player>>,
+) {
+    for (invulnerable, mut sprite) in player_query.iter_mut() {
+        let hidden = invulnerable.is_some_and(|invulnerable| {
+            (invulnerable.timer.elapsed_secs() / FLASH_SECONDS) as u32 % 2 == 1
+        });
+        let alpha = if hidden { FLASH_ALPHA } else { 1. };
+        if sprite.color.alpha() != alpha {
+            sprite.color.set_alpha(alpha);
         }
     }
 }
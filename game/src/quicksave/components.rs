@@ -0,0 +1,56 @@
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+
+/// The version of the snapshots written by this build.
+///
+/// Quicksaves are short-lived, so snapshots of other versions are not migrated but rejected.
+pub const SNAPSHOT_VERSION: u32 = 2;
+
+/// The state of a game in the middle of a level.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Snapshot {
+    pub version: u32,
+    /// The iid of the level the player is in
+    pub level: String,
+    pub player: PlayerSnapshot,
+    /// The iids of the collected fruits
+    pub collected_fruits: BTreeSet<String>,
+    /// The phase of the traps by their iid
+    pub traps: BTreeMap<String, TrapPhase>,
+    /// How long the player has been in the level in seconds
+    pub level_time: f32,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+pub struct PlayerSnapshot {
+    pub translation: [f32; 2],
+    pub velocity: [f32; 2],
+    /// How long the player has been holding their breath in seconds
+    pub breath: f32,
+    pub health: u32,
+}
+
+/// How far a trap is into its animation.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+pub struct TrapPhase {
+    pub frame: usize,
+    /// The elapsed seconds of the current frame
+    pub elapsed: f32,
+}
+
+/// Saves a snapshot of the game to the quicksave.
+#[derive(Event, Debug, Clone, Copy)]
+pub struct Quicksave;
+
+/// Restores the game from the quicksave.
+#[derive(Event, Debug, Clone, Copy)]
+pub struct Quickload;
+
+/// The snapshot to apply to the player once the world is respawned.
+#[derive(Resource, Debug)]
+pub struct PendingSnapshot(pub Snapshot);
+
+/// The trap phases to apply to the traps once their level is respawned.
+#[derive(Resource, Default, Debug, Deref, DerefMut)]
+pub struct PendingTrapPhases(pub HashMap<String, TrapPhase>);
@@ -0,0 +1,39 @@
+pub mod components;
+pub mod systems;
+
+use bevy::prelude::*;
+
+use crate::camera::systems::reset_camera;
+use crate::quicksave::components::{PendingTrapPhases, Quickload, Quicksave};
+use crate::save::systems::track_level;
+use crate::AppState;
+
+pub use systems::{restore_snapshot, take_snapshot};
+
+/// The name of the file the quicksave is stored in.
+pub const QUICKSAVE_FILE: &str = "quicksave.json";
+
+/// Snapshots the live game, so it can be restored in the middle of a level.
+pub struct QuicksavePlugin;
+
+impl Plugin for QuicksavePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<Quicksave>()
+            .add_event::<Quickload>()
+            .init_resource::<PendingTrapPhases>()
+            .add_systems(
+                Update,
+                (
+                    systems::quicksave.run_if(on_event::<Quicksave>()),
+                    systems::quickload.run_if(on_event::<Quickload>()),
+                    systems::apply_player_snapshot
+                        .before(reset_camera)
+                        .before(track_level),
+                    systems::apply_trap_phases,
+                )
+                    .chain()
+                    .run_if(in_state(AppState::InGame)),
+            )
+            .add_systems(OnExit(AppState::InGame), systems::forget_snapshot);
+    }
+}
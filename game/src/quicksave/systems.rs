@@ -0,0 +1,283 @@
+use bevy::prelude::*;
+use bevy::time::Stopwatch;
+use bevy_ecs_ldtk::prelude::*;
+use bevy_rapier2d::prelude::*;
+use std::time::Duration;
+
+use crate::animation::components::AnimationTimer;
+use crate::fruit::components::CollectedFruits;
+use crate::player::components::{Breath, Health, Player, PlayerId};
+use crate::quicksave::components::{
+    PendingSnapshot, PendingTrapPhases, PlayerSnapshot, Snapshot, TrapPhase, SNAPSHOT_VERSION,
+};
+use crate::quicksave::QUICKSAVE_FILE;
+use crate::save::components::LevelTimer;
+use crate::save::storage;
+use crate::trap::components::FireTrap;
+
+type SnapshotPlayer<'a> = (
+    &'a Transform,
+    &'a Velocity,
+    &'a Breath,
+    &'a Health,
+    &'a PlayerId,
+);
+type RestoredPlayer<'a> = (
+    &'a mut Transform,
+    &'a mut GlobalTransform,
+    &'a mut Velocity,
+    &'a mut Breath,
+    &'a mut Health,
+    &'a PlayerId,
+);
+type RestoredTrap<'a> = (&'a EntityIid, &'a mut TextureAtlas, &'a mut AnimationTimer);
+
+/// Takes a snapshot of the game, `None` if the player is not in a level.
+pub fn take_snapshot(world: &mut World) -> Option<Snapshot> {
+    let timer = world.resource::<LevelTimer>();
+    let level = timer.level.clone()?;
+    let level_time = timer.stopwatch.elapsed_secs();
+
+    // The snapshot is of the first player, the second one joins them again
+    let (transform, velocity, breath, health, _) = world
+        .query_filtered::<SnapshotPlayer, With<Player>>()
+        .iter(world)
+        .find(|(.., id)| **id == PlayerId::One)?;
+    let player = PlayerSnapshot {
+        translation: transform.translation.truncate().to_array(),
+        velocity: velocity.linvel.to_array(),
+        breath: breath.elapsed_secs(),
+        health: health.current,
+    };
+
+    let traps = world
+        .query_filtered::<(&EntityIid, &TextureAtlas, &AnimationTimer), With<FireTrap>>()
+        .iter(world)
+        .map(|(iid, atlas, timer)| {
+            let phase = TrapPhase {
+                frame: atlas.index,
+                elapsed: timer.elapsed_secs(),
+            };
+            (iid.to_string(), phase)
+        })
+        .collect();
+
+    Some(Snapshot {
+        version: SNAPSHOT_VERSION,
+        level,
+        player,
+        collected_fruits: world
+            .resource::<CollectedFruits>()
+            .iter()
+            .cloned()
+            .collect(),
+        traps,
+        level_time,
+    })
+}
+
+/// Restores a snapshot by respawning the world.
+///
+/// The player and the traps are put back into their state once they are spawned again.
+pub fn restore_snapshot(world: &mut World, snapshot: Snapshot) {
+    // The collected fruits are despawned again as soon as they are spawned
+    **world.resource_mut::<CollectedFruits>() = snapshot.collected_fruits.iter().cloned().collect();
+    **world.resource_mut::<PendingTrapPhases>() = snapshot
+        .traps
+        .iter()
+        .map(|(iid, phase)| (iid.clone(), *phase))
+        .collect();
+
+    let mut stopwatch = Stopwatch::new();
+    stopwatch.set_elapsed(Duration::from_secs_f32(snapshot.level_time));
+    world.insert_resource(LevelTimer {
+        level: Some(snapshot.level.clone()),
+        stopwatch,
+    });
+
+    // The player is spawned with the first level and moved to the snapshot's level afterwards
+    world.insert_resource(LevelSelection::index(0));
+    let ldtk_worlds: Vec<Entity> = world
+        .query_filtered::<Entity, With<Handle<LdtkProject>>>()
+        .iter(world)
+        .collect();
+    for ldtk_world in ldtk_worlds {
+        world.entity_mut(ldtk_world).insert(Respawn);
+    }
+    world.insert_resource(PendingSnapshot(snapshot));
+}
+
+pub fn quicksave(world: &mut World) {
+    let Some(snapshot) = take_snapshot(world) else {
+        warn!("There is nothing to quicksave");
+        return;
+    };
+    let result = serde_json::to_string(&snapshot)
+        .map_err(Into::into)
+        .and_then(|text| storage::write(QUICKSAVE_FILE, &text));
+    match result {
+        Ok(()) => info!("Quicksaved"),
+        Err(error) => error!("Could not quicksave: {error}"),
+    }
+}
+
+pub fn quickload(world: &mut World) {
+    let text = match storage::read(QUICKSAVE_FILE) {
+        Ok(Some(text)) => text,
+        Ok(None) => {
+            info!("There is no quicksave");
+            return;
+        }
+        Err(error) => {
+            error!("Could not quickload: {error}");
+            return;
+        }
+    };
+    match serde_json::from_str::<Snapshot>(&text) {
+        Ok(snapshot) if snapshot.version == SNAPSHOT_VERSION => {
+            info!("Quickloading");
+            restore_snapshot(world, snapshot);
+        }
+        Ok(snapshot) => warn!(
+            "The quicksave has the unsupported version {}",
+            snapshot.version
+        ),
+        Err(error) => error!("Invalid quicksave: {error}"),
+    }
+}
+
+/// Puts the respawned player back into the state of the snapshot.
+pub fn apply_player_snapshot(
+    mut commands: Commands,
+    pending_snapshot: Option<Res<PendingSnapshot>>,
+    mut player_query: Query<RestoredPlayer, Added<Player>>,
+    mut level_selection: ResMut<LevelSelection>,
+) {
+    let Some(PendingSnapshot(snapshot)) = pending_snapshot.as_deref() else {
+        return;
+    };
+    let Some((mut transform, mut global_transform, mut velocity, mut breath, mut health, _)) =
+        player_query
+            .iter_mut()
+            .find(|(.., id)| **id == PlayerId::One)
+    else {
+        return;
+    };
+    let player = snapshot.player;
+    transform.translation = Vec2::from(player.translation).extend(transform.translation.z);
+    // The camera jumps to the player in this frame, before the transforms are propagated
+    *global_transform = GlobalTransform::from(*transform);
+    velocity.linvel = Vec2::from(player.velocity);
+    breath.set_elapsed(Duration::from_secs_f32(player.breath));
+    health.current = player.health.min(health.max);
+    *level_selection = LevelSelection::iid(snapshot.level.clone());
+    commands.remove_resource::<PendingSnapshot>();
+}
+
+/// Puts the respawned traps back into the phase of the snapshot.
+pub fn apply_trap_phases(
+    mut trap_query: Query<RestoredTrap, Added<FireTrap>>,
+    mut pending_trap_phases: ResMut<PendingTrapPhases>,
+) {
+    if pending_trap_phases.is_empty() {
+        return;
+    }
+    for (iid, mut atlas, mut timer) in trap_query.iter_mut() {
+        if let Some(phase) = pending_trap_phases.remove(iid.as_str()) {
+            atlas.index = phase.frame;
+            timer.set_elapsed(Duration::from_secs_f32(phase.elapsed));
+        }
+    }
+}
+
+pub fn forget_snapshot(mut commands: Commands, mut pending_trap_phases: ResMut<PendingTrapPhases>) {
+    commands.remove_resource::<PendingSnapshot>();
+    pending_trap_phases.clear();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bevy::ecs::system::RunSystemOnce;
+
+    fn spawn_player(world: &mut World, translation: Vec3) -> Entity {
+        world
+            .spawn((
+                Player,
+                Transform::from_translation(translation),
+                GlobalTransform::default(),
+                Velocity::default(),
+                Breath(Timer::from_seconds(10., TimerMode::Once)),
+                Health::new(3),
+                PlayerId::One,
+            ))
+            .id()
+    }
+
+    #[test]
+    fn restores_the_player_and_the_collected_fruits() {
+        let mut world = World::new();
+        world.init_resource::<CollectedFruits>();
+        world.init_resource::<PendingTrapPhases>();
+        world.insert_resource(LevelSelection::index(0));
+        let mut stopwatch = Stopwatch::new();
+        stopwatch.set_elapsed(Duration::from_secs(3));
+        world.insert_resource(LevelTimer {
+            level: Some("level".to_string()),
+            stopwatch,
+        });
+        world
+            .resource_mut::<CollectedFruits>()
+            .insert("fruit".to_string());
+        let player = spawn_player(&mut world, Vec3::new(16., 32., 1.));
+        world.get_mut::<Velocity>(player).unwrap().linvel = Vec2::new(4., -2.);
+        world
+            .get_mut::<Breath>(player)
+            .unwrap()
+            .set_elapsed(Duration::from_secs(2));
+        world.get_mut::<Health>(player).unwrap().current = 1;
+        let ldtk_world = world.spawn(Handle::<LdtkProject>::default()).id();
+
+        let snapshot = take_snapshot(&mut world).unwrap();
+        assert_eq!(snapshot.level, "level");
+        assert_eq!(snapshot.player.translation, [16., 32.]);
+        assert_eq!(snapshot.level_time, 3.);
+
+        // Playing on changes the game
+        world
+            .resource_mut::<CollectedFruits>()
+            .insert("other".to_string());
+        world.resource_mut::<LevelTimer>().stopwatch.reset();
+        world.despawn(player);
+
+        restore_snapshot(&mut world, snapshot.clone());
+        assert!(world.get::<Respawn>(ldtk_world).is_some());
+        assert_eq!(
+            world
+                .resource::<CollectedFruits>()
+                .iter()
+                .collect::<Vec<_>>(),
+            vec!["fruit"]
+        );
+        assert_eq!(world.resource::<LevelTimer>().stopwatch.elapsed_secs(), 3.);
+
+        // The world respawns the player at their start
+        let player = spawn_player(&mut world, Vec3::new(0., 0., 1.));
+        world.run_system_once(apply_player_snapshot);
+        world.flush();
+        let transform = world.get::<Transform>(player).unwrap();
+        assert_eq!(transform.translation, Vec3::new(16., 32., 1.));
+        assert_eq!(
+            world.get::<Velocity>(player).unwrap().linvel,
+            Vec2::new(4., -2.)
+        );
+        assert_eq!(world.get::<Breath>(player).unwrap().elapsed_secs(), 2.);
+        assert_eq!(world.get::<Health>(player).unwrap().current, 1);
+        assert_eq!(
+            *world.resource::<LevelSelection>(),
+            LevelSelection::iid("level")
+        );
+        assert!(!world.contains_resource::<PendingSnapshot>());
+        assert_eq!(take_snapshot(&mut world), Some(snapshot));
+    }
+}
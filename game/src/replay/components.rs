@@ -0,0 +1,364 @@
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::hash::Hasher;
+
+use crate::hash::Fnv1a;
+use crate::player::components::{Character, Movement};
+use crate::replay::MAX_REPLAY_FRAMES;
+
+/// The version of the replays written by this build.
+pub const REPLAY_VERSION: u32 = 1;
+
+/// The speeds a replay can be played back at.
+pub const PLAYBACK_SPEEDS: [f32; 5] = [0.25, 0.5, 1., 2., 4.];
+
+/// The first player in a frame of a replay.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+pub struct ReplayFrame {
+    /// The time since the level was entered in seconds
+    pub time: f32,
+    pub translation: [f32; 2],
+    pub velocity: [f32; 2],
+    pub grounded: bool,
+    /// The pressed actions and the stick, as the bits of a [`crate::input::bits::InputBits`]
+    pub actions: u8,
+    pub movement: Movement,
+    /// The index of the frame of the animation
+    pub frame: usize,
+    pub flip_x: bool,
+}
+
+/// How the first player played a level.
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+pub struct Replay {
+    pub version: u32,
+    /// The iid of the level
+    pub level: String,
+    pub character: Character,
+    pub frames: Vec<ReplayFrame>,
+}
+
+/// The recording of the level that is being played.
+///
+/// Besides the replay, the speedrun ghost is sampled from it
+/// and leaderboard times are identified by the hash of its inputs.
+#[derive(Resource, Default, Debug)]
+pub struct ReplayRecorder {
+    /// The replay, whose frames stop at [`MAX_REPLAY_FRAMES`]
+    pub replay: Option<Replay>,
+    /// The last recorded frame, also once the replay is full
+    pub last_frame: Option<ReplayFrame>,
+    /// The number of fixed steps recorded in the level, also once the replay is full
+    pub step_count: u32,
+    /// The hash of the steps in which the pressed actions changed
+    input_hasher: Fnv1a,
+}
+
+impl ReplayRecorder {
+    /// Starts recording a level, returning the replay of the previous one.
+    pub fn start(&mut self, level: String, character: Character) -> Option<Replay> {
+        let previous = std::mem::take(self).replay;
+        self.replay = Some(Replay {
+            version: REPLAY_VERSION,
+            level,
+            character,
+            frames: Vec::new(),
+        });
+        previous
+    }
+
+    pub fn level(&self) -> Option<&str> {
+        self.replay.as_ref().map(|replay| replay.level.as_str())
+    }
+
+    /// Records the frame of the next fixed step.
+    pub fn record(&mut self, frame: ReplayFrame) {
+        let last_actions = self.last_frame.map_or(0, |last| last.actions);
+        if frame.actions != last_actions {
+            self.input_hasher.write(&self.step_count.to_le_bytes());
+            self.input_hasher.write(&[frame.actions]);
+        }
+        if let Some(replay) = &mut self.replay {
+            if replay.frames.len() < MAX_REPLAY_FRAMES {
+                replay.frames.push(frame);
+            }
+        }
+        self.last_frame = Some(frame);
+        self.step_count += 1;
+    }
+
+    /// Returns the hash of the inputs, which identifies the replay of the level.
+    pub fn input_hash(&self) -> String {
+        format!("{:016x}", self.input_hasher.finish())
+    }
+}
+
+/// The replay that is being viewed.
+#[derive(Resource, Debug)]
+pub struct Playback {
+    pub replay: Replay,
+    /// The time in the replay in seconds
+    pub time: f32,
+    pub playing: bool,
+    /// The index into [`PLAYBACK_SPEEDS`]
+    pub speed: usize,
+}
+
+impl Playback {
+    pub fn new(replay: Replay) -> Self {
+        let time = replay.frames.first().map_or(0., |frame| frame.time);
+        Self {
+            replay,
+            time,
+            playing: true,
+            speed: PLAYBACK_SPEEDS
+                .iter()
+                .position(|speed| *speed == 1.)
+                .unwrap(),
+        }
+    }
+
+    pub fn speed(&self) -> f32 {
+        PLAYBACK_SPEEDS[self.speed]
+    }
+
+    /// The index of the frame shown at the current time.
+    pub fn frame_index(&self) -> usize {
+        let frames = &self.replay.frames;
+        frames
+            .partition_point(|frame| frame.time <= self.time)
+            .saturating_sub(1)
+    }
+
+    pub fn frame(&self) -> Option<&ReplayFrame> {
+        self.replay.frames.get(self.frame_index())
+    }
+
+    fn start_time(&self) -> f32 {
+        self.replay.frames.first().map_or(0., |frame| frame.time)
+    }
+
+    fn end_time(&self) -> f32 {
+        self.replay.frames.last().map_or(0., |frame| frame.time)
+    }
+
+    /// How far the replay was played, from 0 to 1.
+    pub fn progress(&self) -> f32 {
+        let duration = self.end_time() - self.start_time();
+        if duration > 0. {
+            (self.time - self.start_time()) / duration
+        } else {
+            0.
+        }
+    }
+
+    /// Plays the replay on by real seconds, stopping at its end.
+    pub fn advance(&mut self, seconds: f32) {
+        if !self.playing {
+            return;
+        }
+        self.time = (self.time + seconds * self.speed()).min(self.end_time());
+        if self.time >= self.end_time() {
+            self.playing = false;
+        }
+    }
+
+    /// Pauses on the frame a number of frames before or after the current one.
+    pub fn step(&mut self, frames: isize) {
+        self.playing = false;
+        let last = self.replay.frames.len().saturating_sub(1);
+        let index = self.frame_index().saturating_add_signed(frames).min(last);
+        if let Some(frame) = self.replay.frames.get(index) {
+            self.time = frame.time;
+        }
+    }
+
+    /// Jumps to a point of the replay, from 0 to 1.
+    pub fn seek(&mut self, progress: f32) {
+        let (start, end) = (self.start_time(), self.end_time());
+        self.time = start + (end - start) * progress.clamp(0., 1.);
+    }
+
+    /// Plays or pauses, starting over once the replay ended.
+    pub fn toggle(&mut self) {
+        if !self.playing && self.time >= self.end_time() {
+            self.time = self.start_time();
+        }
+        self.playing = !self.playing;
+    }
+
+    pub fn faster(&mut self) {
+        self.speed = (self.speed + 1).min(PLAYBACK_SPEEDS.len() - 1);
+    }
+
+    pub fn slower(&mut self) {
+        self.speed = self.speed.saturating_sub(1);
+    }
+}
+
+/// The sprite of the player in the replay.
+#[derive(Component)]
+pub struct ReplayPuppet;
+
+#[derive(Component)]
+pub struct ReplayViewer;
+
+/// Shows the frame, the pressed actions and the velocity.
+#[derive(Component)]
+pub struct ReplayOverlay;
+
+/// The bar the replay is scrubbed on.
+#[derive(Component)]
+pub struct Timeline;
+
+/// The part of the timeline that was played.
+#[derive(Component)]
+pub struct TimelineProgress;
+
+#[derive(Component, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReplayButton {
+    StepBack,
+    PlayPause,
+    StepForward,
+    Slower,
+    Faster,
+    /// Detaches the camera from the player
+    FreeCamera,
+    Back,
+}
+
+impl ReplayButton {
+    pub const ALL: [ReplayButton; 7] = [
+        ReplayButton::StepBack,
+        ReplayButton::PlayPause,
+        ReplayButton::StepForward,
+        ReplayButton::Slower,
+        ReplayButton::Faster,
+        ReplayButton::FreeCamera,
+        ReplayButton::Back,
+    ];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            ReplayButton::StepBack => "<",
+            ReplayButton::PlayPause => "Play",
+            ReplayButton::StepForward => ">",
+            ReplayButton::Slower => "-",
+            ReplayButton::Faster => "+",
+            ReplayButton::FreeCamera => "Camera",
+            ReplayButton::Back => "Back",
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn playback() -> Playback {
+        let frames = (0..=60)
+            .map(|index| ReplayFrame {
+                time: 1. + index as f32 / 60.,
+                translation: [index as f32, 0.],
+                velocity: [60., 0.],
+                grounded: true,
+                actions: 0,
+                movement: Movement::Run,
+                frame: 0,
+                flip_x: false,
+            })
+            .collect();
+        Playback::new(Replay {
+            version: REPLAY_VERSION,
+            level: "level".to_string(),
+            character: Character::default(),
+            frames,
+        })
+    }
+
+    #[test]
+    fn plays_at_the_chosen_speed_until_the_end() {
+        let mut playback = playback();
+        assert_eq!(playback.frame_index(), 0);
+        playback.faster();
+        playback.advance(0.25);
+        assert_eq!(playback.frame_index(), 30);
+        assert_eq!(playback.progress(), 0.5);
+
+        playback.advance(10.);
+        assert_eq!(playback.frame_index(), 60);
+        assert!(!playback.playing);
+        // Playing again starts over
+        playback.toggle();
+        assert_eq!(playback.frame_index(), 0);
+        assert!(playback.playing);
+    }
+
+    #[test]
+    fn steps_and_scrubs() {
+        let mut playback = playback();
+        playback.step(3);
+        assert!(!playback.playing);
+        assert_eq!(playback.frame_index(), 3);
+        playback.step(-10);
+        assert_eq!(playback.frame_index(), 0);
+        playback.step(100);
+        assert_eq!(playback.frame_index(), 60);
+
+        playback.seek(0.25);
+        assert_eq!(playback.frame().unwrap().translation, [15., 0.]);
+        // Paused playback stays where it was scrubbed to
+        playback.advance(1.);
+        assert_eq!(playback.frame_index(), 15);
+    }
+
+    fn recorded_frame(actions: u8) -> ReplayFrame {
+        ReplayFrame {
+            actions,
+            ..playback().replay.frames[0]
+        }
+    }
+
+    #[test]
+    fn caps_the_replay_but_keeps_hashing_the_inputs() {
+        let mut recorder = ReplayRecorder::default();
+        recorder.start("level".to_string(), Character::default());
+        let idle = recorder.input_hash();
+        for _ in 0..MAX_REPLAY_FRAMES {
+            recorder.record(recorded_frame(0));
+        }
+        // Frames without inputs don't change the hash
+        assert_eq!(recorder.input_hash(), idle);
+        recorder.record(recorded_frame(0b0010));
+
+        assert_eq!(
+            recorder.replay.as_ref().unwrap().frames.len(),
+            MAX_REPLAY_FRAMES
+        );
+        assert_eq!(recorder.last_frame.unwrap().actions, 0b0010);
+        assert_ne!(recorder.input_hash(), idle);
+
+        // The same inputs in another frame are another replay
+        let mut other = ReplayRecorder::default();
+        other.record(recorded_frame(0b0010));
+        assert_ne!(other.input_hash(), recorder.input_hash());
+
+        let replay = recorder.start("next".to_string(), Character::default());
+        assert_eq!(replay.unwrap().level, "level");
+        assert_eq!(recorder.level(), Some("next"));
+        assert_eq!(recorder.input_hash(), idle);
+    }
+
+    #[test]
+    fn clamps_the_speed() {
+        let mut playback = playback();
+        for _ in 0..10 {
+            playback.slower();
+        }
+        assert_eq!(playback.speed(), PLAYBACK_SPEEDS[0]);
+        for _ in 0..10 {
+            playback.faster();
+        }
+        assert_eq!(playback.speed(), 4.);
+    }
+}
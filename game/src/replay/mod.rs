@@ -0,0 +1,70 @@
+pub mod components;
+pub mod systems;
+
+use bevy::prelude::*;
+
+use crate::camera::systems::{reset_camera, update_camera};
+use crate::replay::components::{Playback, ReplayRecorder};
+use crate::{player, world, AppState, GameState};
+
+/// The name of the file the replay of the last played level is stored in.
+pub const REPLAY_FILE: &str = "replay.json";
+/// How many frames a replay keeps, ten minutes of fixed steps.
+pub const MAX_REPLAY_FRAMES: usize = player::STEPS_PER_SECOND as usize * 60 * 10;
+
+/// Records how the levels are played and plays the last one back.
+pub struct ReplayPlugin;
+
+impl Plugin for ReplayPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<ReplayRecorder>()
+            // Once per fixed step, so the recording is the same at any frame rate
+            .add_systems(
+                FixedPostUpdate,
+                systems::record_replay
+                    .run_if(in_state(AppState::InGame))
+                    .run_if(in_state(GameState::Running)),
+            )
+            .add_systems(OnExit(AppState::InGame), systems::save_replay)
+            .add_systems(
+                OnEnter(AppState::Replay),
+                (
+                    player::systems::load_player_tilesets,
+                    // The world starts at the first level, the replay selects its own
+                    world::systems::spawn,
+                    systems::load_replay,
+                    systems::spawn_viewer,
+                    reset_camera,
+                )
+                    .chain(),
+            )
+            .add_systems(
+                OnExit(AppState::Replay),
+                (systems::despawn_viewer, world::systems::despawn),
+            )
+            .add_systems(
+                Update,
+                (
+                    systems::remove_players,
+                    systems::control_with_keyboard,
+                    systems::interact_with_replay_buttons,
+                )
+                    .run_if(in_state(AppState::Replay)),
+            )
+            .add_systems(
+                Update,
+                (
+                    systems::scrub_timeline,
+                    systems::advance_playback,
+                    systems::apply_frame,
+                    systems::update_overlay,
+                )
+                    .chain()
+                    .after(systems::control_with_keyboard)
+                    .after(systems::interact_with_replay_buttons)
+                    .before(update_camera)
+                    .run_if(resource_exists::<Playback>)
+                    .run_if(in_state(AppState::Replay)),
+            );
+    }
+}
@@ -0,0 +1,472 @@
+use bevy::prelude::*;
+use bevy::window::PrimaryWindow;
+use bevy_ecs_ldtk::prelude::*;
+use bevy_rapier2d::prelude::*;
+
+use crate::camera::components::{CameraTarget, FreeCamera};
+use crate::input::bits::InputBits;
+use crate::player::components::{Character, Movement, Player, PlayerId, PlayerInput};
+use crate::replay::components::{
+    Playback, Replay, ReplayButton, ReplayFrame, ReplayOverlay, ReplayPuppet, ReplayRecorder,
+    ReplayViewer, Timeline, TimelineProgress, REPLAY_VERSION,
+};
+use crate::replay::REPLAY_FILE;
+use crate::save::components::LevelTimer;
+use crate::save::storage;
+use crate::transition::components::{StartTransition, TransitionStyle, TransitionTarget};
+use crate::{color, AppState, Tilesets};
+
+type RecordedPlayer<'a> = (
+    &'a GlobalTransform,
+    &'a Velocity,
+    &'a KinematicCharacterControllerOutput,
+    &'a PlayerInput,
+    &'a Handle<Image>,
+    &'a TextureAtlas,
+    &'a Sprite,
+    &'a Character,
+    &'a PlayerId,
+);
+
+fn save(replay: &Replay) {
+    let result = serde_json::to_string(replay)
+        .map_err(Into::into)
+        .and_then(|text| storage::write(REPLAY_FILE, &text));
+    if let Err(error) = result {
+        error!("Could not save the replay: {error}");
+    }
+}
+
+/// Records the first player after each fixed step, saving the replay of a level once they leave it.
+///
+/// Leaving a level starts the recording over, also for the leaderboard and the ghost.
+pub fn record_replay(
+    player_query: Query<RecordedPlayer, With<Player>>,
+    timer: Res<LevelTimer>,
+    tileset: Res<Tilesets<(Character, Movement)>>,
+    mut recorder: ResMut<ReplayRecorder>,
+) {
+    let Some((transform, velocity, output, input, texture, atlas, sprite, character, _)) =
+        player_query.iter().find(|(.., id)| **id == PlayerId::One)
+    else {
+        return;
+    };
+    let Some(level) = &timer.level else {
+        return;
+    };
+    if recorder.level() != Some(level.as_str()) {
+        if let Some(replay) = recorder.start(level.clone(), *character) {
+            save(&replay);
+        }
+    }
+    let movement = tileset
+        .iter()
+        .find(|(_, handle)| *handle == texture)
+        .map(|((_, movement), _)| *movement)
+        .unwrap_or_default();
+    recorder.record(ReplayFrame {
+        time: timer.stopwatch.elapsed_secs(),
+        translation: transform.translation().truncate().to_array(),
+        velocity: velocity.linvel.to_array(),
+        grounded: output.grounded,
+        actions: input.current.0,
+        movement,
+        frame: atlas.index,
+        flip_x: sprite.flip_x,
+    });
+}
+
+/// Saves the replay of the level the game was left in.
+pub fn save_replay(mut recorder: ResMut<ReplayRecorder>) {
+    if let Some(replay) = recorder
+        .replay
+        .take()
+        .filter(|replay| !replay.frames.is_empty())
+    {
+        save(&replay);
+    }
+}
+
+pub fn load_replay(mut commands: Commands, mut level_selection: ResMut<LevelSelection>) {
+    let replay = match storage::read(REPLAY_FILE) {
+        Ok(Some(text)) => match serde_json::from_str::<Replay>(&text) {
+            Ok(replay) if replay.version == REPLAY_VERSION => replay,
+            Ok(replay) => {
+                warn!("The replay has the unsupported version {}", replay.version);
+                return;
+            }
+            Err(error) => {
+                warn!("Ignoring the invalid replay: {error}");
+                return;
+            }
+        },
+        Ok(None) => {
+            info!("There is no replay");
+            return;
+        }
+        Err(error) => {
+            warn!("Could not read the replay: {error}");
+            return;
+        }
+    };
+    *level_selection = LevelSelection::iid(replay.level.clone());
+    commands.insert_resource(Playback::new(replay));
+}
+
+/// Removes the players the levels spawn, the replay has its own.
+pub fn remove_players(mut commands: Commands, player_query: Query<Entity, Added<Player>>) {
+    for player in player_query.iter() {
+        commands.entity(player).despawn_recursive();
+    }
+}
+
+pub fn spawn_viewer(
+    mut commands: Commands,
+    playback: Option<Res<Playback>>,
+    tileset: Res<Tilesets<(Character, Movement)>>,
+    mut layouts: ResMut<Assets<TextureAtlasLayout>>,
+) {
+    let text_style = |font_size| TextStyle {
+        font_size,
+        color: color::PRIMARY_CONTENT,
+        ..default()
+    };
+    if let Some(playback) = &playback {
+        commands.spawn((
+            SpriteBundle {
+                texture: tileset
+                    .get(&(playback.replay.character, Movement::Idle))
+                    .cloned()
+                    .unwrap_or_default(),
+                transform: Transform::from_xyz(0., 0., 10.),
+                ..default()
+            },
+            TextureAtlas {
+                layout: layouts.add(TextureAtlasLayout::from_grid(
+                    UVec2::splat(32),
+                    12,
+                    1,
+                    None,
+                    None,
+                )),
+                index: 0,
+            },
+            Velocity::default(),
+            KinematicCharacterControllerOutput::default(),
+            CameraTarget,
+            ReplayPuppet,
+        ));
+    }
+
+    commands
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    flex_direction: FlexDirection::Column,
+                    justify_content: JustifyContent::SpaceBetween,
+                    align_items: AlignItems::Center,
+                    width: Val::Percent(100.0),
+                    height: Val::Percent(100.0),
+                    padding: UiRect::all(Val::Px(10.0)),
+                    ..default()
+                },
+                ..default()
+            },
+            ReplayViewer,
+        ))
+        .with_children(|parent| {
+            let overlay = if playback.is_some() {
+                String::new()
+            } else {
+                "No replay recorded yet".to_string()
+            };
+            parent.spawn((
+                TextBundle::from_section(overlay, text_style(24.0)).with_style(Style {
+                    align_self: AlignSelf::Start,
+                    ..default()
+                }),
+                ReplayOverlay,
+            ));
+
+            parent
+                .spawn(NodeBundle {
+                    style: Style {
+                        flex_direction: FlexDirection::Column,
+                        align_items: AlignItems::Center,
+                        row_gap: Val::Px(10.0),
+                        width: Val::Percent(100.0),
+                        ..default()
+                    },
+                    ..default()
+                })
+                .with_children(|parent| {
+                    // Timeline
+                    parent
+                        .spawn((
+                            NodeBundle {
+                                style: Style {
+                                    width: Val::Percent(80.0),
+                                    height: Val::Px(12.0),
+                                    ..default()
+                                },
+                                background_color: color::PRIMARY_CONTENT.with_alpha(0.3).into(),
+                                ..default()
+                            },
+                            Interaction::default(),
+                            Timeline,
+                        ))
+                        .with_children(|parent| {
+                            parent.spawn((
+                                NodeBundle {
+                                    style: Style {
+                                        width: Val::Percent(0.0),
+                                        height: Val::Percent(100.0),
+                                        ..default()
+                                    },
+                                    background_color: color::PRIMARY.into(),
+                                    ..default()
+                                },
+                                TimelineProgress,
+                            ));
+                        });
+
+                    // Buttons
+                    parent
+                        .spawn(NodeBundle {
+                            style: Style {
+                                flex_direction: FlexDirection::Row,
+                                column_gap: Val::Px(10.0),
+                                ..default()
+                            },
+                            ..default()
+                        })
+                        .with_children(|parent| {
+                            for button in ReplayButton::ALL {
+                                parent
+                                    .spawn((
+                                        ButtonBundle {
+                                            background_color: color::PRIMARY.into(),
+                                            style: Style {
+                                                justify_content: JustifyContent::Center,
+                                                align_items: AlignItems::Center,
+                                                min_width: Val::Px(56.0),
+                                                height: Val::Px(48.0),
+                                                padding: UiRect::horizontal(Val::Px(10.0)),
+                                                ..default()
+                                            },
+                                            ..default()
+                                        },
+                                        button,
+                                    ))
+                                    .with_children(|parent| {
+                                        parent.spawn(TextBundle::from_section(
+                                            button.label(),
+                                            text_style(24.0),
+                                        ));
+                                    });
+                            }
+                        });
+                });
+        });
+}
+
+type ViewerEntities = Or<(With<ReplayViewer>, With<ReplayPuppet>)>;
+
+pub fn despawn_viewer(mut commands: Commands, query: Query<Entity, ViewerEntities>) {
+    for entity in query.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+    commands.remove_resource::<Playback>();
+    commands.remove_resource::<FreeCamera>();
+}
+
+/// Which action a replay button or key triggers.
+fn apply_control(
+    button: ReplayButton,
+    playback: Option<&mut Playback>,
+    free_camera: bool,
+    commands: &mut Commands,
+    transition_events: &mut EventWriter<StartTransition>,
+) {
+    match (button, playback) {
+        (ReplayButton::Back, _) => {
+            transition_events.send(StartTransition {
+                target: TransitionTarget::App(AppState::MainMenu),
+                style: TransitionStyle::Fade,
+            });
+        }
+        (ReplayButton::FreeCamera, _) if free_camera => commands.remove_resource::<FreeCamera>(),
+        (ReplayButton::FreeCamera, _) => commands.insert_resource(FreeCamera),
+        (ReplayButton::StepBack, Some(playback)) => playback.step(-1),
+        (ReplayButton::PlayPause, Some(playback)) => playback.toggle(),
+        (ReplayButton::StepForward, Some(playback)) => playback.step(1),
+        (ReplayButton::Slower, Some(playback)) => playback.slower(),
+        (ReplayButton::Faster, Some(playback)) => playback.faster(),
+        (_, None) => {}
+    }
+}
+
+/// Controls the playback with the arrow keys, space, F and escape.
+pub fn control_with_keyboard(
+    mut commands: Commands,
+    keyboard_input: Res<ButtonInput<KeyCode>>,
+    mut playback: Option<ResMut<Playback>>,
+    free_camera: Option<Res<FreeCamera>>,
+    mut transition_events: EventWriter<StartTransition>,
+) {
+    let keys = [
+        (KeyCode::ArrowLeft, ReplayButton::StepBack),
+        (KeyCode::Space, ReplayButton::PlayPause),
+        (KeyCode::ArrowRight, ReplayButton::StepForward),
+        (KeyCode::ArrowDown, ReplayButton::Slower),
+        (KeyCode::ArrowUp, ReplayButton::Faster),
+        (KeyCode::KeyF, ReplayButton::FreeCamera),
+        (KeyCode::Escape, ReplayButton::Back),
+    ];
+    for (key, button) in keys {
+        if keyboard_input.just_pressed(key) {
+            apply_control(
+                button,
+                playback.as_deref_mut(),
+                free_camera.is_some(),
+                &mut commands,
+                &mut transition_events,
+            );
+        }
+    }
+}
+
+type ButtonForInteraction<'a> = (&'a Interaction, &'a mut BackgroundColor, &'a ReplayButton);
+
+pub fn interact_with_replay_buttons(
+    mut commands: Commands,
+    mut button_query: Query<ButtonForInteraction, Changed<Interaction>>,
+    mut playback: Option<ResMut<Playback>>,
+    free_camera: Option<Res<FreeCamera>>,
+    mut transition_events: EventWriter<StartTransition>,
+) {
+    for (interaction, mut background_color, button) in button_query.iter_mut() {
+        match interaction {
+            Interaction::None => {
+                *background_color = color::PRIMARY.into();
+            }
+            Interaction::Pressed => {
+                *background_color = color::PRIMARY.into();
+                apply_control(
+                    *button,
+                    playback.as_deref_mut(),
+                    free_camera.is_some(),
+                    &mut commands,
+                    &mut transition_events,
+                );
+            }
+            Interaction::Hovered => {
+                *background_color = color::PRIMARY_HOVER.into();
+            }
+        }
+    }
+}
+
+/// Seeks to where the timeline is pressed, for as long as it is held.
+pub fn scrub_timeline(
+    timeline_query: Query<(&Interaction, &Node, &GlobalTransform), With<Timeline>>,
+    window_query: Query<&Window, With<PrimaryWindow>>,
+    mut playback: ResMut<Playback>,
+) {
+    let Ok((interaction, node, transform)) = timeline_query.get_single() else {
+        return;
+    };
+    let Some(cursor) = window_query
+        .get_single()
+        .ok()
+        .and_then(Window::cursor_position)
+    else {
+        return;
+    };
+    if *interaction != Interaction::Pressed {
+        return;
+    }
+    let rect = node.logical_rect(transform);
+    if rect.width() > 0. {
+        playback.seek((cursor.x - rect.min.x) / rect.width());
+    }
+}
+
+pub fn advance_playback(mut playback: ResMut<Playback>, time: Res<Time>) {
+    playback.advance(time.delta_seconds());
+}
+
+type PuppetRelated<'a> = (
+    &'a mut Transform,
+    &'a mut Handle<Image>,
+    &'a mut TextureAtlas,
+    &'a mut Sprite,
+    &'a mut Velocity,
+    &'a mut KinematicCharacterControllerOutput,
+);
+
+/// Puts the player of the replay where they were in the current frame.
+pub fn apply_frame(
+    playback: Res<Playback>,
+    mut puppet_query: Query<PuppetRelated, With<ReplayPuppet>>,
+    tileset: Res<Tilesets<(Character, Movement)>>,
+) {
+    let Ok((mut transform, mut texture, mut atlas, mut sprite, mut velocity, mut output)) =
+        puppet_query.get_single_mut()
+    else {
+        return;
+    };
+    let Some(frame) = playback.frame() else {
+        return;
+    };
+    transform.translation = Vec2::from(frame.translation).extend(transform.translation.z);
+    if let Some(handle) = tileset.get(&(playback.replay.character, frame.movement)) {
+        if *texture != *handle {
+            *texture = handle.clone();
+        }
+    }
+    atlas.index = frame.frame;
+    sprite.flip_x = frame.flip_x;
+    velocity.linvel = Vec2::from(frame.velocity);
+    output.grounded = frame.grounded;
+}
+
+/// Shows the pressed actions and the velocity of the current frame, and the progress.
+pub fn update_overlay(
+    playback: Res<Playback>,
+    mut overlay_query: Query<&mut Text, With<ReplayOverlay>>,
+    mut progress_query: Query<&mut Style, With<TimelineProgress>>,
+) {
+    if let Ok(mut style) = progress_query.get_single_mut() {
+        style.width = Val::Percent(playback.progress() * 100.);
+    }
+    let (Ok(mut text), Some(frame)) = (overlay_query.get_single_mut(), playback.frame()) else {
+        return;
+    };
+    let input = InputBits(frame.actions);
+    let pressed: Vec<_> = InputBits::ACTIONS
+        .into_iter()
+        .filter(|action| input.pressed(*action))
+        .map(|action| format!("{action:?}"))
+        .collect();
+    let state = if playback.playing {
+        "Playing"
+    } else {
+        "Paused"
+    };
+    text.sections[0].value = format!(
+        "{state} at {}x\nFrame {}/{}  {:.2}s\nInput: {}\nVelocity: {:.0}, {:.0}{}",
+        playback.speed(),
+        playback.frame_index() + 1,
+        playback.replay.frames.len(),
+        frame.time,
+        if pressed.is_empty() {
+            "none".to_string()
+        } else {
+            pressed.join(" ")
+        },
+        frame.velocity[0],
+        frame.velocity[1],
+        if frame.grounded { "  grounded" } else { "" },
+    );
+}
@@ -0,0 +1,61 @@
+use bevy::prelude::*;
+use bevy::time::Stopwatch;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+use crate::save::CHARACTERS;
+
+/// The progress of the campaign, that is saved between sessions.
+#[derive(Resource, Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(default)]
+pub struct SaveGame {
+    /// The iid of the level the player was in last
+    pub current_level: Option<String>,
+    /// The progress of each level by its iid
+    pub levels: BTreeMap<String, LevelProgress>,
+    pub unlocked_characters: Vec<String>,
+}
+
+impl Default for SaveGame {
+    fn default() -> Self {
+        Self {
+            current_level: None,
+            levels: BTreeMap::new(),
+            unlocked_characters: vec![CHARACTERS[0].to_string()],
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Default, Debug, Clone, PartialEq)]
+#[serde(default)]
+pub struct LevelProgress {
+    /// Whether every fruit of the level was collected
+    pub completed: bool,
+    /// The fastest time in seconds every fruit of the level was collected in
+    pub best_time: Option<f32>,
+    /// The most fruits collected in a single visit of the level
+    pub fruits_collected: u32,
+}
+
+/// How long the player has been in the current level.
+#[derive(Resource, Default, Debug)]
+pub struct LevelTimer {
+    /// The iid of the current level
+    pub level: Option<String>,
+    pub stopwatch: Stopwatch,
+}
+
+/// Moves the player to the start of a level once they are spawned, to continue the campaign.
+#[derive(Resource, Debug)]
+pub struct PendingStart(pub String);
+
+/// Sent when every fruit of a level was collected.
+#[derive(Event, Debug, Clone)]
+pub struct LevelCompleted {
+    /// The iid of the level
+    pub level: String,
+    /// The identifier of the level
+    pub name: String,
+    /// How long collecting the fruits took in seconds
+    pub time: f32,
+}
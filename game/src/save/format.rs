@@ -0,0 +1,166 @@
+//! The versioned format of the save file.
+//!
+//! Saves are JSON objects with a `version` field. Saves of older versions are upgraded
+//! by applying the migrations since their version, before they are deserialized.
+
+use serde_json::{Map, Value};
+use std::fmt;
+
+use crate::save::components::SaveGame;
+
+/// Upgrades a save to the next version.
+pub type Migration = fn(&mut Map<String, Value>);
+
+/// The migration at index `n` upgrades a save of version `n + 1` to version `n + 2`.
+///
+/// To change the format, bump nothing by hand: append a migration here.
+const MIGRATIONS: &[Migration] = &[];
+
+/// The version of the saves written by this build.
+pub const SAVE_VERSION: u64 = MIGRATIONS.len() as u64 + 1;
+
+const VERSION_FIELD: &str = "version";
+
+#[derive(Debug)]
+pub enum SaveError {
+    Json(serde_json::Error),
+    /// The save has no version, so it is not a save
+    MissingVersion,
+    /// The save was written by a newer build
+    UnsupportedVersion(u64),
+    Storage(String),
+}
+
+impl fmt::Display for SaveError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SaveError::Json(error) => write!(f, "invalid save: {error}"),
+            SaveError::MissingVersion => write!(f, "the save has no version"),
+            SaveError::UnsupportedVersion(version) => write!(
+                f,
+                "the save has version {version}, but only versions up to {SAVE_VERSION} are supported"
+            ),
+            SaveError::Storage(error) => write!(f, "could not access the save: {error}"),
+        }
+    }
+}
+
+impl std::error::Error for SaveError {}
+
+impl From<serde_json::Error> for SaveError {
+    fn from(error: serde_json::Error) -> Self {
+        SaveError::Json(error)
+    }
+}
+
+/// Serializes a save with the current version.
+pub fn encode(save: &SaveGame) -> Result<String, SaveError> {
+    let mut value = serde_json::to_value(save)?;
+    if let Some(object) = value.as_object_mut() {
+        object.insert(VERSION_FIELD.to_string(), SAVE_VERSION.into());
+    }
+    Ok(serde_json::to_string_pretty(&value)?)
+}
+
+/// Deserializes a save of the current or an older version.
+pub fn decode(text: &str) -> Result<SaveGame, SaveError> {
+    decode_with_migrations(text, MIGRATIONS)
+}
+
+fn decode_with_migrations(text: &str, migrations: &[Migration]) -> Result<SaveGame, SaveError> {
+    let mut value: Value = serde_json::from_str(text)?;
+    let object = value.as_object_mut().ok_or(SaveError::MissingVersion)?;
+    let version = object
+        .remove(VERSION_FIELD)
+        .and_then(|version| version.as_u64())
+        .ok_or(SaveError::MissingVersion)?;
+    let current_version = migrations.len() as u64 + 1;
+    if version == 0 || version > current_version {
+        return Err(SaveError::UnsupportedVersion(version));
+    }
+    for migration in &migrations[version as usize - 1..] {
+        migration(object);
+    }
+    Ok(serde_json::from_value(value)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::save::components::LevelProgress;
+
+    #[test]
+    fn round_trip() {
+        let mut save = SaveGame {
+            current_level: Some("level".to_string()),
+            ..Default::default()
+        };
+        save.levels.insert(
+            "level".to_string(),
+            LevelProgress {
+                completed: true,
+                best_time: Some(12.5),
+                fruits_collected: 19,
+            },
+        );
+        let text = encode(&save).unwrap();
+        assert!(text.contains(&format!("\"version\": {SAVE_VERSION}")));
+        assert_eq!(decode(&text).unwrap(), save);
+    }
+
+    #[test]
+    fn missing_fields_are_defaulted() {
+        let save = decode(&format!("{{\"version\": {SAVE_VERSION}}}")).unwrap();
+        assert_eq!(save, SaveGame::default());
+    }
+
+    #[test]
+    fn rejects_saves_without_version() {
+        assert!(matches!(decode("{}"), Err(SaveError::MissingVersion)));
+        assert!(matches!(decode("[]"), Err(SaveError::MissingVersion)));
+    }
+
+    #[test]
+    fn rejects_newer_versions() {
+        let text = format!("{{\"version\": {}}}", SAVE_VERSION + 1);
+        assert!(matches!(
+            decode(&text),
+            Err(SaveError::UnsupportedVersion(version)) if version == SAVE_VERSION + 1
+        ));
+    }
+
+    #[test]
+    fn applies_migrations_since_the_version() {
+        // Version 1 called the current level `level`, version 2 added a field
+        let migrations: &[Migration] = &[
+            |save| {
+                let level = save.remove("level").unwrap_or_default();
+                save.insert("current_level".to_string(), level);
+            },
+            |save| {
+                save.insert("unlocked_characters".to_string(), Value::Array(vec![]));
+            },
+        ];
+
+        let save =
+            decode_with_migrations(r#"{"version": 1, "level": "first"}"#, migrations).unwrap();
+        assert_eq!(save.current_level.as_deref(), Some("first"));
+        assert!(save.unlocked_characters.is_empty());
+
+        // A save of version 2 already has the current level
+        let save = decode_with_migrations(
+            r#"{"version": 2, "current_level": "second", "unlocked_characters": ["Pink Man"]}"#,
+            migrations,
+        )
+        .unwrap();
+        assert_eq!(save.current_level.as_deref(), Some("second"));
+        assert!(save.unlocked_characters.is_empty());
+
+        let save = decode_with_migrations(
+            r#"{"version": 3, "unlocked_characters": ["Pink Man"]}"#,
+            migrations,
+        )
+        .unwrap();
+        assert_eq!(save.unlocked_characters, vec!["Pink Man".to_string()]);
+    }
+}
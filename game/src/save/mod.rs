@@ -0,0 +1,45 @@
+pub mod components;
+pub mod format;
+pub mod storage;
+pub mod systems;
+
+use bevy::prelude::*;
+
+use crate::camera::systems::reset_camera;
+use crate::save::components::{LevelCompleted, LevelTimer, SaveGame};
+use crate::{AppState, GameState};
+
+/// The name of the file the campaign progress is saved to.
+pub const SAVE_FILE: &str = "save.json";
+
+/// The playable characters, in the order they are unlocked by completing levels.
+pub const CHARACTERS: [&str; 4] = ["Mask Dude", "Ninja Frog", "Pink Man", "Virtual Guy"];
+
+pub struct SavePlugin;
+
+impl Plugin for SavePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<LevelCompleted>()
+            .init_resource::<LevelTimer>()
+            // The main menu needs the save when it is spawned
+            .add_systems(PreStartup, systems::load)
+            .add_systems(
+                Update,
+                (
+                    systems::move_player_to_start.before(reset_camera),
+                    systems::track_level,
+                    systems::record_fruits,
+                )
+                    .chain()
+                    .run_if(in_state(AppState::InGame)),
+            )
+            .add_systems(OnExit(AppState::InGame), systems::reset_level_timer)
+            .add_systems(Last, systems::persist.run_if(resource_exists::<SaveGame>))
+            .add_systems(
+                Update,
+                systems::tick_level_timer
+                    .run_if(in_state(AppState::InGame))
+                    .run_if(in_state(GameState::Running)),
+            );
+    }
+}
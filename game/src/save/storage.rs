@@ -0,0 +1,92 @@
+//! Stores named files in the platform data directory on native builds
+//! and in `localStorage` on the web.
+
+use crate::save::format::SaveError;
+
+/// The directory or key prefix the files of the game are stored under.
+const APP_NAME: &str = "tile-based-game";
+
+#[cfg(not(target_arch = "wasm32"))]
+mod platform {
+    use std::path::PathBuf;
+    use std::{env, fs, io};
+
+    use super::{SaveError, APP_NAME};
+
+    /// Returns the directory applications store their data in on this platform.
+    fn data_dir() -> Option<PathBuf> {
+        let home = || env::var_os("HOME").map(PathBuf::from);
+        if cfg!(target_os = "windows") {
+            env::var_os("APPDATA").map(PathBuf::from)
+        } else if cfg!(target_os = "macos") {
+            home().map(|home| home.join("Library/Application Support"))
+        } else {
+            env::var_os("XDG_DATA_HOME")
+                .map(PathBuf::from)
+                .or_else(|| home().map(|home| home.join(".local/share")))
+        }
+    }
+
+    fn path(name: &str) -> Result<PathBuf, SaveError> {
+        data_dir()
+            .map(|dir| dir.join(APP_NAME).join(name))
+            .ok_or_else(|| SaveError::Storage("no data directory".to_string()))
+    }
+
+    pub fn read(name: &str) -> Result<Option<String>, SaveError> {
+        match fs::read_to_string(path(name)?) {
+            Ok(contents) => Ok(Some(contents)),
+            Err(error) if error.kind() == io::ErrorKind::NotFound => Ok(None),
+            Err(error) => Err(SaveError::Storage(error.to_string())),
+        }
+    }
+
+    pub fn write(name: &str, contents: &str) -> Result<(), SaveError> {
+        let path = path(name)?;
+        let storage_error = |error: io::Error| SaveError::Storage(error.to_string());
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir).map_err(storage_error)?;
+        }
+        // Writing to a temporary file first keeps the old file when writing fails halfway
+        let temporary = path.with_extension("tmp");
+        fs::write(&temporary, contents).map_err(storage_error)?;
+        fs::rename(temporary, path).map_err(storage_error)
+    }
+}
+
+#[cfg(target_arch = "wasm32")]
+mod platform {
+    use super::{SaveError, APP_NAME};
+
+    fn local_storage() -> Result<web_sys::Storage, SaveError> {
+        web_sys::window()
+            .and_then(|window| window.local_storage().ok().flatten())
+            .ok_or_else(|| SaveError::Storage("localStorage is not available".to_string()))
+    }
+
+    fn key(name: &str) -> String {
+        format!("{APP_NAME}/{name}")
+    }
+
+    pub fn read(name: &str) -> Result<Option<String>, SaveError> {
+        local_storage()?
+            .get_item(&key(name))
+            .map_err(|error| SaveError::Storage(format!("{error:?}")))
+    }
+
+    pub fn write(name: &str, contents: &str) -> Result<(), SaveError> {
+        local_storage()?
+            .set_item(&key(name), contents)
+            .map_err(|error| SaveError::Storage(format!("{error:?}")))
+    }
+}
+
+/// Reads a stored file, `None` if it was never written.
+pub fn read(name: &str) -> Result<Option<String>, SaveError> {
+    platform::read(name)
+}
+
+/// Stores a file, replacing the previous contents.
+pub fn write(name: &str, contents: &str) -> Result<(), SaveError> {
+    platform::write(name, contents)
+}
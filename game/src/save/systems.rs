@@ -0,0 +1,192 @@
+use bevy::prelude::*;
+use bevy_ecs_ldtk::prelude::*;
+use std::collections::HashSet;
+
+use crate::fruit::components::{Fruit, FruitCollected};
+use crate::fruit::FRUIT_IDENTIFIER;
+use crate::player::components::{Player, PlayerId};
+use crate::save::components::{LevelCompleted, LevelTimer, PendingStart, SaveGame};
+use crate::save::{format, storage, CHARACTERS, SAVE_FILE};
+use crate::world::SpawnedLevels;
+
+/// The identifier of the LDtk entity marking where the player starts a level when continuing.
+pub const START_IDENTIFIER: &str = "Start";
+
+/// Loads the save, starting a new one when there is none or it can't be read.
+pub fn load(mut commands: Commands) {
+    let save = match storage::read(SAVE_FILE)
+        .and_then(|text| text.map(|text| format::decode(&text)).transpose())
+    {
+        Ok(save) => save.unwrap_or_default(),
+        Err(error) => {
+            warn!("Starting a new save: {error}");
+            SaveGame::default()
+        }
+    };
+    commands.insert_resource(save);
+}
+
+/// Writes the save when it changed.
+pub fn persist(save: Res<SaveGame>) {
+    if !save.is_changed() || save.is_added() {
+        return;
+    }
+    if let Err(error) = format::encode(&save).and_then(|text| storage::write(SAVE_FILE, &text)) {
+        error!("Could not save the game: {error}");
+    }
+}
+
+/// Moves the player to the start of the saved level once they are spawned.
+pub fn move_player_to_start(
+    mut commands: Commands,
+    pending_start: Option<Res<PendingStart>>,
+    mut player_query: Query<(&mut Transform, &mut GlobalTransform, &PlayerId), Added<Player>>,
+    mut level_selection: ResMut<LevelSelection>,
+    ldtk_projects: Query<&Handle<LdtkProject>>,
+    ldtk_project_assets: Res<Assets<LdtkProject>>,
+) {
+    let Some(pending_start) = pending_start else {
+        return;
+    };
+    let Some((mut transform, mut global_transform, _)) = player_query
+        .iter_mut()
+        .find(|(_, _, id)| **id == PlayerId::One)
+    else {
+        return;
+    };
+    commands.remove_resource::<PendingStart>();
+    let Some(ldtk_project) = ldtk_projects
+        .iter()
+        .find_map(|ldtk_handle| ldtk_project_assets.get(ldtk_handle))
+    else {
+        return;
+    };
+    let Some(level) = ldtk_project.get_raw_level_by_iid(&pending_start.0) else {
+        warn!("The saved level {} no longer exists", pending_start.0);
+        return;
+    };
+    let Some(start) = level
+        .layer_instances
+        .iter()
+        .flatten()
+        .flat_map(|layer| &layer.entity_instances)
+        .find(|entity| entity.identifier == START_IDENTIFIER)
+    else {
+        warn!("The level {} has no start", level.identifier);
+        return;
+    };
+
+    // LDtk's y axis points down and the pivot is relative to the entity's size
+    let size = Vec2::new(start.width as f32, start.height as f32);
+    let center = start.px.as_vec2() + (Vec2::splat(0.5) - start.pivot) * size;
+    let level_origin = Vec2::new(level.world_x as f32, -(level.world_y + level.px_hei) as f32);
+    let position = level_origin + Vec2::new(center.x, level.px_hei as f32 - center.y);
+
+    transform.translation = position.extend(transform.translation.z);
+    // The camera jumps to the player in this frame, before the transforms are propagated
+    *global_transform = GlobalTransform::from(*transform);
+    *level_selection = LevelSelection::iid(level.iid.clone());
+}
+
+/// Restarts the level timer and remembers the level when the player enters a new one.
+pub fn track_level(
+    player_query: Query<(&GlobalTransform, &PlayerId), With<Player>>,
+    spawned_levels: SpawnedLevels,
+    mut timer: ResMut<LevelTimer>,
+    mut save: ResMut<SaveGame>,
+) {
+    let Some((player, _)) = player_query.iter().find(|(_, id)| **id == PlayerId::One) else {
+        return;
+    };
+    let Some((_, level)) = spawned_levels.containing(player.translation().truncate()) else {
+        return;
+    };
+    if timer.level.as_ref() != Some(&level.iid) {
+        timer.level = Some(level.iid.clone());
+        timer.stopwatch.reset();
+    }
+    if save.current_level.as_ref() != Some(&level.iid) {
+        save.current_level = Some(level.iid.clone());
+    }
+}
+
+pub fn reset_level_timer(mut timer: ResMut<LevelTimer>) {
+    *timer = LevelTimer::default();
+}
+
+pub fn tick_level_timer(mut timer: ResMut<LevelTimer>, time: Res<Time>) {
+    timer.stopwatch.tick(time.delta());
+}
+
+/// Records the fruits collected in each level and completes the level when every fruit is collected.
+pub fn record_fruits(
+    mut collected_events: EventReader<FruitCollected>,
+    fruit_query: Query<(Entity, &GlobalTransform), With<Fruit>>,
+    spawned_levels: SpawnedLevels,
+    timer: Res<LevelTimer>,
+    mut save: ResMut<SaveGame>,
+    mut completed_events: EventWriter<LevelCompleted>,
+) {
+    let collected: Vec<_> = collected_events.read().cloned().collect();
+    // The collected fruits may not be despawned yet
+    let collected_fruits: HashSet<Entity> = collected.iter().map(|event| event.fruit).collect();
+    let mut recorded = HashSet::new();
+    for event in collected {
+        let Some((bounds, level)) = spawned_levels.containing(event.position) else {
+            continue;
+        };
+        if !recorded.insert(level.iid.clone()) {
+            continue;
+        }
+
+        let total = level
+            .layer_instances
+            .iter()
+            .flatten()
+            .flat_map(|layer| &layer.entity_instances)
+            .filter(|entity| entity.identifier == FRUIT_IDENTIFIER)
+            .count() as u32;
+        let remaining = fruit_query
+            .iter()
+            .filter(|(fruit, transform)| {
+                !collected_fruits.contains(fruit)
+                    && bounds.contains(transform.translation().truncate())
+            })
+            .count() as u32;
+
+        let progress = save.levels.entry(level.iid.clone()).or_default();
+        progress.fruits_collected = progress
+            .fruits_collected
+            .max(total.saturating_sub(remaining));
+        if total == 0 || remaining > 0 {
+            continue;
+        }
+
+        info!("Level {} completed", level.identifier);
+        progress.completed = true;
+        // The time only counts when the player spent the whole visit in the level
+        let time =
+            (timer.level.as_ref() == Some(&level.iid)).then(|| timer.stopwatch.elapsed_secs());
+        if let Some(time) = time {
+            progress.best_time = Some(progress.best_time.map_or(time, |best| best.min(time)));
+            completed_events.send(LevelCompleted {
+                level: level.iid.clone(),
+                name: level.identifier.clone(),
+                time,
+            });
+        }
+
+        // Every completed level unlocks the next character
+        let completed = save.levels.values().filter(|level| level.completed).count();
+        for character in CHARACTERS.iter().take(completed + 1) {
+            if !save
+                .unlocked_characters
+                .iter()
+                .any(|unlocked| unlocked == character)
+            {
+                info!("{character} unlocked");
+                save.unlocked_characters.push(character.to_string());
+            }
+        }
+    }
+}
@@ -0,0 +1,76 @@
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+use crate::player::components::Movement;
+
+/// The version of the personal bests written by this build.
+pub const PERSONAL_BEST_VERSION: u32 = 1;
+
+/// The time of the run when a level was completed.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Split {
+    /// The iid of the level
+    pub level: String,
+    /// The identifier of the level
+    pub name: String,
+    /// The time since the start of the run in seconds
+    pub time: f32,
+}
+
+/// The player's state at a point of a run, replayed by the ghost.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+pub struct GhostFrame {
+    /// The time since the start of the run in seconds
+    pub time: f32,
+    pub translation: [f32; 2],
+    pub movement: Movement,
+    /// The index in the sprite sheet of the movement
+    pub frame: usize,
+    pub flip_x: bool,
+}
+
+/// The fastest run through every level.
+#[derive(Resource, Serialize, Deserialize, Debug, Clone, Default)]
+pub struct PersonalBest {
+    pub version: u32,
+    pub splits: Vec<Split>,
+    pub ghost: Vec<GhostFrame>,
+}
+
+impl PersonalBest {
+    /// Returns the split of the personal best for a level.
+    pub fn split(&self, level: &str) -> Option<&Split> {
+        self.splits.iter().find(|split| split.level == level)
+    }
+}
+
+/// The current run.
+#[derive(Resource, Default, Debug)]
+pub struct RunTimer {
+    pub elapsed: Duration,
+    pub splits: Vec<Split>,
+    pub ghost: Vec<GhostFrame>,
+    /// Whether every level was completed
+    pub finished: bool,
+}
+
+impl RunTimer {
+    pub fn elapsed_secs(&self) -> f32 {
+        self.elapsed.as_secs_f32()
+    }
+}
+
+/// Replays the recorded run of the personal best.
+#[derive(Component)]
+pub struct Ghost;
+
+#[derive(Component)]
+pub struct RunDisplay;
+
+#[derive(Component)]
+pub struct RunTimerDisplay;
+
+/// Shows how the last split compares to the personal best.
+#[derive(Component)]
+pub struct SplitComparisonDisplay;
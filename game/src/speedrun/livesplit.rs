@@ -0,0 +1,109 @@
+//! Exports splits as a LiveSplit splits file (`.lss`).
+
+use std::fmt::Write;
+
+use crate::speedrun::components::Split;
+
+const GAME_NAME: &str = "Awesome Game";
+const CATEGORY_NAME: &str = "All Levels";
+
+/// Formats seconds the way LiveSplit stores times, like `00:01:02.5000000`.
+fn format_time(seconds: f32) -> String {
+    let ticks = (seconds as f64 * 10_000_000.).round() as u64;
+    let (seconds, fraction) = (ticks / 10_000_000, ticks % 10_000_000);
+    format!(
+        "{:02}:{:02}:{:02}.{fraction:07}",
+        seconds / 3600,
+        seconds / 60 % 60,
+        seconds % 60
+    )
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&apos;")
+}
+
+/// Returns a LiveSplit run with the splits as its personal best.
+pub fn to_lss(splits: &[Split]) -> String {
+    let mut lss = String::new();
+    let _ = writeln!(lss, r#"<?xml version="1.0" encoding="UTF-8"?>"#);
+    let _ = writeln!(lss, r#"<Run version="1.7.0">"#);
+    let _ = writeln!(lss, "  <GameIcon />");
+    let _ = writeln!(lss, "  <GameName>{}</GameName>", escape(GAME_NAME));
+    let _ = writeln!(
+        lss,
+        "  <CategoryName>{}</CategoryName>",
+        escape(CATEGORY_NAME)
+    );
+    let _ = writeln!(lss, "  <Offset>00:00:00</Offset>");
+    let _ = writeln!(lss, "  <AttemptCount>0</AttemptCount>");
+    let _ = writeln!(lss, "  <AttemptHistory />");
+    let _ = writeln!(lss, "  <Segments>");
+    let mut previous = 0.;
+    for split in splits {
+        let _ = writeln!(lss, "    <Segment>");
+        let _ = writeln!(lss, "      <Name>{}</Name>", escape(&split.name));
+        let _ = writeln!(lss, "      <Icon />");
+        let _ = writeln!(lss, "      <SplitTimes>");
+        let _ = writeln!(lss, r#"        <SplitTime name="Personal Best">"#);
+        let _ = writeln!(
+            lss,
+            "          <RealTime>{}</RealTime>",
+            format_time(split.time)
+        );
+        let _ = writeln!(lss, "        </SplitTime>");
+        let _ = writeln!(lss, "      </SplitTimes>");
+        let _ = writeln!(lss, "      <BestSegmentTime>");
+        let _ = writeln!(
+            lss,
+            "        <RealTime>{}</RealTime>",
+            format_time(split.time - previous)
+        );
+        let _ = writeln!(lss, "      </BestSegmentTime>");
+        let _ = writeln!(lss, "      <SegmentHistory />");
+        let _ = writeln!(lss, "    </Segment>");
+        previous = split.time;
+    }
+    let _ = writeln!(lss, "  </Segments>");
+    let _ = writeln!(lss, "  <AutoSplitterSettings />");
+    let _ = writeln!(lss, "</Run>");
+    lss
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn formats_times_like_livesplit() {
+        assert_eq!(format_time(0.), "00:00:00.0000000");
+        assert_eq!(format_time(62.5), "00:01:02.5000000");
+        assert_eq!(format_time(3725.25), "01:02:05.2500000");
+    }
+
+    #[test]
+    fn exports_a_segment_per_split() {
+        let splits = [
+            Split {
+                level: "a".to_string(),
+                name: "Level_0".to_string(),
+                time: 10.,
+            },
+            Split {
+                level: "b".to_string(),
+                name: "Caves & <Cliffs>".to_string(),
+                time: 25.5,
+            },
+        ];
+        let lss = to_lss(&splits);
+        assert_eq!(lss.matches("<Segment>").count(), 2);
+        assert!(lss.contains("<Name>Caves &amp; &lt;Cliffs&gt;</Name>"));
+        assert!(lss.contains("<RealTime>00:00:25.5000000</RealTime>"));
+        // The best segment of the second level is the time since the first split
+        assert!(lss.contains("<RealTime>00:00:15.5000000</RealTime>"));
+    }
+}
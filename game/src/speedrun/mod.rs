@@ -0,0 +1,51 @@
+pub mod components;
+pub mod livesplit;
+pub mod systems;
+
+use bevy::prelude::*;
+
+use crate::camera::systems::player_spawned;
+use crate::save::systems::record_fruits;
+use crate::speedrun::components::RunTimer;
+use crate::{AppState, GameState};
+
+/// The name of the file the personal best is stored in.
+pub const PERSONAL_BEST_FILE: &str = "speedrun.json";
+/// The name of the file the personal best splits are exported to for LiveSplit.
+pub const SPLITS_FILE: &str = "speedrun.lss";
+/// How often the player's position is recorded for the ghost.
+pub const GHOST_SAMPLE_SECONDS: f32 = 1. / 30.;
+pub const GHOST_ALPHA: f32 = 0.4;
+
+/// Times runs through every level and races the ghost of the personal best.
+pub struct SpeedrunPlugin;
+
+impl Plugin for SpeedrunPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<RunTimer>()
+            .add_systems(Startup, systems::load_personal_best)
+            .add_systems(OnEnter(AppState::InGame), systems::start_run)
+            .add_systems(OnExit(AppState::InGame), systems::despawn)
+            .add_systems(
+                Update,
+                (
+                    systems::spawn_ghost.run_if(player_spawned),
+                    systems::record_splits.after(record_fruits),
+                    systems::update_run_display,
+                )
+                    .run_if(in_state(AppState::InGame)),
+            )
+            .add_systems(
+                Update,
+                // The timer stops while the game is paused
+                (
+                    systems::tick_run_timer,
+                    systems::record_ghost,
+                    systems::replay_ghost,
+                )
+                    .chain()
+                    .run_if(in_state(AppState::InGame))
+                    .run_if(in_state(GameState::Running)),
+            );
+    }
+}
@@ -0,0 +1,332 @@
+use bevy::prelude::*;
+use bevy_ecs_ldtk::prelude::*;
+
+use crate::color;
+use crate::fruit::FRUIT_IDENTIFIER;
+use crate::player::components::{Character, Movement, Player, PlayerId};
+use crate::replay::components::ReplayRecorder;
+use crate::replay::MAX_REPLAY_FRAMES;
+use crate::save::components::LevelCompleted;
+use crate::save::storage;
+use crate::speedrun::components::{
+    Ghost, GhostFrame, PersonalBest, RunDisplay, RunTimer, RunTimerDisplay, Split,
+    SplitComparisonDisplay, PERSONAL_BEST_VERSION,
+};
+use crate::speedrun::{
+    livesplit, GHOST_ALPHA, GHOST_SAMPLE_SECONDS, PERSONAL_BEST_FILE, SPLITS_FILE,
+};
+use crate::Tilesets;
+
+pub fn load_personal_best(mut commands: Commands) {
+    let personal_best = match storage::read(PERSONAL_BEST_FILE) {
+        Ok(Some(text)) => match serde_json::from_str::<PersonalBest>(&text) {
+            Ok(personal_best) if personal_best.version == PERSONAL_BEST_VERSION => personal_best,
+            Ok(personal_best) => {
+                warn!(
+                    "Ignoring the personal best of the unsupported version {}",
+                    personal_best.version
+                );
+                PersonalBest::default()
+            }
+            Err(error) => {
+                warn!("Ignoring the invalid personal best: {error}");
+                PersonalBest::default()
+            }
+        },
+        Ok(None) => PersonalBest::default(),
+        Err(error) => {
+            warn!("Could not read the personal best: {error}");
+            PersonalBest::default()
+        }
+    };
+    commands.insert_resource(personal_best);
+}
+
+pub fn start_run(mut commands: Commands) {
+    commands.insert_resource(RunTimer::default());
+    commands
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    flex_direction: FlexDirection::Column,
+                    align_items: AlignItems::End,
+                    top: Val::Px(10.0),
+                    right: Val::Px(10.0),
+                    row_gap: Val::Px(4.0),
+                    ..default()
+                },
+                ..default()
+            },
+            RunDisplay,
+        ))
+        .with_children(|parent| {
+            parent.spawn((
+                TextBundle::from_section(
+                    String::new(),
+                    TextStyle {
+                        font_size: 32.0,
+                        color: color::PRIMARY_CONTENT,
+                        ..default()
+                    },
+                ),
+                RunTimerDisplay,
+            ));
+            parent.spawn((
+                TextBundle {
+                    text: Text::from_section(
+                        String::new(),
+                        TextStyle {
+                            font_size: 24.0,
+                            color: color::PRIMARY_CONTENT,
+                            ..default()
+                        },
+                    ),
+                    visibility: Visibility::Hidden,
+                    ..default()
+                },
+                SplitComparisonDisplay,
+            ));
+        });
+}
+
+type SpeedrunEntities = Or<(With<RunDisplay>, With<Ghost>)>;
+
+pub fn despawn(mut commands: Commands, query: Query<Entity, SpeedrunEntities>) {
+    for entity in query.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+}
+
+pub fn tick_run_timer(mut run_timer: ResMut<RunTimer>, time: Res<Time>) {
+    if !run_timer.finished {
+        run_timer.elapsed += time.delta();
+    }
+}
+
+/// Samples the first player's position and animation for the ghost of the run
+/// from the recording of the replay.
+pub fn record_ghost(mut run_timer: ResMut<RunTimer>, recorder: Res<ReplayRecorder>) {
+    let Some(recorded) = recorder.last_frame else {
+        return;
+    };
+    let time = run_timer.elapsed_secs();
+    let sampled = run_timer
+        .ghost
+        .last()
+        .is_some_and(|frame| time - frame.time < GHOST_SAMPLE_SECONDS);
+    let full = run_timer.ghost.len() >= MAX_REPLAY_FRAMES;
+    if run_timer.finished || sampled || full {
+        return;
+    }
+    run_timer.ghost.push(GhostFrame {
+        time,
+        translation: recorded.translation,
+        movement: recorded.movement,
+        frame: recorded.frame,
+        flip_x: recorded.flip_x,
+    });
+}
+
+/// Spawns the ghost of the personal best next to the player.
+pub fn spawn_ghost(
+    mut commands: Commands,
+    player_query: Query<(&TextureAtlas, &PlayerId), With<Player>>,
+    ghost_query: Query<(), With<Ghost>>,
+    personal_best: Res<PersonalBest>,
+    tileset: Res<Tilesets<(Character, Movement)>>,
+) {
+    let Some((atlas, _)) = player_query.iter().find(|(_, id)| **id == PlayerId::One) else {
+        return;
+    };
+    if personal_best.ghost.is_empty() || !ghost_query.is_empty() {
+        return;
+    }
+    commands.spawn((
+        SpriteBundle {
+            sprite: Sprite {
+                color: Color::WHITE.with_alpha(GHOST_ALPHA),
+                ..default()
+            },
+            texture: tileset
+                .get(&(Character::default(), Movement::Idle))
+                .cloned()
+                .unwrap_or_default(),
+            visibility: Visibility::Hidden,
+            ..default()
+        },
+        TextureAtlas {
+            layout: atlas.layout.clone(),
+            index: 0,
+        },
+        Ghost,
+    ));
+}
+
+type GhostRelated<'a> = (
+    &'a mut Transform,
+    &'a mut Handle<Image>,
+    &'a mut TextureAtlas,
+    &'a mut Sprite,
+    &'a mut Visibility,
+);
+
+/// Moves the ghost to where the player was at the same time of the personal best.
+pub fn replay_ghost(
+    mut ghost_query: Query<GhostRelated, With<Ghost>>,
+    player_query: Query<(&GlobalTransform, &PlayerId), With<Player>>,
+    personal_best: Res<PersonalBest>,
+    run_timer: Res<RunTimer>,
+    tileset: Res<Tilesets<(Character, Movement)>>,
+) {
+    let Ok((mut transform, mut texture, mut atlas, mut sprite, mut visibility)) =
+        ghost_query.get_single_mut()
+    else {
+        return;
+    };
+    let frames = &personal_best.ghost;
+    let time = run_timer.elapsed_secs();
+    let next = frames.partition_point(|frame| frame.time <= time);
+    if next == 0 || next == frames.len() {
+        // The personal best ended
+        *visibility = Visibility::Hidden;
+        return;
+    }
+    let (frame, next_frame) = (frames[next - 1], frames[next]);
+    let progress = (time - frame.time) / (next_frame.time - frame.time);
+    let translation =
+        Vec2::from(frame.translation).lerp(Vec2::from(next_frame.translation), progress);
+    // The ghost is drawn just behind the player
+    let z = player_query
+        .iter()
+        .find(|(_, id)| **id == PlayerId::One)
+        .map_or(transform.translation.z, |(player, _)| {
+            player.translation().z - 0.01
+        });
+    transform.translation = translation.extend(z);
+    if let Some(handle) = tileset.get(&(Character::default(), frame.movement)) {
+        if *texture != *handle {
+            *texture = handle.clone();
+        }
+    }
+    atlas.index = frame.frame;
+    sprite.flip_x = frame.flip_x;
+    *visibility = Visibility::Inherited;
+}
+
+/// Returns the number of levels that can be completed, so a run is finished once they all are.
+fn completable_levels(ldtk_project: &LdtkProject) -> usize {
+    ldtk_project
+        .iter_raw_levels()
+        .filter(|level| {
+            level
+                .layer_instances
+                .iter()
+                .flatten()
+                .flat_map(|layer| &layer.entity_instances)
+                .any(|entity| entity.identifier == FRUIT_IDENTIFIER)
+        })
+        .count()
+}
+
+/// Splits when a level is completed and saves the run when it is a new personal best.
+pub fn record_splits(
+    mut completed_events: EventReader<LevelCompleted>,
+    mut run_timer: ResMut<RunTimer>,
+    mut personal_best: ResMut<PersonalBest>,
+    ldtk_projects: Query<&Handle<LdtkProject>>,
+    ldtk_project_assets: Res<Assets<LdtkProject>>,
+) {
+    for event in completed_events.read() {
+        if run_timer.finished
+            || run_timer
+                .splits
+                .iter()
+                .any(|split| split.level == event.level)
+        {
+            continue;
+        }
+        let split = Split {
+            level: event.level.clone(),
+            name: event.name.clone(),
+            time: run_timer.elapsed_secs(),
+        };
+        info!("Split {} at {:.2}s", split.name, split.time);
+        run_timer.splits.push(split);
+
+        let Some(levels) = ldtk_projects
+            .iter()
+            .find_map(|ldtk_handle| ldtk_project_assets.get(ldtk_handle))
+            .map(completable_levels)
+        else {
+            continue;
+        };
+        if run_timer.splits.len() < levels {
+            continue;
+        }
+        run_timer.finished = true;
+        let time = run_timer.elapsed_secs();
+        let previous = personal_best.splits.last().map(|split| split.time);
+        info!("Run finished in {time:.2}s");
+        if previous.is_some_and(|previous| previous <= time) {
+            continue;
+        }
+
+        info!("New personal best");
+        *personal_best = PersonalBest {
+            version: PERSONAL_BEST_VERSION,
+            splits: run_timer.splits.clone(),
+            ghost: run_timer.ghost.clone(),
+        };
+        let result = serde_json::to_string(&*personal_best)
+            .map_err(Into::into)
+            .and_then(|text| storage::write(PERSONAL_BEST_FILE, &text))
+            .and_then(|()| storage::write(SPLITS_FILE, &livesplit::to_lss(&personal_best.splits)));
+        if let Err(error) = result {
+            error!("Could not save the personal best: {error}");
+        }
+    }
+}
+
+/// Formats seconds like `1:02.50`.
+fn format_run_time(seconds: f32) -> String {
+    format!("{}:{:05.2}", (seconds / 60.) as u32, seconds % 60.)
+}
+
+type TimerText<'a> = &'a mut Text;
+type ComparisonText<'a> = (&'a mut Text, &'a mut Visibility);
+
+pub fn update_run_display(
+    mut timer_query: Query<TimerText, (With<RunTimerDisplay>, Without<SplitComparisonDisplay>)>,
+    mut comparison_query: Query<
+        ComparisonText,
+        (With<SplitComparisonDisplay>, Without<RunTimerDisplay>),
+    >,
+    run_timer: Res<RunTimer>,
+    personal_best: Res<PersonalBest>,
+) {
+    if let Ok(mut text) = timer_query.get_single_mut() {
+        text.sections[0].value = format_run_time(run_timer.elapsed_secs());
+    }
+    let Ok((mut text, mut visibility)) = comparison_query.get_single_mut() else {
+        return;
+    };
+    // Compares the last split to the personal best of the same level
+    let comparison = run_timer.splits.last().and_then(|split| {
+        let best = personal_best.split(&split.level)?;
+        Some((split, split.time - best.time))
+    });
+    match comparison {
+        Some((split, difference)) => {
+            let section = &mut text.sections[0];
+            section.value = format!("{} {difference:+.2}", split.name);
+            section.style.color = if difference <= 0. {
+                color::SUCCESS
+            } else {
+                color::SECONDARY
+            };
+            *visibility = Visibility::Inherited;
+        }
+        None => *visibility = Visibility::Hidden,
+    }
+}
@@ -0,0 +1,62 @@
+use bevy::prelude::*;
+
+use crate::AppState;
+
+/// How the screen is covered.
+#[derive(Default, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TransitionStyle {
+    /// Fades to black
+    #[default]
+    Fade,
+    /// Covers the screen with growing `Transition.png` tiles from one corner to the other
+    Wipe,
+}
+
+/// What changes while the screen is covered.
+#[derive(Debug, Clone, PartialEq)]
+pub enum TransitionTarget {
+    /// Switches to an app state, with the game running
+    App(AppState),
+    /// Respawns the world, so the player starts over
+    Respawn,
+}
+
+/// Covers the screen, changes the target once it is covered and reveals the screen again.
+///
+/// Transitions requested while another one is playing are ignored.
+#[derive(Event, Debug, Clone)]
+pub struct StartTransition {
+    pub target: TransitionTarget,
+    pub style: TransitionStyle,
+}
+
+/// The transition that is playing.
+#[derive(Resource, Default, Debug)]
+pub enum Transition {
+    #[default]
+    Idle,
+    Covering {
+        target: TransitionTarget,
+        style: TransitionStyle,
+        timer: Timer,
+    },
+    Holding {
+        style: TransitionStyle,
+        timer: Timer,
+    },
+    Revealing {
+        style: TransitionStyle,
+        timer: Timer,
+    },
+}
+
+/// The node covering the screen during a transition.
+#[derive(Component, Debug)]
+pub struct TransitionCover;
+
+/// A tile of the wipe.
+#[derive(Component, Debug)]
+pub struct WipeTile {
+    pub column: u32,
+    pub row: u32,
+}
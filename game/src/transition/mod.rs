@@ -0,0 +1,34 @@
+pub mod components;
+pub mod systems;
+
+use bevy::prelude::*;
+
+use crate::transition::components::{StartTransition, Transition};
+
+/// How long covering the screen takes in seconds.
+pub const COVER_SECONDS: f32 = 0.4;
+/// How long the screen stays covered after the change, so the next screen can load.
+pub const HOLD_SECONDS: f32 = 0.2;
+/// How long revealing the screen takes in seconds.
+pub const REVEAL_SECONDS: f32 = 0.4;
+
+/// The size in logical pixels of the tiles of the wipe.
+pub const WIPE_TILE_SIZE: f32 = 64.;
+
+pub struct TransitionPlugin;
+
+impl Plugin for TransitionPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<StartTransition>()
+            .init_resource::<Transition>()
+            .add_systems(
+                Update,
+                (
+                    systems::start_transition,
+                    systems::update_transition,
+                    systems::animate_transition,
+                )
+                    .chain(),
+            );
+    }
+}
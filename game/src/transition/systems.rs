@@ -0,0 +1,176 @@
+use bevy::prelude::*;
+use bevy::window::PrimaryWindow;
+use bevy_ecs_ldtk::prelude::*;
+
+use crate::transition::components::{
+    StartTransition, Transition, TransitionCover, TransitionStyle, TransitionTarget, WipeTile,
+};
+use crate::transition::{COVER_SECONDS, HOLD_SECONDS, REVEAL_SECONDS, WIPE_TILE_SIZE};
+use crate::{AppState, GameState};
+
+/// How much of the cover time the last tile of the wipe starts after the first one.
+const WIPE_SPREAD: f32 = 0.6;
+
+/// Spawns the cover of a requested transition.
+pub fn start_transition(
+    mut commands: Commands,
+    mut start_events: EventReader<StartTransition>,
+    mut transition: ResMut<Transition>,
+    window_query: Query<&Window, With<PrimaryWindow>>,
+    asset_server: Res<AssetServer>,
+) {
+    let Some(StartTransition { target, style }) = start_events.read().last().cloned() else {
+        return;
+    };
+    if !matches!(*transition, Transition::Idle) {
+        return;
+    }
+    let Ok(window) = window_query.get_single() else {
+        return;
+    };
+
+    commands
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    width: Val::Percent(100.0),
+                    height: Val::Percent(100.0),
+                    ..default()
+                },
+                background_color: Color::NONE.into(),
+                // Covers everything, including the menus
+                z_index: ZIndex::Global(i32::MAX),
+                ..default()
+            },
+            TransitionCover,
+        ))
+        .with_children(|parent| {
+            if style != TransitionStyle::Wipe {
+                return;
+            }
+            let texture = asset_server.load("Other/Transition.png");
+            let columns = (window.width() / WIPE_TILE_SIZE).ceil() as u32;
+            let rows = (window.height() / WIPE_TILE_SIZE).ceil() as u32;
+            for column in 0..columns {
+                for row in 0..rows {
+                    parent.spawn((
+                        ImageBundle {
+                            image: UiImage::new(texture.clone()),
+                            style: Style {
+                                position_type: PositionType::Absolute,
+                                width: Val::Px(0.),
+                                height: Val::Px(0.),
+                                ..default()
+                            },
+                            ..default()
+                        },
+                        WipeTile { column, row },
+                    ));
+                }
+            }
+        });
+
+    *transition = Transition::Covering {
+        target,
+        style,
+        timer: Timer::from_seconds(COVER_SECONDS, TimerMode::Once),
+    };
+}
+
+/// Advances the transition and changes the target once the screen is covered.
+pub fn update_transition(
+    mut commands: Commands,
+    mut transition: ResMut<Transition>,
+    cover_query: Query<Entity, With<TransitionCover>>,
+    world_query: Query<Entity, With<Handle<LdtkProject>>>,
+    mut app_state_next: ResMut<NextState<AppState>>,
+    mut game_state_next: ResMut<NextState<GameState>>,
+    time: Res<Time>,
+) {
+    match &mut *transition {
+        Transition::Idle => {}
+        Transition::Covering {
+            target,
+            style,
+            timer,
+        } => {
+            if !timer.tick(time.delta()).finished() {
+                return;
+            }
+            match target {
+                TransitionTarget::App(app_state) => {
+                    app_state_next.set(*app_state);
+                    game_state_next.set(GameState::Running);
+                }
+                TransitionTarget::Respawn => {
+                    for world in world_query.iter() {
+                        commands.entity(world).insert(Respawn);
+                    }
+                }
+            }
+            *transition = Transition::Holding {
+                style: *style,
+                timer: Timer::from_seconds(HOLD_SECONDS, TimerMode::Once),
+            };
+        }
+        Transition::Holding { style, timer } => {
+            if timer.tick(time.delta()).finished() {
+                *transition = Transition::Revealing {
+                    style: *style,
+                    timer: Timer::from_seconds(REVEAL_SECONDS, TimerMode::Once),
+                };
+            }
+        }
+        Transition::Revealing { timer, .. } => {
+            if timer.tick(time.delta()).finished() {
+                for cover in cover_query.iter() {
+                    commands.entity(cover).despawn_recursive();
+                }
+                *transition = Transition::Idle;
+            }
+        }
+    }
+}
+
+/// Fades the cover or grows and shrinks the tiles of the wipe.
+pub fn animate_transition(
+    transition: Res<Transition>,
+    mut cover_query: Query<&mut BackgroundColor, With<TransitionCover>>,
+    mut tile_query: Query<(&WipeTile, &mut Style)>,
+) {
+    let (style, coverage) = match &*transition {
+        Transition::Idle => return,
+        Transition::Covering { style, timer, .. } => (style, timer.fraction()),
+        Transition::Holding { style, .. } => (style, 1.),
+        Transition::Revealing { style, timer } => (style, 1. - timer.fraction()),
+    };
+    match style {
+        TransitionStyle::Fade => {
+            for mut background_color in cover_query.iter_mut() {
+                *background_color = Color::BLACK.with_alpha(coverage).into();
+            }
+        }
+        TransitionStyle::Wipe => {
+            let last_diagonal = tile_query
+                .iter()
+                .map(|(tile, _)| tile.column + tile.row)
+                .max()
+                .unwrap_or_default()
+                .max(1) as f32;
+            for (tile, mut style) in tile_query.iter_mut() {
+                // The tiles grow one diagonal after the other, starting at the top left
+                let delay = (tile.column + tile.row) as f32 / last_diagonal * WIPE_SPREAD;
+                let scale = (coverage * (1. + WIPE_SPREAD) - delay).clamp(0., 1.);
+                // Slightly larger than a tile, so neighbouring tiles overlap without gaps
+                let size = WIPE_TILE_SIZE * 1.5 * scale;
+                let center =
+                    Vec2::new(tile.column as f32 + 0.5, tile.row as f32 + 0.5) * WIPE_TILE_SIZE;
+                style.width = Val::Px(size);
+                style.height = Val::Px(size);
+                style.left = Val::Px(center.x - size / 2.);
+                style.top = Val::Px(center.y - size / 2.);
+            }
+        }
+    }
+}
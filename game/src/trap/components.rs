@@ -0,0 +1,58 @@
+use bevy::prelude::*;
+use bevy_ecs_ldtk::prelude::*;
+use bevy_rapier2d::prelude::*;
+
+use crate::animation::components::{AnimationIndices, AnimationTimer};
+use crate::lighting::components::PointLight2d;
+
+#[derive(Default, Component)]
+pub struct FireTrap;
+
+#[derive(Default, LdtkEntity, Bundle)]
+pub struct FireTrapBundle {
+    fire_trap: FireTrap,
+    #[sprite_sheet_bundle("Traps/Fire/On (16x32).png", 16, 32, 3, 1, 0, 0, 0)]
+    sprite_sheet_bundle: LdtkSpriteSheetBundle,
+    #[with(animation_indices)]
+    animation_indices: AnimationIndices,
+    #[with(animation_timer)]
+    animation_timer: AnimationTimer,
+    #[with(light)]
+    light: PointLight2d,
+    #[with(flame_collider)]
+    collider: Collider,
+    sensor: Sensor,
+    #[with(active_collision_types)]
+    active_collision_types: ActiveCollisionTypes,
+}
+
+/// The flame in the upper half of the sprite hurts.
+fn flame_collider(_: &EntityInstance) -> Collider {
+    Collider::compound(vec![(Vec2::new(0., 8.), 0., Collider::cuboid(6., 8.))])
+}
+
+fn active_collision_types(_: &EntityInstance) -> ActiveCollisionTypes {
+    ActiveCollisionTypes::default() | ActiveCollisionTypes::KINEMATIC_STATIC
+}
+
+fn animation_indices(_: &EntityInstance) -> AnimationIndices {
+    AnimationIndices { first: 0, last: 2 }
+}
+
+fn animation_timer(_: &EntityInstance) -> AnimationTimer {
+    AnimationTimer(Timer::from_seconds(0.1, TimerMode::Repeating))
+}
+
+fn light(entity_instance: &EntityInstance) -> PointLight2d {
+    PointLight2d {
+        color: Color::srgb(1., 0.6, 0.2),
+        intensity: entity_instance
+            .get_float_field("LightIntensity")
+            .copied()
+            .unwrap_or(1.5),
+        radius: entity_instance
+            .get_float_field("LightRadius")
+            .copied()
+            .unwrap_or(96.),
+    }
+}
@@ -0,0 +1,17 @@
+use bevy::prelude::{Component, Timer};
+
+#[derive(Component)]
+pub struct Hud;
+
+#[derive(Component)]
+pub struct BreathDisplay;
+
+/// A row of hearts, one per point of the player's maximum health.
+#[derive(Component)]
+pub struct HeartsDisplay;
+
+/// Announces an unlocked achievement until its timer finishes.
+#[derive(Component)]
+pub struct AchievementToast {
+    pub timer: Timer,
+}
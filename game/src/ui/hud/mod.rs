@@ -0,0 +1,25 @@
+mod components;
+mod systems;
+
+use bevy::prelude::*;
+
+use crate::AppState;
+
+pub struct HudPlugin;
+
+impl Plugin for HudPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(OnEnter(AppState::InGame), systems::spawn)
+            .add_systems(OnExit(AppState::InGame), systems::despawn)
+            .add_systems(
+                Update,
+                (
+                    systems::update_breath_display,
+                    systems::update_hearts_display,
+                    systems::show_achievement_toasts,
+                    systems::expire_achievement_toasts,
+                )
+                    .run_if(in_state(AppState::InGame)),
+            );
+    }
+}
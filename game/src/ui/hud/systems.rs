@@ -0,0 +1,186 @@
+use bevy::prelude::*;
+
+use super::components::{AchievementToast, BreathDisplay, HeartsDisplay, Hud};
+use crate::achievements::components::AchievementUnlocked;
+use crate::color;
+use crate::player::components::{Breath, Health, Player, PlayerId, Surroundings};
+
+pub fn spawn(mut commands: Commands) {
+    commands
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    flex_direction: FlexDirection::Column,
+                    top: Val::Px(10.0),
+                    left: Val::Px(10.0),
+                    row_gap: Val::Px(10.0),
+                    ..default()
+                },
+                ..default()
+            },
+            Hud {},
+        ))
+        .with_children(|parent| {
+            // Hearts
+            parent.spawn((
+                NodeBundle {
+                    style: Style {
+                        flex_direction: FlexDirection::Row,
+                        column_gap: Val::Px(6.0),
+                        ..default()
+                    },
+                    ..default()
+                },
+                HeartsDisplay,
+            ));
+
+            // Breath
+            parent.spawn((
+                TextBundle {
+                    text: Text {
+                        justify: JustifyText::Left,
+                        sections: vec![TextSection {
+                            value: String::new(),
+                            style: TextStyle {
+                                font_size: 32.0,
+                                color: color::PRIMARY_CONTENT,
+                                ..default()
+                            },
+                        }],
+                        ..default()
+                    },
+                    visibility: Visibility::Hidden,
+                    ..default()
+                },
+                BreathDisplay,
+            ));
+        });
+}
+
+const HEART_SIZE: f32 = 16.;
+/// The alpha of the hearts of lost health
+const LOST_HEART_ALPHA: f32 = 0.25;
+
+type DamagedPlayer = (With<Player>, Changed<Health>);
+
+/// Shows a heart per point of the first player's health, the lost ones dimmed.
+pub fn update_hearts_display(
+    mut commands: Commands,
+    hearts_display_query: Query<Entity, With<HeartsDisplay>>,
+    player_query: Query<(&Health, &PlayerId), DamagedPlayer>,
+) {
+    let Ok(hearts_display) = hearts_display_query.get_single() else {
+        return;
+    };
+    let Some((health, _)) = player_query.iter().find(|(_, id)| **id == PlayerId::One) else {
+        return;
+    };
+    commands
+        .entity(hearts_display)
+        .despawn_descendants()
+        .with_children(|parent| {
+            for heart in 0..health.max {
+                let color = if heart < health.current {
+                    color::SECONDARY
+                } else {
+                    color::SECONDARY.with_alpha(LOST_HEART_ALPHA)
+                };
+                parent.spawn(NodeBundle {
+                    style: Style {
+                        width: Val::Px(HEART_SIZE),
+                        height: Val::Px(HEART_SIZE),
+                        ..default()
+                    },
+                    background_color: color.into(),
+                    ..default()
+                });
+            }
+        });
+}
+
+/// How long an unlocked achievement is shown.
+const TOAST_SECONDS: f32 = 3.;
+
+pub fn show_achievement_toasts(
+    mut commands: Commands,
+    mut unlocked_events: EventReader<AchievementUnlocked>,
+    hud_query: Query<Entity, With<Hud>>,
+) {
+    let Ok(hud_entity) = hud_query.get_single() else {
+        return;
+    };
+    for unlocked in unlocked_events.read() {
+        let toast = commands
+            .spawn((
+                NodeBundle {
+                    style: Style {
+                        flex_direction: FlexDirection::Column,
+                        padding: UiRect::all(Val::Px(8.0)),
+                        ..default()
+                    },
+                    background_color: color::PRIMARY.into(),
+                    ..default()
+                },
+                AchievementToast {
+                    timer: Timer::from_seconds(TOAST_SECONDS, TimerMode::Once),
+                },
+            ))
+            .with_children(|parent| {
+                parent.spawn(TextBundle::from_sections([
+                    TextSection::new(
+                        format!("Achievement unlocked: {}\n", unlocked.name),
+                        TextStyle {
+                            font_size: 24.0,
+                            color: color::PRIMARY_CONTENT,
+                            ..default()
+                        },
+                    ),
+                    TextSection::new(
+                        unlocked.description.clone(),
+                        TextStyle {
+                            font_size: 18.0,
+                            color: color::PRIMARY_CONTENT,
+                            ..default()
+                        },
+                    ),
+                ]));
+            })
+            .id();
+        commands.entity(hud_entity).add_child(toast);
+    }
+}
+
+pub fn expire_achievement_toasts(
+    mut commands: Commands,
+    mut toast_query: Query<(Entity, &mut AchievementToast)>,
+    time: Res<Time>,
+) {
+    for (toast_entity, mut toast) in toast_query.iter_mut() {
+        if toast.timer.tick(time.delta()).finished() {
+            commands.entity(toast_entity).despawn_recursive();
+        }
+    }
+}
+
+pub fn despawn(mut commands: Commands, hud_query: Query<Entity, With<Hud>>) {
+    if let Ok(hud_entity) = hud_query.get_single() {
+        commands.entity(hud_entity).despawn_recursive();
+    }
+}
+
+/// Shows the remaining breath while the player is in water.
+pub fn update_breath_display(
+    mut breath_display_query: Query<(&mut Text, &mut Visibility), With<BreathDisplay>>,
+    player_query: Query<(&Breath, &Surroundings, &PlayerId), With<Player>>,
+) {
+    if let Ok((mut text, mut visibility)) = breath_display_query.get_single_mut() {
+        match player_query.iter().find(|(.., id)| **id == PlayerId::One) {
+            Some((breath, surroundings, _)) if surroundings.in_water => {
+                text.sections[0].value = format!("Breath: {:.1}s", breath.remaining_secs());
+                *visibility = Visibility::Inherited;
+            }
+            _ => *visibility = Visibility::Hidden,
+        }
+    }
+}
@@ -1,4 +1,6 @@
+pub mod hud;
 pub mod pause_menu;
+pub mod touch_controls;
 
 use bevy::prelude::*;
 
@@ -6,6 +8,8 @@ pub struct UiPlugin;
 
 impl Plugin for UiPlugin {
     fn build(&self, app: &mut App) {
-        app.add_plugins(pause_menu::PauseMenuPlugin);
+        app.add_plugins(pause_menu::PauseMenuPlugin)
+            .add_plugins(hud::HudPlugin)
+            .add_plugins(touch_controls::TouchControlsPlugin);
     }
 }
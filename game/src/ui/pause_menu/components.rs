@@ -6,6 +6,12 @@ pub struct PauseMenu;
 #[derive(Component)]
 pub struct ResumeButton;
 
+#[derive(Component)]
+pub struct QuicksaveButton;
+
+#[derive(Component)]
+pub struct QuickloadButton;
+
 #[derive(Component)]
 pub struct MainMenuButton;
 
@@ -23,6 +23,8 @@ impl Plugin for PauseMenuPlugin {
             (
                 systems::interact_with_main_menu_button,
                 systems::interact_with_resume_button,
+                systems::interact_with_quicksave_button,
+                systems::interact_with_quickload_button,
                 systems::interact_with_quit_button,
             )
                 .run_if(in_state(AppState::InGame))
@@ -1,6 +1,11 @@
 use bevy::prelude::*;
 
-use super::components::{MainMenuButton, PauseMenu, QuitButton, ResumeButton};
+use super::components::{
+    MainMenuButton, PauseMenu, QuickloadButton, QuicksaveButton, QuitButton, ResumeButton,
+};
+use crate::input::components::ButtonPrompt;
+use crate::quicksave::components::{Quickload, Quicksave};
+use crate::transition::components::{StartTransition, TransitionStyle, TransitionTarget};
 use crate::{color, AppState, GameState};
 
 pub fn spawn(mut commands: Commands, assets_server: Res<AssetServer>) {
@@ -88,6 +93,74 @@ pub fn spawn(mut commands: Commands, assets_server: Res<AssetServer>) {
                     });
                 });
 
+            // Quicksave Button
+            parent
+                .spawn((
+                    ButtonBundle {
+                        background_color: color::PRIMARY.into(),
+                        style: Style {
+                            justify_content: JustifyContent::Center,
+                            align_items: AlignItems::Center,
+                            width: Val::Px(200.0),
+                            height: Val::Px(80.0),
+                            ..default()
+                        },
+                        ..default()
+                    },
+                    QuicksaveButton,
+                ))
+                .with_children(|parent| {
+                    parent.spawn(TextBundle {
+                        text: Text {
+                            justify: JustifyText::Center,
+                            sections: vec![TextSection {
+                                value: "Quicksave".to_string(),
+                                style: TextStyle {
+                                    font_size: 32.0,
+                                    color: color::PRIMARY_CONTENT,
+                                    ..default()
+                                },
+                            }],
+                            ..default()
+                        },
+                        ..default()
+                    });
+                });
+
+            // Quickload Button
+            parent
+                .spawn((
+                    ButtonBundle {
+                        background_color: color::PRIMARY.into(),
+                        style: Style {
+                            justify_content: JustifyContent::Center,
+                            align_items: AlignItems::Center,
+                            width: Val::Px(200.0),
+                            height: Val::Px(80.0),
+                            ..default()
+                        },
+                        ..default()
+                    },
+                    QuickloadButton,
+                ))
+                .with_children(|parent| {
+                    parent.spawn(TextBundle {
+                        text: Text {
+                            justify: JustifyText::Center,
+                            sections: vec![TextSection {
+                                value: "Quickload".to_string(),
+                                style: TextStyle {
+                                    font_size: 32.0,
+                                    color: color::PRIMARY_CONTENT,
+                                    ..default()
+                                },
+                            }],
+                            ..default()
+                        },
+                        ..default()
+                    });
+                });
+
             // MainMenu Button
             parent
                 .spawn((
@@ -156,6 +229,19 @@ pub fn spawn(mut commands: Commands, assets_server: Res<AssetServer>) {
                         ..default()
                     });
                 });
+
+            // Controls of the device in use
+            parent.spawn((
+                TextBundle::from_section(
+                    String::new(),
+                    TextStyle {
+                        font_size: 20.0,
+                        color: color::PRIMARY_CONTENT,
+                        ..default()
+                    },
+                ),
+                ButtonPrompt,
+            ));
         });
 }
 pub fn despawn(mut commands: Commands, pause_menu_query: Query<Entity, With<PauseMenu>>) {
@@ -186,6 +272,48 @@ pub fn interact_with_resume_button(
     }
 }
 
+pub fn interact_with_quicksave_button(
+    mut button_query: Query<ColorForInteraction, (Changed<Interaction>, With<QuicksaveButton>)>,
+    mut quicksave_events: EventWriter<Quicksave>,
+) {
+    if let Ok((interaction, mut background_color)) = button_query.get_single_mut() {
+        match interaction {
+            Interaction::None => {
+                *background_color = color::PRIMARY.into();
+            }
+            Interaction::Pressed => {
+                *background_color = color::PRIMARY.into();
+                quicksave_events.send(Quicksave);
+            }
+            Interaction::Hovered => {
+                *background_color = color::PRIMARY_HOVER.into();
+            }
+        }
+    }
+}
+
+pub fn interact_with_quickload_button(
+    mut button_query: Query<ColorForInteraction, (Changed<Interaction>, With<QuickloadButton>)>,
+    mut quickload_events: EventWriter<Quickload>,
+    mut game_state_next: ResMut<NextState<GameState>>,
+) {
+    if let Ok((interaction, mut background_color)) = button_query.get_single_mut() {
+        match interaction {
+            Interaction::None => {
+                *background_color = color::PRIMARY.into();
+            }
+            Interaction::Pressed => {
+                *background_color = color::PRIMARY.into();
+                quickload_events.send(Quickload);
+                game_state_next.set(GameState::Running);
+            }
+            Interaction::Hovered => {
+                *background_color = color::PRIMARY_HOVER.into();
+            }
+        }
+    }
+}
+
 pub fn interact_with_quit_button(
     mut app_exit_event_writer: EventWriter<AppExit>,
     mut button_query: Query<ColorForInteraction, (Changed<Interaction>, With<QuitButton>)>,
@@ -208,8 +336,7 @@ pub fn interact_with_quit_button(
 
 pub fn interact_with_main_menu_button(
     mut button_query: Query<ColorForInteraction, (Changed<Interaction>, With<MainMenuButton>)>,
-    mut app_state_next: ResMut<NextState<AppState>>,
-    mut game_state_next: ResMut<NextState<GameState>>,
+    mut transition_events: EventWriter<StartTransition>,
 ) {
     if let Ok((interaction, mut background_color)) = button_query.get_single_mut() {
         match interaction {
@@ -218,8 +345,10 @@ pub fn interact_with_main_menu_button(
             }
             Interaction::Pressed => {
                 *background_color = color::PRIMARY.into();
-                app_state_next.set(AppState::MainMenu);
-                game_state_next.set(GameState::Running);
+                transition_events.send(StartTransition {
+                    target: TransitionTarget::App(AppState::MainMenu),
+                    style: TransitionStyle::Fade,
+                });
             }
             Interaction::Hovered => {
                 *background_color = color::PRIMARY_HOVER.into();
@@ -230,10 +359,14 @@ pub fn interact_with_main_menu_button(
 
 pub fn toggle_pause_menu(
     keyboard_input: Res<ButtonInput<KeyCode>>,
+    gamepad_input: Res<ButtonInput<GamepadButton>>,
     game_state: Res<State<GameState>>,
     mut next_state: ResMut<NextState<GameState>>,
 ) {
-    if keyboard_input.any_just_pressed(vec![KeyCode::Escape]) {
+    let start_pressed = gamepad_input
+        .get_just_pressed()
+        .any(|button| button.button_type == GamepadButtonType::Start);
+    if keyboard_input.any_just_pressed(vec![KeyCode::Escape]) || start_pressed {
         match game_state.get() {
             GameState::Running => {
                 next_state.set(GameState::Paused);
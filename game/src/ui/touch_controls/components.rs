@@ -0,0 +1,100 @@
+use bevy::prelude::*;
+use bevy::utils::HashSet;
+
+use crate::ui::touch_controls::{BUTTON_SIZE, JUMP_BUTTON_SIZE, MARGIN};
+use crate::Action;
+
+/// Which side of the screen the d-pad and the jump button are on.
+#[derive(Resource, Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum TouchLayout {
+    /// The d-pad is on the left and the jump button on the right
+    #[default]
+    RightHanded,
+    /// The d-pad is on the right and the jump button on the left
+    LeftHanded,
+}
+
+/// Where a button is, measured from the bottom corner of its side of the screen.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ButtonPlacement {
+    pub from_left: bool,
+    /// The distance from the side of the screen
+    pub offset: f32,
+    pub size: f32,
+}
+
+impl TouchLayout {
+    /// The actions with an on-screen button.
+    pub const ACTIONS: [Action; 4] = [Action::Left, Action::Fall, Action::Right, Action::Jump];
+
+    pub fn placement(&self, action: Action) -> Option<ButtonPlacement> {
+        let dpad_from_left = *self == TouchLayout::RightHanded;
+        // The d-pad reads left to right on both sides of the screen
+        let dpad_slot = match action {
+            Action::Left => 0.,
+            Action::Fall => 1.,
+            Action::Right => 2.,
+            Action::Jump => {
+                return Some(ButtonPlacement {
+                    from_left: !dpad_from_left,
+                    offset: MARGIN,
+                    size: JUMP_BUTTON_SIZE,
+                })
+            }
+            Action::Move => return None,
+        };
+        let slot = if dpad_from_left {
+            dpad_slot
+        } else {
+            2. - dpad_slot
+        };
+        Some(ButtonPlacement {
+            from_left: dpad_from_left,
+            offset: MARGIN + slot * BUTTON_SIZE,
+            size: BUTTON_SIZE,
+        })
+    }
+}
+
+impl ButtonPlacement {
+    pub fn style(&self) -> Style {
+        let offset = Val::Px(self.offset);
+        Style {
+            position_type: PositionType::Absolute,
+            justify_content: JustifyContent::Center,
+            align_items: AlignItems::Center,
+            left: if self.from_left { offset } else { Val::Auto },
+            right: if self.from_left { Val::Auto } else { offset },
+            bottom: Val::Px(MARGIN),
+            width: Val::Px(self.size),
+            height: Val::Px(self.size),
+            ..default()
+        }
+    }
+
+    /// The area of the button in logical window coordinates, with the origin at the top left.
+    pub fn rect(&self, window_size: Vec2) -> Rect {
+        let x = if self.from_left {
+            self.offset
+        } else {
+            window_size.x - self.offset - self.size
+        };
+        let y = window_size.y - MARGIN - self.size;
+        Rect::new(x, y, x + self.size, y + self.size)
+    }
+}
+
+/// The on-screen controls, shown while the player is using a touch screen.
+#[derive(Component)]
+pub struct TouchOverlay;
+
+#[derive(Component)]
+pub struct TouchButton(pub Action);
+
+/// The actions whose buttons are touched.
+#[derive(Resource, Debug, Default)]
+pub struct TouchedActions {
+    pub pressed: HashSet<Action>,
+    /// The actions whose button was touched in this frame
+    pub just_pressed: HashSet<Action>,
+}
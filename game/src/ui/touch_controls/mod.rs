@@ -0,0 +1,42 @@
+pub mod components;
+mod systems;
+
+use bevy::prelude::*;
+
+use crate::ui::touch_controls::components::{TouchLayout, TouchedActions};
+use crate::{player, AppState, GameState};
+
+pub const BUTTON_SIZE: f32 = 72.0;
+pub const JUMP_BUTTON_SIZE: f32 = 96.0;
+/// The distance between the buttons and the edges of the screen
+pub const MARGIN: f32 = 24.0;
+
+pub struct TouchControlsPlugin;
+
+impl Plugin for TouchControlsPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<TouchLayout>()
+            .init_resource::<TouchedActions>()
+            .add_systems(OnEnter(AppState::InGame), systems::spawn)
+            .add_systems(OnExit(AppState::InGame), systems::despawn)
+            .add_systems(
+                Update,
+                (
+                    systems::detect_touched_buttons,
+                    systems::press_touched_actions.run_if(in_state(GameState::Running)),
+                )
+                    .chain()
+                    .before(player::systems::move_player)
+                    .run_if(in_state(AppState::InGame)),
+            )
+            .add_systems(
+                Update,
+                (
+                    systems::highlight_touched_buttons,
+                    systems::show_overlay,
+                    systems::update_layout.run_if(resource_changed::<TouchLayout>),
+                )
+                    .run_if(in_state(AppState::InGame)),
+            );
+    }
+}
@@ -0,0 +1,301 @@
+use bevy::prelude::*;
+use bevy::window::PrimaryWindow;
+use leafwing_input_manager::prelude::*;
+
+use crate::input::components::InputDevice;
+use crate::player::components::{Player, PlayerId};
+use crate::ui::touch_controls::components::{
+    TouchButton, TouchLayout, TouchOverlay, TouchedActions,
+};
+use crate::{color, Action};
+
+pub fn spawn(mut commands: Commands, layout: Res<TouchLayout>, device: Res<InputDevice>) {
+    let visibility = if *device == InputDevice::Touch {
+        Visibility::Inherited
+    } else {
+        Visibility::Hidden
+    };
+    commands
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    width: Val::Percent(100.0),
+                    height: Val::Percent(100.0),
+                    ..default()
+                },
+                visibility,
+                ..default()
+            },
+            TouchOverlay,
+        ))
+        .with_children(|parent| {
+            for action in TouchLayout::ACTIONS {
+                let Some(placement) = layout.placement(action) else {
+                    continue;
+                };
+                let label = match action {
+                    Action::Left => "<",
+                    Action::Right => ">",
+                    Action::Fall => "v",
+                    _ => "Jump",
+                };
+                parent
+                    .spawn((
+                        NodeBundle {
+                            style: placement.style(),
+                            background_color: color::PRIMARY.with_alpha(0.5).into(),
+                            ..default()
+                        },
+                        TouchButton(action),
+                    ))
+                    .with_children(|parent| {
+                        parent.spawn(TextBundle::from_section(
+                            label,
+                            TextStyle {
+                                font_size: 32.0,
+                                color: color::PRIMARY_CONTENT,
+                                ..default()
+                            },
+                        ));
+                    });
+            }
+        });
+}
+
+pub fn despawn(mut commands: Commands, overlay_query: Query<Entity, With<TouchOverlay>>) {
+    if let Ok(overlay_entity) = overlay_query.get_single() {
+        commands.entity(overlay_entity).despawn_recursive();
+    }
+}
+
+/// Hit-tests every touch against the buttons of the layout.
+pub fn detect_touched_buttons(
+    touches: Res<Touches>,
+    window_query: Query<&Window, With<PrimaryWindow>>,
+    layout: Res<TouchLayout>,
+    mut touched: ResMut<TouchedActions>,
+) {
+    let Ok(window) = window_query.get_single() else {
+        return;
+    };
+    let window_size = window.size();
+    touched.pressed.clear();
+    touched.just_pressed.clear();
+    for action in TouchLayout::ACTIONS {
+        let Some(rect) = layout
+            .placement(action)
+            .map(|placement| placement.rect(window_size))
+        else {
+            continue;
+        };
+        if touches.iter().any(|touch| rect.contains(touch.position())) {
+            touched.pressed.insert(action);
+        }
+        // A finger sliding onto a button presses it too
+        if touches.iter().any(|touch| {
+            rect.contains(touch.position()) && !rect.contains(touch.previous_position())
+        }) || touches
+            .iter_just_pressed()
+            .any(|touch| rect.contains(touch.position()))
+        {
+            touched.just_pressed.insert(action);
+        }
+    }
+}
+
+pub fn press_touched_actions(
+    touched: Res<TouchedActions>,
+    mut action_state_query: Query<(&mut ActionState<Action>, &PlayerId), With<Player>>,
+) {
+    // The touch screen controls the first player
+    let Some((mut action_state, _)) = action_state_query
+        .iter_mut()
+        .find(|(_, id)| **id == PlayerId::One)
+    else {
+        return;
+    };
+    for action in touched.pressed.iter() {
+        // The inputs release the action every frame, so holding the button would jump again
+        if *action == Action::Jump && !touched.just_pressed.contains(action) {
+            continue;
+        }
+        action_state.press(action);
+    }
+}
+
+pub fn highlight_touched_buttons(
+    touched: Res<TouchedActions>,
+    mut button_query: Query<(&TouchButton, &mut BackgroundColor)>,
+) {
+    for (button, mut background_color) in button_query.iter_mut() {
+        let color = if touched.pressed.contains(&button.0) {
+            color::PRIMARY_HOVER
+        } else {
+            color::PRIMARY
+        };
+        *background_color = color.with_alpha(0.5).into();
+    }
+}
+
+/// Shows the overlay only while the player is using a touch screen.
+pub fn show_overlay(
+    device: Res<InputDevice>,
+    mut overlay_query: Query<&mut Visibility, With<TouchOverlay>>,
+) {
+    if let Ok(mut visibility) = overlay_query.get_single_mut() {
+        visibility.set_if_neq(if *device == InputDevice::Touch {
+            Visibility::Inherited
+        } else {
+            Visibility::Hidden
+        });
+    }
+}
+
+pub fn update_layout(
+    layout: Res<TouchLayout>,
+    mut button_query: Query<(&TouchButton, &mut Style)>,
+) {
+    for (button, mut style) in button_query.iter_mut() {
+        if let Some(placement) = layout.placement(button.0) {
+            *style = placement.style();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bevy::input::touch::{TouchInput, TouchPhase};
+    use bevy::input::InputPlugin;
+
+    fn app(layout: TouchLayout) -> (App, Entity) {
+        let mut app = App::new();
+        app.add_plugins((MinimalPlugins, InputPlugin))
+            .insert_resource(layout)
+            .init_resource::<TouchedActions>()
+            .add_systems(Update, detect_touched_buttons);
+        let window = app
+            .world_mut()
+            .spawn((Window::default(), PrimaryWindow))
+            .id();
+        (app, window)
+    }
+
+    fn touch(app: &mut App, window: Entity, id: u64, phase: TouchPhase, position: Vec2) {
+        app.world_mut().send_event(TouchInput {
+            phase,
+            position,
+            window,
+            force: None,
+            id,
+        });
+    }
+
+    /// The center of the button of the action in the default window.
+    fn center(layout: TouchLayout, action: Action) -> Vec2 {
+        let size = Window::default().size();
+        layout.placement(action).unwrap().rect(size).center()
+    }
+
+    #[test]
+    fn presses_every_touched_button() {
+        let layout = TouchLayout::RightHanded;
+        let (mut app, window) = app(layout);
+        touch(
+            &mut app,
+            window,
+            0,
+            TouchPhase::Started,
+            center(layout, Action::Left),
+        );
+        touch(
+            &mut app,
+            window,
+            1,
+            TouchPhase::Started,
+            center(layout, Action::Jump),
+        );
+        app.update();
+        let touched = app.world().resource::<TouchedActions>();
+        assert_eq!(touched.pressed.len(), 2);
+        assert!(touched.pressed.contains(&Action::Left));
+        assert!(touched.just_pressed.contains(&Action::Jump));
+
+        // Holding the buttons keeps them pressed
+        app.update();
+        let touched = app.world().resource::<TouchedActions>();
+        assert!(touched.pressed.contains(&Action::Jump));
+        assert!(touched.just_pressed.is_empty());
+
+        touch(
+            &mut app,
+            window,
+            1,
+            TouchPhase::Ended,
+            center(layout, Action::Jump),
+        );
+        app.update();
+        let touched = app.world().resource::<TouchedActions>();
+        assert!(touched.pressed.contains(&Action::Left));
+        assert!(!touched.pressed.contains(&Action::Jump));
+    }
+
+    #[test]
+    fn sliding_onto_a_button_presses_it() {
+        let layout = TouchLayout::RightHanded;
+        let (mut app, window) = app(layout);
+        touch(
+            &mut app,
+            window,
+            0,
+            TouchPhase::Started,
+            center(layout, Action::Left),
+        );
+        app.update();
+        touch(
+            &mut app,
+            window,
+            0,
+            TouchPhase::Moved,
+            center(layout, Action::Right),
+        );
+        app.update();
+        let touched = app.world().resource::<TouchedActions>();
+        assert!(touched.pressed.contains(&Action::Right));
+        assert!(!touched.pressed.contains(&Action::Left));
+        assert!(touched.just_pressed.contains(&Action::Right));
+    }
+
+    #[test]
+    fn ignores_touches_outside_the_buttons() {
+        let (mut app, window) = app(TouchLayout::RightHanded);
+        touch(
+            &mut app,
+            window,
+            0,
+            TouchPhase::Started,
+            Vec2::new(640., 100.),
+        );
+        app.update();
+        assert!(app.world().resource::<TouchedActions>().pressed.is_empty());
+    }
+
+    #[test]
+    fn left_handed_layout_mirrors_the_buttons() {
+        let size = Window::default().size();
+        let rect = |layout: TouchLayout, action| layout.placement(action).unwrap().rect(size);
+        for action in TouchLayout::ACTIONS {
+            // The d-pad still reads left to right, so its arrows swap places
+            let mirrored = match action {
+                Action::Left => Action::Right,
+                Action::Right => Action::Left,
+                action => action,
+            };
+            let right_handed = rect(TouchLayout::RightHanded, mirrored);
+            let left_handed = rect(TouchLayout::LeftHanded, action);
+            assert_eq!(left_handed.min.x, size.x - right_handed.max.x);
+            assert_eq!(left_handed.min.y, right_handed.min.y);
+        }
+    }
+}
@@ -1,5 +1,8 @@
 use bevy::prelude::*;
 use bevy_ecs_ldtk::prelude::*;
+use std::collections::{HashMap, HashSet};
+
+use crate::world::merge;
 
 #[derive(Copy, Clone, Eq, PartialEq, Debug, Default, Component)]
 pub struct Ground;
@@ -8,3 +11,297 @@ pub struct Ground;
 pub struct GroundBundle {
     ground: Ground,
 }
+
+/// Marks a ground cell that breaks when the player bumps their head into it.
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Default, Component)]
+pub struct Breakable;
+
+#[derive(Clone, Debug, Default, Bundle, LdtkIntCell)]
+pub struct BreakableBlockBundle {
+    ground: Ground,
+    breakable: Breakable,
+}
+
+/// The ground cells of a level and the colliders spawned for them.
+///
+/// Used to rebuild only the colliders around cells that are added or removed at runtime.
+#[derive(Clone, Debug, Default, Component)]
+pub struct GroundColliders {
+    /// The grid coordinates of every ground cell entity
+    pub cells: HashMap<Entity, GridCoords>,
+    /// The collider entities and the rectangles of cells they cover
+    pub colliders: Vec<(Entity, merge::Rect)>,
+}
+
+impl GroundColliders {
+    /// Adds and removes cells and merges the changed regions again.
+    ///
+    /// Returns the colliders that intersect the changed cells or their neighbours, which have to
+    /// be despawned, and the rectangles to spawn colliders for, which the caller pushes
+    /// to `colliders`. The other colliders are kept, as they still cover their cells.
+    pub fn rebuild(
+        &mut self,
+        added: &HashMap<Entity, GridCoords>,
+        removed: &[Entity],
+        columns: i32,
+        rows: i32,
+    ) -> (Vec<Entity>, Vec<merge::Rect>) {
+        let removed_cells: Vec<GridCoords> = removed
+            .iter()
+            .filter_map(|cell_entity| self.cells.remove(cell_entity))
+            .collect();
+        self.cells.extend(added.iter());
+
+        // The changed cells and their neighbours, so added cells merge with the adjacent ground.
+        // Overlapping regions are joined, distant changes keep the colliders between them.
+        let mut regions: Vec<merge::Rect> = Vec::new();
+        for cell in removed_cells.iter().chain(added.values()) {
+            let mut region = merge::Rect {
+                left: cell.x,
+                right: cell.x,
+                top: cell.y,
+                bottom: cell.y,
+            }
+            .grow(1);
+            while let Some(index) = regions.iter().position(|other| other.intersects(&region)) {
+                region = region.union(&regions.swap_remove(index));
+            }
+            regions.push(region);
+        }
+        if regions.is_empty() {
+            return (Vec::new(), Vec::new());
+        }
+
+        let (outdated, kept): (Vec<_>, Vec<_>) = self
+            .colliders
+            .drain(..)
+            .partition(|(_, rect)| regions.iter().any(|region| rect.intersects(region)));
+        self.colliders = kept;
+
+        // Only the cells of the outdated colliders and the added cells are merged again,
+        // the other cells are still covered by the kept colliders
+        let cells: HashSet<GridCoords> = self
+            .cells
+            .values()
+            .filter(|cell| {
+                added.values().any(|added_cell| added_cell == *cell)
+                    || outdated.iter().any(|(_, rect)| rect.contains(cell))
+            })
+            .copied()
+            .collect();
+
+        (
+            outdated.into_iter().map(|(entity, _)| entity).collect(),
+            merge::merge_cells_greedy(&cells, columns, rows),
+        )
+    }
+}
+
+/// A piece of a broken block flying off.
+#[derive(Clone, Debug, Default, Component)]
+pub struct Debris {
+    pub velocity: Vec2,
+    pub lifetime: Timer,
+}
+
+/// Marks a cell of a platform that can be jumped through from below and stood on from above.
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Default, Component)]
+pub struct OneWayPlatform;
+
+#[derive(Clone, Debug, Default, Bundle, LdtkIntCell)]
+pub struct OneWayPlatformBundle {
+    one_way_platform: OneWayPlatform,
+}
+
+/// The collider spawned for a row of connected one-way platform cells.
+#[derive(Copy, Clone, PartialEq, Debug, Default, Component)]
+pub struct OneWayPlatformCollider {
+    pub half_width: f32,
+    pub half_height: f32,
+}
+
+impl OneWayPlatformCollider {
+    /// Whether a box at the position overlaps the platform centered at the given position,
+    /// with its bottom further below the platform's surface than the tolerance.
+    pub fn overlaps(&self, center: Vec2, position: Vec2, half_size: Vec2, tolerance: f32) -> bool {
+        let distance = (position - center).abs();
+        let bottom = position.y - half_size.y;
+        distance.x < self.half_width + half_size.x
+            && distance.y < self.half_height + half_size.y
+            && bottom < center.y + self.half_height - tolerance
+    }
+}
+
+/// Marks a cell of a ladder or a vine the player can climb.
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Default, Component)]
+pub struct Ladder;
+
+#[derive(Clone, Debug, Default, Bundle, LdtkIntCell)]
+pub struct LadderBundle {
+    ladder: Ladder,
+}
+
+/// Marks a cell of water the player can swim in.
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Default, Component)]
+pub struct Water;
+
+#[derive(Clone, Debug, Default, Bundle, LdtkIntCell)]
+pub struct WaterBundle {
+    water: Water,
+}
+
+/// The sensor collider spawned for a rectangle of ladder cells.
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Default, Component)]
+pub struct LadderVolume;
+
+/// The sensor collider spawned for a rectangle of water cells.
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Default, Component)]
+pub struct WaterVolume;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Spawns a fake collider entity for every rectangle, as `add_ground_collider` does.
+    fn spawn(colliders: &mut GroundColliders, rects: Vec<merge::Rect>, next: &mut u32) {
+        for rect in rects {
+            colliders.colliders.push((Entity::from_raw(*next), rect));
+            *next += 1;
+        }
+    }
+
+    fn assert_exact_cover(colliders: &GroundColliders) {
+        let mut covered = HashSet::new();
+        for (_, rect) in &colliders.colliders {
+            for cell in rect.cells() {
+                assert!(covered.insert(cell), "{cell:?} is covered twice");
+            }
+        }
+        let cells: HashSet<GridCoords> = colliders.cells.values().copied().collect();
+        assert_eq!(covered, cells);
+    }
+
+    #[test]
+    fn breaking_a_block_only_rebuilds_the_intersecting_colliders() {
+        let mut colliders = GroundColliders::default();
+        let mut next = 1000;
+        // A 6x3 block of ground on the left and a platform far away on the right
+        let added: HashMap<Entity, GridCoords> = (0..6)
+            .flat_map(|x| (0..3).map(move |y| GridCoords::new(x, y)))
+            .chain((14..20).map(|x| GridCoords::new(x, 2)))
+            .enumerate()
+            .map(|(index, cell)| (Entity::from_raw(index as u32), cell))
+            .collect();
+        let (outdated, rects) = colliders.rebuild(&added, &[], 20, 3);
+        assert!(outdated.is_empty());
+        spawn(&mut colliders, rects, &mut next);
+        assert_eq!(colliders.colliders.len(), 2);
+        assert_exact_cover(&colliders);
+        let platform = colliders
+            .colliders
+            .iter()
+            .find(|(_, rect)| rect.left == 14)
+            .map(|(entity, _)| *entity)
+            .unwrap();
+
+        // Breaking the block in the middle of the left ground
+        let broken = added
+            .iter()
+            .find(|(_, cell)| **cell == GridCoords::new(2, 1))
+            .map(|(entity, _)| *entity)
+            .unwrap();
+        let (outdated, rects) = colliders.rebuild(&HashMap::new(), &[broken], 20, 3);
+        assert_eq!(outdated.len(), 1);
+        assert_ne!(outdated[0], platform);
+        assert!(colliders
+            .colliders
+            .iter()
+            .any(|(entity, _)| *entity == platform));
+        assert!(rects.iter().all(|rect| rect.right < 6));
+        spawn(&mut colliders, rects, &mut next);
+        assert!(!colliders
+            .cells
+            .values()
+            .any(|cell| *cell == GridCoords::new(2, 1)));
+        assert_exact_cover(&colliders);
+
+        // Putting it back merges it with its neighbours again
+        let restored = HashMap::from([(Entity::from_raw(500), GridCoords::new(2, 1))]);
+        let (outdated, rects) = colliders.rebuild(&restored, &[], 20, 3);
+        assert!(!outdated.contains(&platform));
+        spawn(&mut colliders, rects, &mut next);
+        assert_exact_cover(&colliders);
+        assert_eq!(colliders.colliders.len(), 2);
+    }
+
+    #[test]
+    fn distant_breaks_keep_the_colliders_between_them() {
+        let mut colliders = GroundColliders::default();
+        let mut next = 1000;
+        // Three separate 4x3 blocks of ground
+        let added: HashMap<Entity, GridCoords> = [0, 8, 16]
+            .into_iter()
+            .flat_map(|left| {
+                (left..left + 4).flat_map(|x| (0..3).map(move |y| GridCoords::new(x, y)))
+            })
+            .enumerate()
+            .map(|(index, cell)| (Entity::from_raw(index as u32), cell))
+            .collect();
+        let (_, rects) = colliders.rebuild(&added, &[], 20, 3);
+        spawn(&mut colliders, rects, &mut next);
+        assert_eq!(colliders.colliders.len(), 3);
+        let middle = colliders
+            .colliders
+            .iter()
+            .find(|(_, rect)| rect.left == 8)
+            .map(|(entity, _)| *entity)
+            .unwrap();
+
+        // Breaking a block in the left and the right ground in the same frame
+        let broken: Vec<Entity> = added
+            .iter()
+            .filter(|(_, cell)| **cell == GridCoords::new(1, 1) || **cell == GridCoords::new(18, 1))
+            .map(|(entity, _)| *entity)
+            .collect();
+        let (outdated, rects) = colliders.rebuild(&HashMap::new(), &broken, 20, 3);
+        assert_eq!(outdated.len(), 2);
+        assert!(!outdated.contains(&middle));
+        assert!(rects.iter().all(|rect| rect.right < 4 || rect.left >= 16));
+        spawn(&mut colliders, rects, &mut next);
+        assert_exact_cover(&colliders);
+    }
+
+    #[test]
+    fn unknown_removed_cells_change_nothing() {
+        let mut colliders = GroundColliders::default();
+        let added = HashMap::from([(Entity::from_raw(1), GridCoords::new(0, 0))]);
+        let (_, rects) = colliders.rebuild(&added, &[], 4, 4);
+        let mut next = 100;
+        spawn(&mut colliders, rects, &mut next);
+
+        let (outdated, rects) = colliders.rebuild(&HashMap::new(), &[Entity::from_raw(7)], 4, 4);
+        assert!(outdated.is_empty());
+        assert!(rects.is_empty());
+        assert_eq!(colliders.colliders.len(), 1);
+    }
+
+    #[test]
+    fn players_inside_a_platform_overlap_it() {
+        let platform = OneWayPlatformCollider {
+            half_width: 24.,
+            half_height: 4.,
+        };
+        let center = Vec2::new(0., 100.);
+        let half_size = Vec2::splat(16.);
+        let overlaps = |position| platform.overlaps(center, position, half_size, 1.);
+        // Standing on top, or sinking in less than the tolerance
+        assert!(!overlaps(Vec2::new(0., 120.)));
+        assert!(!overlaps(Vec2::new(0., 119.5)));
+        // Jumping up through it
+        assert!(overlaps(Vec2::new(0., 110.)));
+        assert!(overlaps(Vec2::new(30., 90.)));
+        // Below or beside it
+        assert!(!overlaps(Vec2::new(0., 70.)));
+        assert!(!overlaps(Vec2::new(45., 100.)));
+    }
+}
@@ -0,0 +1,458 @@
+//! Merging of grid cells into as few rectangles as possible.
+//!
+//! Spawning one collider per cell leads to bad performance,
+//! so connected cells are merged into rectangles before spawning colliders for them.
+
+use bevy_ecs_ldtk::prelude::*;
+use std::collections::{HashMap, HashSet};
+
+/// Represents a (potentially wide) ground that is 1 cell tall
+#[derive(Clone, Eq, PartialEq, Debug, Default, Hash)]
+struct Plate {
+    left: i32,
+    right: i32,
+}
+
+/// A simple rectangle type representing ground of any size.
+///
+/// All bounds are inclusive grid coordinates.
+#[derive(Clone, Copy, Eq, PartialEq, Debug, Default, Hash)]
+pub struct Rect {
+    pub left: i32,
+    pub right: i32,
+    pub top: i32,
+    pub bottom: i32,
+}
+
+impl Rect {
+    /// The number of columns covered by the rectangle.
+    pub fn width(&self) -> i32 {
+        self.right - self.left + 1
+    }
+
+    /// The number of rows covered by the rectangle.
+    pub fn height(&self) -> i32 {
+        self.top - self.bottom + 1
+    }
+
+    /// Returns whether the cell lies within the rectangle.
+    pub fn contains(&self, cell: &GridCoords) -> bool {
+        (self.left..=self.right).contains(&cell.x) && (self.bottom..=self.top).contains(&cell.y)
+    }
+
+    /// Returns whether the rectangles share at least one cell.
+    pub fn intersects(&self, other: &Rect) -> bool {
+        self.left <= other.right
+            && other.left <= self.right
+            && self.bottom <= other.top
+            && other.bottom <= self.top
+    }
+
+    /// Returns the rectangle grown by the given number of cells in every direction.
+    pub fn grow(&self, cells: i32) -> Rect {
+        Rect {
+            left: self.left - cells,
+            right: self.right + cells,
+            top: self.top + cells,
+            bottom: self.bottom - cells,
+        }
+    }
+
+    /// Returns the smallest rectangle containing both rectangles.
+    pub fn union(&self, other: &Rect) -> Rect {
+        Rect {
+            left: self.left.min(other.left),
+            right: self.right.max(other.right),
+            top: self.top.max(other.top),
+            bottom: self.bottom.min(other.bottom),
+        }
+    }
+
+    /// Returns the smallest rectangle containing all cells, or `None` if there are no cells.
+    pub fn bounding<'a>(cells: impl IntoIterator<Item = &'a GridCoords>) -> Option<Rect> {
+        cells.into_iter().fold(None, |rect, cell| {
+            Some(match rect {
+                None => Rect {
+                    left: cell.x,
+                    right: cell.x,
+                    top: cell.y,
+                    bottom: cell.y,
+                },
+                Some(rect) => Rect {
+                    left: rect.left.min(cell.x),
+                    right: rect.right.max(cell.x),
+                    top: rect.top.max(cell.y),
+                    bottom: rect.bottom.min(cell.y),
+                },
+            })
+        })
+    }
+
+    /// Iterates over all cells covered by the rectangle.
+    pub fn cells(&self) -> impl Iterator<Item = GridCoords> + '_ {
+        (self.bottom..=self.top)
+            .flat_map(move |y| (self.left..=self.right).map(move |x| GridCoords { x, y }))
+    }
+}
+
+/// Combines all connected cells per row to plates.
+fn plates(cells: &HashSet<GridCoords>, columns: i32, rows: i32) -> Vec<Vec<Plate>> {
+    let mut plate_stack: Vec<Vec<Plate>> = Vec::new();
+
+    for row in 0..rows {
+        let mut row_plates: Vec<Plate> = Vec::new();
+        let mut plate_start = None;
+
+        // + 1 to the width such the algorithm "terminates" after processing plates that touch the right edge
+        for col in 0..columns + 1 {
+            match (
+                plate_start,
+                col < columns && cells.contains(&GridCoords { x: col, y: row }),
+            ) {
+                (Some(s), false) => {
+                    row_plates.push(Plate {
+                        left: s,
+                        right: col - 1,
+                    });
+                    plate_start = None;
+                }
+                (None, true) => plate_start = Some(col),
+                _ => (),
+            }
+        }
+        plate_stack.push(row_plates);
+    }
+    plate_stack
+}
+
+/// Merges the cells of a `columns` x `rows` grid into rectangles.
+///
+///  1. Combine all connected cells per row to plates
+///  2. Combine plates of the same extent in consecutive rows into rectangles
+///
+/// Cells outside of the grid are ignored.
+/// The rectangles cover every cell exactly once.
+pub fn merge_cells(cells: &HashSet<GridCoords>, columns: i32, rows: i32) -> Vec<Rect> {
+    let mut plate_stack = plates(cells, columns, rows);
+
+    // combine "plates" into rectangles across multiple rows
+    let mut rect_builder: HashMap<Plate, Rect> = HashMap::new();
+    let mut prev_row_plates: Vec<Plate> = vec![];
+    let mut rects: Vec<Rect> = vec![];
+
+    // an extra empty row so the algorithm "finishes" the rects that touch the top edge
+    plate_stack.push(Vec::new());
+
+    for (row_index, current_row_plates) in plate_stack.into_iter().enumerate() {
+        for prev_row_plate in &prev_row_plates {
+            if !current_row_plates.contains(prev_row_plate) {
+                // remove the finished rect so that the same plate in the future starts a new rect
+                if let Some(rect) = rect_builder.remove(prev_row_plate) {
+                    rects.push(rect);
+                }
+            }
+        }
+        for current_row_plate in &current_row_plates {
+            rect_builder
+                .entry(current_row_plate.clone())
+                .and_modify(|rect| rect.top += 1)
+                .or_insert(Rect {
+                    bottom: row_index as i32,
+                    top: row_index as i32,
+                    left: current_row_plate.left,
+                    right: current_row_plate.right,
+                });
+        }
+        prev_row_plates = current_row_plates;
+    }
+    rects
+}
+
+/// Merges the cells of a `columns` x `rows` grid into rectangles that are 1 cell tall.
+///
+/// Cells outside of the grid are ignored.
+/// The rectangles cover every cell exactly once.
+pub fn merge_cells_per_row(cells: &HashSet<GridCoords>, columns: i32, rows: i32) -> Vec<Rect> {
+    plates(cells, columns, rows)
+        .into_iter()
+        .enumerate()
+        .flat_map(|(row_index, row_plates)| {
+            row_plates.into_iter().map(move |plate| Rect {
+                bottom: row_index as i32,
+                top: row_index as i32,
+                left: plate.left,
+                right: plate.right,
+            })
+        })
+        .collect()
+}
+
+/// Merges the cells of a `columns` x `rows` grid into rectangles greedily.
+///
+/// Starting at the bottom left, every cell that is not covered yet starts a new rectangle,
+/// which is grown to the right as far as possible and then upwards
+/// as long as the whole next row is available.
+///
+/// Unlike [`merge_cells`], the rectangles do not need to start with the same plate in every row,
+/// which results in fewer rectangles for irregular terrain.
+/// Growing upwards can split a wider plate above though, so when [`merge_cells`] needs fewer
+/// rectangles, its rectangles are returned instead.
+/// Cells outside of the grid are ignored.
+/// The rectangles cover every cell exactly once.
+pub fn merge_cells_greedy(cells: &HashSet<GridCoords>, columns: i32, rows: i32) -> Vec<Rect> {
+    let greedy = grow_rects(cells, columns, rows);
+    let plates = merge_cells(cells, columns, rows);
+    if plates.len() < greedy.len() {
+        plates
+    } else {
+        greedy
+    }
+}
+
+/// Grows a rectangle from every cell that is not covered yet, see [`merge_cells_greedy`].
+fn grow_rects(cells: &HashSet<GridCoords>, columns: i32, rows: i32) -> Vec<Rect> {
+    let mut covered: HashSet<GridCoords> = HashSet::new();
+    let is_free = |covered: &HashSet<GridCoords>, x: i32, y: i32| {
+        let cell = GridCoords { x, y };
+        cells.contains(&cell) && !covered.contains(&cell)
+    };
+    let mut rects: Vec<Rect> = vec![];
+
+    for row in 0..rows {
+        for col in 0..columns {
+            if !is_free(&covered, col, row) {
+                continue;
+            }
+
+            let mut right = col;
+            while right + 1 < columns && is_free(&covered, right + 1, row) {
+                right += 1;
+            }
+
+            let mut top = row;
+            while top + 1 < rows && (col..=right).all(|x| is_free(&covered, x, top + 1)) {
+                top += 1;
+            }
+
+            let rect = Rect {
+                left: col,
+                right,
+                top,
+                bottom: row,
+            };
+            covered.extend(rect.cells());
+            rects.push(rect);
+        }
+    }
+    rects
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::rngs::StdRng;
+    use rand::{Rng, SeedableRng};
+
+    type Merge = fn(&HashSet<GridCoords>, i32, i32) -> Vec<Rect>;
+
+    const MERGES: [(&str, Merge); 3] = [
+        ("merge_cells", merge_cells),
+        ("merge_cells_per_row", merge_cells_per_row),
+        ("merge_cells_greedy", merge_cells_greedy),
+    ];
+
+    /// Parses a grid drawn with `#` for cells, the last line being row 0.
+    fn grid(drawing: &str) -> (HashSet<GridCoords>, i32, i32) {
+        let lines: Vec<&str> = drawing.lines().map(str::trim).rev().collect();
+        let rows = lines.len() as i32;
+        let columns = lines.iter().map(|line| line.len()).max().unwrap_or(0) as i32;
+        let cells = lines
+            .iter()
+            .enumerate()
+            .flat_map(|(y, line)| {
+                line.chars()
+                    .enumerate()
+                    .filter(|(_, c)| *c == '#')
+                    .map(move |(x, _)| GridCoords::new(x as i32, y as i32))
+            })
+            .collect();
+        (cells, columns, rows)
+    }
+
+    /// Asserts that the rectangles cover every cell exactly once and nothing else.
+    fn assert_exact_cover(name: &str, cells: &HashSet<GridCoords>, rects: &[Rect]) {
+        let mut covered: HashSet<GridCoords> = HashSet::new();
+        for rect in rects {
+            assert!(rect.width() > 0 && rect.height() > 0, "{name}: {rect:?}");
+            for cell in rect.cells() {
+                assert!(cells.contains(&cell), "{name}: {cell:?} is not a cell");
+                assert!(covered.insert(cell), "{name}: {cell:?} is covered twice");
+            }
+        }
+        assert_eq!(&covered, cells, "{name}: not all cells are covered");
+    }
+
+    #[test]
+    fn empty_grid() {
+        for (name, merge) in MERGES {
+            assert!(merge(&HashSet::new(), 0, 0).is_empty(), "{name}");
+            assert!(merge(&HashSet::new(), 8, 5).is_empty(), "{name}");
+        }
+    }
+
+    #[test]
+    fn full_grid() {
+        let (cells, columns, rows) = grid(
+            "####
+             ####
+             ####",
+        );
+        let full = Rect {
+            left: 0,
+            right: 3,
+            top: 2,
+            bottom: 0,
+        };
+        assert_eq!(merge_cells(&cells, columns, rows), vec![full]);
+        assert_eq!(merge_cells_greedy(&cells, columns, rows), vec![full]);
+        assert_eq!(merge_cells_per_row(&cells, columns, rows).len(), 3);
+    }
+
+    #[test]
+    fn l_shape() {
+        let (cells, columns, rows) = grid(
+            "#..
+             #..
+             ###",
+        );
+        for (name, merge) in MERGES {
+            assert_exact_cover(name, &cells, &merge(&cells, columns, rows));
+        }
+        assert_eq!(merge_cells(&cells, columns, rows).len(), 2);
+        assert_eq!(merge_cells_greedy(&cells, columns, rows).len(), 2);
+        assert_eq!(merge_cells_per_row(&cells, columns, rows).len(), 3);
+    }
+
+    #[test]
+    fn hole() {
+        let (cells, columns, rows) = grid(
+            "###
+             #.#
+             ###",
+        );
+        for (name, merge) in MERGES {
+            let rects = merge(&cells, columns, rows);
+            assert_exact_cover(name, &cells, &rects);
+        }
+        assert_eq!(merge_cells(&cells, columns, rows).len(), 4);
+        assert_eq!(merge_cells_greedy(&cells, columns, rows).len(), 4);
+    }
+
+    #[test]
+    fn edge_touching_cells() {
+        let (cells, columns, rows) = grid(
+            "#...#
+             .....
+             #...#",
+        );
+        for (name, merge) in MERGES {
+            let rects = merge(&cells, columns, rows);
+            assert_exact_cover(name, &cells, &rects);
+            assert_eq!(rects.len(), 4, "{name}");
+        }
+    }
+
+    #[test]
+    fn cells_outside_of_the_grid_are_ignored() {
+        let cells = HashSet::from([
+            GridCoords::new(0, 0),
+            GridCoords::new(2, 0),
+            GridCoords::new(0, 2),
+            GridCoords::new(-1, 0),
+        ]);
+        let inside = HashSet::from([GridCoords::new(0, 0)]);
+        for (name, merge) in MERGES {
+            assert_exact_cover(name, &inside, &merge(&cells, 2, 2));
+        }
+    }
+
+    #[test]
+    fn greedy_merges_irregular_terrain_into_fewer_rectangles() {
+        let (cells, columns, rows) = grid(
+            "##.
+             ###
+             ##.",
+        );
+        assert_eq!(merge_cells(&cells, columns, rows).len(), 3);
+        assert_eq!(merge_cells_greedy(&cells, columns, rows).len(), 2);
+    }
+
+    #[test]
+    fn greedy_never_needs_more_rectangles() {
+        // Growing the middle cell upwards would split the row above it into three
+        let (cells, columns, rows) = grid(
+            "###
+             .#.",
+        );
+        assert_eq!(grow_rects(&cells, columns, rows).len(), 3);
+        assert_eq!(merge_cells(&cells, columns, rows).len(), 2);
+        let rects = merge_cells_greedy(&cells, columns, rows);
+        assert_exact_cover("merge_cells_greedy", &cells, &rects);
+        assert_eq!(rects.len(), 2);
+    }
+
+    #[test]
+    fn bounding_rectangle_intersects_its_cells() {
+        let cells = [GridCoords::new(2, 5), GridCoords::new(4, 1)];
+        let bounding = Rect::bounding(&cells).unwrap();
+        assert_eq!(
+            bounding,
+            Rect {
+                left: 2,
+                right: 4,
+                top: 5,
+                bottom: 1,
+            }
+        );
+        assert!(cells.iter().all(|cell| bounding.contains(cell)));
+        assert!(Rect::bounding(&[]).is_none());
+
+        let right_of = Rect {
+            left: 5,
+            right: 6,
+            top: 5,
+            bottom: 1,
+        };
+        assert!(!bounding.intersects(&right_of));
+        assert!(bounding.grow(1).intersects(&right_of));
+    }
+
+    #[test]
+    fn rectangles_exactly_cover_random_grids() {
+        for seed in 0..500 {
+            let mut rng = StdRng::seed_from_u64(seed);
+            let columns = rng.gen_range(1..16);
+            let rows = rng.gen_range(1..16);
+            let density = rng.gen_range(0.0..=1.0);
+            let cells: HashSet<GridCoords> = (0..rows)
+                .flat_map(|y| (0..columns).map(move |x| GridCoords::new(x, y)))
+                .filter(|_| rng.gen_bool(density))
+                .collect();
+
+            for (name, merge) in MERGES {
+                let rects = merge(&cells, columns, rows);
+                assert_exact_cover(&format!("{name} (seed {seed})"), &cells, &rects);
+            }
+            assert!(
+                merge_cells_per_row(&cells, columns, rows).len()
+                    >= merge_cells(&cells, columns, rows).len(),
+                "seed {seed}"
+            );
+            assert!(
+                merge_cells_greedy(&cells, columns, rows).len()
+                    <= merge_cells(&cells, columns, rows).len(),
+                "seed {seed}"
+            );
+        }
+    }
+}
@@ -1,10 +1,57 @@
 pub mod components;
+pub mod merge;
 pub mod systems;
 
 use crate::player;
-use crate::AppState;
+use crate::{AppState, GameState};
 
+use bevy::ecs::system::SystemParam;
 use bevy::prelude::*;
+use bevy_ecs_ldtk::prelude::*;
+
+/// The LDtk project with the levels, relative to the assets directory.
+pub const LDTK_FILE: &str = "tile-based-game.ldtk";
+/// The identifier of the LDtk layer containing the ground and the one-way platforms.
+pub const TERRAIN_LAYER: &str = "Terrain";
+/// The identifier of the LDtk layer containing the ladders, vines and water.
+pub const VOLUMES_LAYER: &str = "Volumes";
+
+/// Returns the area a spawned level covers in the world.
+pub fn level_bounds(level_transform: &GlobalTransform, level: &Level) -> Rect {
+    // A level's transform is located at its bottom left corner
+    let min = level_transform.translation().truncate();
+    Rect::from_corners(
+        min,
+        min + Vec2::new(level.px_wid as f32, level.px_hei as f32),
+    )
+}
+
+/// The spawned levels, with their data from the LDtk project.
+#[derive(SystemParam)]
+pub struct SpawnedLevels<'w, 's> {
+    level_query: Query<'w, 's, (&'static LevelIid, &'static GlobalTransform, &'static Parent)>,
+    ldtk_projects: Query<'w, 's, &'static Handle<LdtkProject>>,
+    ldtk_project_assets: Res<'w, Assets<LdtkProject>>,
+}
+
+impl SpawnedLevels<'_, '_> {
+    /// Returns the bounds and data of each spawned level.
+    pub fn iter(&self) -> impl Iterator<Item = (Rect, &Level)> {
+        self.level_query
+            .iter()
+            .filter_map(|(level_iid, level_transform, world)| {
+                let ldtk_handle = self.ldtk_projects.get(world.get()).ok()?;
+                let ldtk_project = self.ldtk_project_assets.get(ldtk_handle)?;
+                let level = ldtk_project.get_raw_level_by_iid(&level_iid.to_string())?;
+                Some((level_bounds(level_transform, level), level))
+            })
+    }
+
+    /// Returns the bounds and data of the level containing the point.
+    pub fn containing(&self, point: Vec2) -> Option<(Rect, &Level)> {
+        self.iter().find(|(bounds, _)| bounds.contains(point))
+    }
+}
 
 pub struct WorldPlugin;
 
@@ -17,7 +64,18 @@ impl Plugin for WorldPlugin {
             )
             .add_systems(
                 Update,
-                systems::add_ground_collider.run_if(in_state(AppState::InGame)),
+                (
+                    systems::add_ground_collider,
+                    systems::add_volume_colliders,
+                    systems::add_breakable_block_sprites,
+                    systems::update_level_selection,
+                )
+                    .run_if(in_state(AppState::InGame)),
+            )
+            .add_systems(
+                Update,
+                (systems::break_blocks, systems::update_debris)
+                    .run_if(in_state(GameState::Running)),
             )
             .add_systems(OnExit(AppState::InGame), systems::despawn);
     }
@@ -3,26 +3,94 @@ use bevy_ecs_ldtk::prelude::*;
 use bevy_rapier2d::prelude::*;
 use std::collections::{HashMap, HashSet};
 
-use crate::world::components::Ground;
+use crate::camera::components::CameraShake;
+use crate::collider::ONE_WAY_PLATFORM_GROUP;
+use crate::player::components::{Player, PlayerId};
+use crate::player::{PLAYER_HEIGHT, PLAYER_WIDTH};
+use crate::world::components::{
+    Breakable, Debris, Ground, GroundColliders, Ladder, LadderVolume, OneWayPlatform,
+    OneWayPlatformCollider, Water, WaterVolume,
+};
+use crate::world::{merge, SpawnedLevels, LDTK_FILE, TERRAIN_LAYER, VOLUMES_LAYER};
+
+const BLOCK_SIZE: f32 = 16.;
+/// The debris sprite sheets are strips of frames of this size
+const DEBRIS_FRAME_SIZE: f32 = 22.;
+const DEBRIS_SPEED: f32 = 80.;
+const DEBRIS_GRAVITY: f32 = -400.;
+const DEBRIS_LIFETIME: f32 = 1.;
 
 // http://www.mathforgameprogrammers.com/gdc2016/GDC2016_Pittman_Kyle_BuildingABetterJump.pdf
 pub fn spawn(mut commands: Commands, asset_server: Res<AssetServer>) {
+    commands.insert_resource(LevelSelection::index(0));
     commands.spawn(LdtkWorldBundle {
-        ldtk_handle: asset_server.load("tile-based-game.ldtk"),
+        ldtk_handle: asset_server.load(LDTK_FILE),
         ..Default::default()
     });
 }
 
-pub fn despawn(
-    mut commands: Commands,
-    enemy_entity_query: Query<Entity, With<Handle<LdtkProject>>>,
-) {
-    if let Ok(world_entity) = enemy_entity_query.get_single() {
+pub fn despawn(mut commands: Commands, world_query: Query<Entity, With<Handle<LdtkProject>>>) {
+    for world_entity in world_query.iter() {
         commands.entity(world_entity).despawn_recursive();
     }
 }
 
-/// Spawns colliders for the ground of a level.
+/// Returns the selection of the level containing the point, unless that level is selected already.
+fn select_level_containing<'a>(
+    levels: impl IntoIterator<Item = (Rect, &'a Level)>,
+    point: Vec2,
+    level_selection: &LevelSelection,
+) -> Option<LevelSelection> {
+    let (_, level) = levels
+        .into_iter()
+        .find(|(bounds, _)| bounds.contains(point))?;
+    let selection = LevelSelection::iid(level.iid.clone());
+    (selection != *level_selection).then_some(selection)
+}
+
+/// Selects the level the player is in.
+///
+/// The levels are spawned at their world location together with their neighbours,
+/// so when the player walks across a level border, the neighbours of the new level are loaded
+/// and the levels that are no longer neighbours are despawned together with their colliders.
+pub fn update_level_selection(
+    levels: SpawnedLevels,
+    player_query: Query<(&GlobalTransform, &PlayerId), With<Player>>,
+    mut level_selection: ResMut<LevelSelection>,
+) {
+    let Some((player, _)) = player_query.iter().find(|(_, id)| **id == PlayerId::One) else {
+        return;
+    };
+    let player = player.translation().truncate();
+    if let Some(selection) = select_level_containing(levels.iter(), player, &level_selection) {
+        *level_selection = selection;
+    }
+}
+
+type VolumeCell<'a> = (&'a GridCoords, &'a Parent, Has<OneWayPlatform>, Has<Water>);
+type AddedVolumeCells = Or<(Added<OneWayPlatform>, Added<Ladder>, Added<Water>)>;
+
+/// Returns the number of columns and rows and the grid size of the layer with the given identifier.
+fn layer_size(layers: &[LayerInstance], identifier: &str) -> Option<(i32, i32, i32)> {
+    layers
+        .iter()
+        .find(|layer| layer.identifier == identifier)
+        .map(|layer| (layer.c_wid, layer.c_hei, layer.grid_size))
+}
+
+/// Returns the size and the transform relative to the level of a collider covering the rectangle.
+fn collider_size_and_transform(rect: &merge::Rect, grid_size: i32) -> (f32, f32, Transform) {
+    let width = (rect.width() * grid_size) as f32;
+    let height = (rect.height() * grid_size) as f32;
+    let transform = Transform::from_xyz(
+        ((rect.left + rect.right + 1) * grid_size) as f32 / 2.,
+        ((rect.bottom + rect.top + 1) * grid_size) as f32 / 2.,
+        0.,
+    );
+    (width, height, transform)
+}
+
+/// Spawns colliders for the ground of a level and keeps them up to date.
 ///
 /// One can simply insert a ColliderBundle into the GroundBundle,
 /// but this spawns a new collider for EVERY ground tile.
@@ -35,57 +103,169 @@ pub fn despawn(
 /// These grid coordinates are assigned to the respective level, which is obtained with the
 /// parent_query.
 ///
-/// For each level obtained with the level_query we
-///  1. Combine all connected ground cells per row to plates
-///  2. Combine all plates per row into rectangles
-///  3. Spawn the collider for each rectangle
+/// Ground cells can be added and removed at runtime, e.g. when a breakable block is destroyed.
+/// The level's [`GroundColliders`] remembers the cells and the colliders spawned for them,
+/// so for each level obtained with the level_query we
+///  1. Find the regions around the added and removed cells
+///  2. Despawn the colliders that intersect these regions
+///  3. Merge the remaining cells of these colliders and the added cells into rectangles,
+///     see [`merge::merge_cells_greedy`]
+///  4. Spawn the collider for each rectangle
+///
+/// When a level spawns, all of its cells are added at once, so all colliders are built.
 pub fn add_ground_collider(
     mut commands: Commands,
-    ground_query: Query<(&GridCoords, &Parent), Added<Ground>>,
+    ground_query: Query<(Entity, &GridCoords, &Parent), Added<Ground>>,
+    mut removed_ground: RemovedComponents<Ground>,
     parent_query: Query<&Parent, Without<Ground>>,
-    level_query: Query<(Entity, &LevelIid)>,
+    mut level_query: Query<(Entity, &LevelIid, &Parent, Option<&mut GroundColliders>)>,
     ldtk_projects: Query<&Handle<LdtkProject>>,
     ldtk_project_assets: Res<Assets<LdtkProject>>,
 ) {
-    /// Represents a (potentially wide) ground that is 1 cell tall
-    /// Used to spawn ground colliders
-    #[derive(Clone, Eq, PartialEq, Debug, Default, Hash)]
-    struct Plate {
-        left: i32,
-        right: i32,
+    // Stores the grid coordinates of the added Ground entities for the respective level
+    let mut added_for_level: HashMap<Entity, HashMap<Entity, GridCoords>> = HashMap::new();
+
+    // For every grid coordinate belonging to a `Ground` entity,
+    // we obtain the level of type `Entity` (ground.parent = layer, ground.parent.parent = level),
+    // and assign the grid coordinate to the respective level it belongs to.
+    ground_query
+        .iter()
+        .for_each(|(cell_entity, &grid_coordinates, parent)| {
+            // An intgrid tile's direct parent will be a layer entity, not the level entity
+            // To get the level entity, you need the tile's grandparent, we obtain using parent_query
+            if let Ok(level) = parent_query.get(parent.get()) {
+                added_for_level
+                    .entry(level.get())
+                    .or_default()
+                    .insert(cell_entity, grid_coordinates);
+            }
+        });
+    let removed: Vec<Entity> = removed_ground.read().collect();
+
+    if added_for_level.is_empty() && removed.is_empty() {
+        return;
     }
 
-    /// A simple rectangle type representing ground of any size
-    #[derive(Debug)]
-    struct Rect {
-        left: i32,
-        right: i32,
-        top: i32,
-        bottom: i32,
+    level_query
+        .iter_mut()
+        .for_each(|(level_entity, level_iid, world, ground_colliders)| {
+            let mut new_ground_colliders = None;
+            let ground_colliders = match ground_colliders {
+                Some(ground_colliders) => ground_colliders.into_inner(),
+                None => new_ground_colliders.insert(GroundColliders::default()),
+            };
+
+            let added = added_for_level.remove(&level_entity).unwrap_or_default();
+            let removed_here = removed
+                .iter()
+                .any(|cell_entity| ground_colliders.cells.contains_key(cell_entity));
+            if added.is_empty() && !removed_here {
+                return;
+            }
+
+            // A level's parent is the world it belongs to
+            let ldtk_project = ldtk_projects
+                .get(world.get())
+                .ok()
+                .and_then(|ldtk_handle| ldtk_project_assets.get(ldtk_handle))
+                .expect("Project should be loaded if level has spawned");
+
+            let level = ldtk_project
+                .as_standalone()
+                .get_loaded_level_by_iid(&level_iid.to_string())
+                .expect("Spawned level should exist in LDtk project");
+
+            let Some((columns, rows, grid_size)) =
+                layer_size(level.layer_instances(), TERRAIN_LAYER)
+            else {
+                warn!("Level {level_iid} has no {TERRAIN_LAYER} layer");
+                return;
+            };
+
+            let (outdated, rects) = ground_colliders.rebuild(&added, &removed, columns, rows);
+            for collider_entity in outdated {
+                commands.entity(collider_entity).despawn_recursive();
+            }
+
+            commands.entity(level_entity).with_children(|level| {
+                // Spawn colliders for every rectangle..
+                // Making the collider a child of the level serves two purposes:
+                // 1. Adjusts the transforms to be relative to the level for free
+                // 2. the colliders will be despawned automatically when levels unload
+                for ground_rect in rects {
+                    let (width, height, transform) =
+                        collider_size_and_transform(&ground_rect, grid_size);
+                    let collider_entity = level
+                        .spawn((
+                            Collider::cuboid(width / 2., height / 2.),
+                            RigidBody::Fixed,
+                            Friction::new(1.0),
+                            transform,
+                            GlobalTransform::default(),
+                        ))
+                        .id();
+                    ground_colliders
+                        .colliders
+                        .push((collider_entity, ground_rect));
+                }
+            });
+
+            if let Some(new_ground_colliders) = new_ground_colliders {
+                commands.entity(level_entity).insert(new_ground_colliders);
+            }
+        });
+}
+
+/// Spawns colliders for the one-way platforms and the volumes of a level.
+///
+/// One-way platform cells are merged only per row,
+/// because stacking them into rectangles would hide the surface of the lower platforms.
+///
+/// Ladder and water cells are merged into rectangles the same way as the ground,
+/// but spawn sensor colliders the player can move through.
+pub fn add_volume_colliders(
+    mut commands: Commands,
+    cell_query: Query<VolumeCell, AddedVolumeCells>,
+    parent_query: Query<&Parent, Without<Ground>>,
+    level_query: Query<(Entity, &LevelIid, &Parent)>,
+    ldtk_projects: Query<&Handle<LdtkProject>>,
+    ldtk_project_assets: Res<Assets<LdtkProject>>,
+) {
+    /// The grid coordinates of the cells of a level, grouped by what they were marked with
+    #[derive(Debug, Default)]
+    struct LevelCells {
+        platforms: HashSet<GridCoords>,
+        ladders: HashSet<GridCoords>,
+        water: HashSet<GridCoords>,
     }
 
-    // Stores the grid coordinates belonging to Ground entities for the respective level
-    let mut ground_coordinates_for_level: HashMap<Entity, HashSet<GridCoords>> = HashMap::new();
+    // Stores the grid coordinates of the marked cells for the respective level
+    let mut cells_for_level: HashMap<Entity, LevelCells> = HashMap::new();
 
-    // For every grid coordinate belonging to a `Ground` entity,
-    // we obtain the level of type `Entity` (ground.parent = layer, ground.parent.parent = level),
-    // and assign the grid coordinate to the respective level it belongs to.
-    ground_query.iter().for_each(|(&grid_coordinates, parent)| {
-        // An intgrid tile's direct parent will be a layer entity, not the level entity
-        // To get the level entity, you need the tile's grandparent, we obtain using parent_query
-        if let Ok(level) = parent_query.get(parent.get()) {
-            ground_coordinates_for_level
-                .entry(level.get())
-                .or_default()
-                .insert(grid_coordinates);
-        }
-    });
+    cell_query
+        .iter()
+        .for_each(|(&grid_coordinates, parent, is_platform, is_water)| {
+            if let Ok(level) = parent_query.get(parent.get()) {
+                let cells = cells_for_level.entry(level.get()).or_default();
+                if is_platform {
+                    cells.platforms.insert(grid_coordinates);
+                } else if is_water {
+                    cells.water.insert(grid_coordinates);
+                } else {
+                    cells.ladders.insert(grid_coordinates);
+                }
+            }
+        });
 
-    if !ground_query.is_empty() {
-        level_query.iter().for_each(|(level_entity, level_iid)| {
-            if let Some(ground_coordinates) = ground_coordinates_for_level.get(&level_entity) {
-                let ldtk_project = ldtk_project_assets
-                    .get(ldtk_projects.single())
+    level_query
+        .iter()
+        .for_each(|(level_entity, level_iid, world)| {
+            if let Some(cells) = cells_for_level.get(&level_entity) {
+                // A level's parent is the world it belongs to
+                let ldtk_project = ldtk_projects
+                    .get(world.get())
+                    .ok()
+                    .and_then(|ldtk_handle| ldtk_project_assets.get(ldtk_handle))
                     .expect("Project should be loaded if level has spawned");
 
                 let level = ldtk_project
@@ -93,98 +273,204 @@ pub fn add_ground_collider(
                     .get_loaded_level_by_iid(&level_iid.to_string())
                     .expect("Spawned level should exist in LDtk project");
 
-                // Get the levels width and height
-                let LayerInstance {
-                    c_wid: columns,
-                    c_hei: rows,
-                    grid_size,
-                    ..
-                } = level.layer_instances()[0];
-
-                // combine ground grid coordinates into flat "plates" in each individual row
-                let mut plate_stack: Vec<Vec<Plate>> = Vec::new();
-
-                for row in 0..rows {
-                    let mut row_plates: Vec<Plate> = Vec::new();
-                    let mut plate_start = None;
-
-                    // + 1 to the width such the algorithm "terminates" after processing plates that touch the right edge
-                    for col in 0..columns + 1 {
-                        match (
-                            plate_start,
-                            ground_coordinates.contains(&GridCoords { x: col, y: row }),
-                        ) {
-                            (Some(s), false) => {
-                                row_plates.push(Plate {
-                                    left: s,
-                                    right: col - 1,
-                                });
-                                plate_start = None;
-                            }
-                            (None, true) => plate_start = Some(col),
-                            _ => (),
-                        }
-                    }
-                    plate_stack.push(row_plates);
+                let layers = level.layer_instances();
+                let mut platform_rects = vec![];
+                if let Some((columns, rows, grid_size)) = layer_size(layers, TERRAIN_LAYER) {
+                    platform_rects = merge::merge_cells_per_row(&cells.platforms, columns, rows)
+                        .into_iter()
+                        .map(|rect| collider_size_and_transform(&rect, grid_size))
+                        .collect();
                 }
-
-                // combine "plates" into rectangles across multiple rows
-                let mut rect_builder: HashMap<Plate, Rect> = HashMap::new();
-                let mut prev_row_plates: Vec<Plate> = vec![];
-                let mut ground_rects: Vec<Rect> = vec![];
-
-                // an extra empty row so the algorithm "finishes" the rects that touch the top edge
-                plate_stack.push(Vec::new());
-
-                for (row_index, current_row_plates) in plate_stack.into_iter().enumerate() {
-                    for prev_row_plate in &prev_row_plates {
-                        if !current_row_plates.contains(prev_row_plate) {
-                            // remove the finished rect so that the same plate in the future starts a new rect
-                            if let Some(rect) = rect_builder.remove(prev_row_plate) {
-                                ground_rects.push(rect);
-                            }
-                        }
-                    }
-                    for current_row_plate in &current_row_plates {
-                        rect_builder
-                            .entry(current_row_plate.clone())
-                            .and_modify(|rect| rect.top += 1)
-                            .or_insert(Rect {
-                                bottom: row_index as i32,
-                                top: row_index as i32,
-                                left: current_row_plate.left,
-                                right: current_row_plate.right,
-                            });
-                    }
-                    prev_row_plates = current_row_plates;
+                let mut ladder_rects = vec![];
+                let mut water_rects = vec![];
+                if let Some((columns, rows, grid_size)) = layer_size(layers, VOLUMES_LAYER) {
+                    ladder_rects = merge::merge_cells_greedy(&cells.ladders, columns, rows)
+                        .into_iter()
+                        .map(|rect| collider_size_and_transform(&rect, grid_size))
+                        .collect();
+                    water_rects = merge::merge_cells_greedy(&cells.water, columns, rows)
+                        .into_iter()
+                        .map(|rect| collider_size_and_transform(&rect, grid_size))
+                        .collect();
                 }
 
                 commands.entity(level_entity).with_children(|level| {
-                    // Spawn colliders for every rectangle..
-                    // Making the collider a child of the level serves two purposes:
-                    // 1. Adjusts the transforms to be relative to the level for free
-                    // 2. the colliders will be despawned automatically when levels unload
-                    for ground_rect in ground_rects {
-                        let width = ((ground_rect.right - ground_rect.left + 1) * grid_size) as f32;
-                        let height =
-                            ((ground_rect.top - ground_rect.bottom + 1) * grid_size) as f32;
-
+                    for (width, height, transform) in platform_rects {
                         level.spawn((
                             Collider::cuboid(width / 2., height / 2.),
                             RigidBody::Fixed,
                             Friction::new(1.0),
-                            Transform::from_xyz(
-                                ((ground_rect.left + ground_rect.right + 1) * grid_size) as f32
-                                    / 2.,
-                                ((ground_rect.bottom + ground_rect.top + 1) * grid_size) as f32
-                                    / 2.,
-                                0.,
-                            ),
+                            CollisionGroups::new(ONE_WAY_PLATFORM_GROUP, Group::ALL),
+                            OneWayPlatformCollider {
+                                half_width: width / 2.,
+                                half_height: height / 2.,
+                            },
+                            transform,
+                            GlobalTransform::default(),
+                        ));
+                    }
+                    // The player is kinematic and the volumes are fixed,
+                    // so the intersections between them have to be enabled explicitly.
+                    for (width, height, transform) in ladder_rects {
+                        level.spawn((
+                            Collider::cuboid(width / 2., height / 2.),
+                            Sensor,
+                            ActiveCollisionTypes::default()
+                                | ActiveCollisionTypes::KINEMATIC_STATIC,
+                            LadderVolume,
+                            transform,
+                            GlobalTransform::default(),
+                        ));
+                    }
+                    for (width, height, transform) in water_rects {
+                        level.spawn((
+                            Collider::cuboid(width / 2., height / 2.),
+                            Sensor,
+                            ActiveCollisionTypes::default()
+                                | ActiveCollisionTypes::KINEMATIC_STATIC,
+                            WaterVolume,
+                            transform,
                             GlobalTransform::default(),
                         ));
                     }
                 });
             }
         });
+}
+
+/// Gives the breakable blocks their sprite, the terrain's auto tiles do not cover them.
+pub fn add_breakable_block_sprites(
+    mut commands: Commands,
+    block_query: Query<Entity, Added<Breakable>>,
+    asset_server: Res<AssetServer>,
+) {
+    for block in block_query.iter() {
+        commands.entity(block).insert((
+            Sprite {
+                custom_size: Some(Vec2::splat(BLOCK_SIZE)),
+                ..default()
+            },
+            asset_server.load::<Image>("Traps/Blocks/Idle.png"),
+        ));
+    }
+}
+
+/// Breaks the block the player bumps their head into.
+///
+/// The block's cell is despawned, which rebuilds the ground colliders around it,
+/// and its two halves fly off as debris.
+pub fn break_blocks(
+    mut commands: Commands,
+    player_query: Query<(&KinematicCharacterControllerOutput, &GlobalTransform), With<Player>>,
+    block_query: Query<(Entity, &Transform, &GlobalTransform, &Parent), With<Breakable>>,
+    mut shake_events: EventWriter<CameraShake>,
+    asset_server: Res<AssetServer>,
+) {
+    for (output, player) in player_query.iter() {
+        // The player moved up, but less than desired, so they hit something with their head
+        let bumped_head = output.desired_translation.y > 0.
+            && output.effective_translation.y < output.desired_translation.y;
+        if !bumped_head {
+            continue;
+        }
+
+        let player_top = player.translation().y + PLAYER_HEIGHT / 2.;
+        let hit_block = block_query
+            .iter()
+            .filter(|(_, _, block, _)| {
+                let block_bottom = block.translation().y - BLOCK_SIZE / 2.;
+                (block_bottom - player_top).abs() < BLOCK_SIZE / 2.
+                    && (block.translation().x - player.translation().x).abs()
+                        < (BLOCK_SIZE + PLAYER_WIDTH) / 2.
+            })
+            .min_by(|(_, _, a, _), (_, _, b, _)| {
+                let distance = |block: &GlobalTransform| {
+                    (block.translation().x - player.translation().x).abs()
+                };
+                distance(a).total_cmp(&distance(b))
+            });
+
+        if let Some((block_entity, transform, _, layer)) = hit_block {
+            shake_events.send(CameraShake { trauma: 0.3 });
+            commands.entity(block_entity).despawn_recursive();
+            // The debris is a child of the layer, so it is despawned with the level
+            commands.entity(layer.get()).with_children(|layer| {
+                for (part, direction) in [("Part 1", -1.), ("Part 2", 1.)] {
+                    layer.spawn((
+                        SpriteBundle {
+                            texture: asset_server.load(format!("Traps/Blocks/{part} (22x22).png")),
+                            sprite: Sprite {
+                                custom_size: Some(Vec2::splat(BLOCK_SIZE)),
+                                // Only the first frame of the strip
+                                rect: Some(Rect::new(0., 0., DEBRIS_FRAME_SIZE, DEBRIS_FRAME_SIZE)),
+                                ..default()
+                            },
+                            transform: *transform,
+                            ..default()
+                        },
+                        Debris {
+                            velocity: Vec2::new(direction * DEBRIS_SPEED, DEBRIS_SPEED),
+                            lifetime: Timer::from_seconds(DEBRIS_LIFETIME, TimerMode::Once),
+                        },
+                    ));
+                }
+            });
+        }
+    }
+}
+
+/// Lets the debris of broken blocks fall and despawns it after its lifetime.
+pub fn update_debris(
+    mut commands: Commands,
+    mut debris_query: Query<(Entity, &mut Debris, &mut Transform)>,
+    time: Res<Time>,
+) {
+    for (entity, mut debris, mut transform) in debris_query.iter_mut() {
+        debris.velocity.y += DEBRIS_GRAVITY * time.delta_seconds();
+        transform.translation += (debris.velocity * time.delta_seconds()).extend(0.);
+        transform.rotate_z(debris.velocity.x.signum() * -10. * time.delta_seconds());
+        if debris.lifetime.tick(time.delta()).finished() {
+            commands.entity(entity).despawn_recursive();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn level(iid: &str, world_x: i32) -> (Rect, Level) {
+        let level = Level {
+            iid: iid.to_string(),
+            px_wid: 100,
+            px_hei: 100,
+            world_x,
+            ..default()
+        };
+        let min = Vec2::new(world_x as f32, 0.);
+        (Rect::from_corners(min, min + Vec2::splat(100.)), level)
+    }
+
+    #[test]
+    fn selects_the_neighbouring_level_the_player_walks_into() {
+        let levels = [level("left", 0), level("right", 100)];
+        let levels = || levels.iter().map(|(bounds, level)| (*bounds, level));
+        let start = LevelSelection::index(0);
+
+        let right = select_level_containing(levels(), Vec2::new(150., 50.), &start);
+        assert_eq!(right, Some(LevelSelection::iid("right")));
+        let right = right.unwrap();
+        assert_eq!(
+            select_level_containing(levels(), Vec2::new(160., 50.), &right),
+            None
+        );
+        assert_eq!(
+            select_level_containing(levels(), Vec2::new(50., 50.), &right),
+            Some(LevelSelection::iid("left"))
+        );
+        assert_eq!(
+            select_level_containing(levels(), Vec2::new(250., 50.), &right),
+            None
+        );
     }
 }